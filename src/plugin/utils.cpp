@@ -23,6 +23,7 @@
 #include <boost/process/posix.hpp>
 #include <boost/process/search_path.hpp>
 #include <boost/process/system.hpp>
+#include <fstream>
 #include <sstream>
 
 // XXX: With Boost 1.75 at least, this header cannot be included in alphabetical
@@ -47,6 +48,7 @@ fs::path find_plugin_library(const fs::path& this_plugin_path,
 fs::path normalize_plugin_path(const fs::path& windows_library_path,
                                PluginType plugin_type);
 std::variant<OverridenWinePrefix, fs::path, DefaultWinePrefix> find_wine_prefix(
+    const fs::path& native_library_path,
     fs::path windows_plugin_path);
 
 PluginInfo::PluginInfo(PluginType plugin_type, bool prefer_32bit_vst3)
@@ -62,7 +64,7 @@ PluginInfo::PluginInfo(PluginType plugin_type, bool prefer_32bit_vst3)
       plugin_arch(find_dll_architecture(windows_library_path)),
       windows_plugin_path(
           normalize_plugin_path(windows_library_path, plugin_type)),
-      wine_prefix(find_wine_prefix(windows_plugin_path)) {}
+      wine_prefix(find_wine_prefix(native_library_path, windows_plugin_path)) {}
 
 bp::environment PluginInfo::create_host_env() const {
     bp::environment env = boost::this_process::environment();
@@ -253,12 +255,26 @@ fs::path normalize_plugin_path(const fs::path& windows_library_path,
 }
 
 std::variant<OverridenWinePrefix, fs::path, DefaultWinePrefix> find_wine_prefix(
+    const fs::path& native_library_path,
     fs::path windows_plugin_path) {
     bp::environment env = boost::this_process::environment();
     if (!env["WINEPREFIX"].empty()) {
         return OverridenWinePrefix{env["WINEPREFIX"].to_string()};
     }
 
+    // yabridgectl lets you associate a plugin location with a Wine prefix. In
+    // that case it will write the path to that prefix to a `.wineprefix` file
+    // next to this library.
+    const fs::path prefix_file_path =
+        fs::path(native_library_path).replace_extension(".wineprefix");
+    if (fs::exists(prefix_file_path)) {
+        std::ifstream prefix_file(prefix_file_path.string());
+        std::string prefix;
+        if (std::getline(prefix_file, prefix) && !prefix.empty()) {
+            return fs::path(prefix);
+        }
+    }
+
     std::optional<fs::path> dosdevices_dir = find_dominating_file(
         "dosdevices", windows_plugin_path, fs::is_directory);
     if (!dosdevices_dir) {
@@ -137,11 +137,13 @@ struct PluginInfo {
     /**
      * The Wine prefix to use for hosting `windows_plugin_path`. If the
      * `WINEPREFIX` environment variable is set, then that will be used as an
-     * override. Otherwise, we'll try to find the Wine prefix
-     * `windows_plugin_path` is located in. The detection works by looking for a
-     * directory containing a directory called `dosdevices`. If the plugin is
-     * not inside of a Wine prefix, this will be left empty, and the default
-     * prefix will be used instead.
+     * override. If yabridgectl wrote a `.wineprefix` file containing the path
+     * to a Wine prefix next to `native_library_path`, then that prefix will be
+     * used. Otherwise, we'll try to find the Wine prefix `windows_plugin_path`
+     * is located in. The detection works by looking for a directory
+     * containing a directory called `dosdevices`. If the plugin is not inside
+     * of a Wine prefix, this will be left empty, and the default prefix will
+     * be used instead.
      */
     const std::
         variant<OverridenWinePrefix, boost::filesystem::path, DefaultWinePrefix>
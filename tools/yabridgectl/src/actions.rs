@@ -16,56 +16,536 @@
 
 //! Handlers for the subcommands, just to keep `main.rs` clean.
 
-use anyhow::{Context, Result};
-use colored::Colorize;
-use std::collections::{HashMap, HashSet};
+use anyhow::{anyhow, Context, Result};
+use colored::{ColoredString, Colorize};
+use filetime::FileTime;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
 use std::fs;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::process;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
 
-use crate::config::{yabridge_vst3_home, Config, InstallationMethod, YabridgeFiles};
-use crate::files::{self, NativeFile, Plugin, Vst2Plugin};
-use crate::utils::{self, get_file_type};
-use crate::utils::{verify_path_setup, verify_wine_setup};
+use crate::config::{
+    vst3_presets_home, yabridgectl_directories, Config, InstallationMethod, Manifest, Pe32Cache,
+    Pe32Parser, YabridgeFiles, WINE_PREFIX_FILE_EXTENSION, YABRIDGE_HOST_32_EXE_NAME,
+    YABRIDGE_HOST_EXE_NAME,
+};
+use crate::files::{
+    self, LibArchitecture, NativeFile, Plugin, PluginFormat, PluginSymlink, SearchResults,
+    Vst2Plugin, Vst3Module,
+};
+use crate::utils;
+use crate::utils::{
+    verify_path_setup, verify_wine_prefix, verify_wine_prefix_architectures, verify_wine_setup,
+};
 
 pub mod blacklist;
+pub mod keep;
+
+/// How often `yabridgectl status --watch` should refresh the status.
+const STATUS_WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long the plugin locations should stay unchanged before `yabridgectl sync --watch` syncs
+/// again. Installers can take a while to write all of their files.
+const SYNC_WATCH_DELAY: Duration = Duration::from_secs(5);
+
+/// How often `yabridgectl sync --watch` checks whether Ctrl+C has been pressed while waiting for
+/// changes.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The plugin install locations relative to a Wine prefix that `yabridgectl add-prefix` will add if
+/// they exist. These are the VST3 directories and the most common VST2 directories.
+const WINE_PREFIX_PLUGIN_DIRS: &[&str] = &[
+    "drive_c/Program Files/Common Files/VST3",
+    "drive_c/Program Files (x86)/Common Files/VST3",
+    "drive_c/Program Files/Steinberg/VstPlugins",
+    "drive_c/Program Files/Steinberg/VSTPlugins",
+    "drive_c/Program Files (x86)/Steinberg/VstPlugins",
+    "drive_c/Program Files (x86)/Steinberg/VSTPlugins",
+    "drive_c/Program Files/VstPlugins",
+    "drive_c/Program Files/VSTPlugins",
+    "drive_c/Program Files (x86)/VstPlugins",
+    "drive_c/Program Files (x86)/VSTPlugins",
+];
+
+/// The exit code used by `yabridgectl status --fail-if-no-yabridge` when yabridge's files could not
+/// be found. This is different from the exit code used for other errors.
+const NO_YABRIDGE_EXIT_CODE: i32 = 2;
+
+/// Errors that should make yabridgectl exit with a specific exit code instead of the usual exit code
+/// for errors. Actions return these like any other error, and `main()` maps them to their exit
+/// codes after everything has been cleaned up.
+#[derive(Debug)]
+pub enum ExitCodeError {
+    /// `yabridgectl status --fail-if-no-yabridge` could not find yabridge's files. The status
+    /// already explains why, so this doesn't print anything.
+    NoYabridge,
+    /// `yabridgectl sync --strict` printed warnings during the post-installation setup checks.
+    VerificationFailed,
+}
+
+impl ExitCodeError {
+    /// The exit code yabridgectl should exit with for this error.
+    pub fn exit_code(&self) -> i32 {
+        match &self {
+            ExitCodeError::NoYabridge => NO_YABRIDGE_EXIT_CODE,
+            ExitCodeError::VerificationFailed => VERIFICATION_FAILED_EXIT_CODE,
+        }
+    }
+}
+
+impl Display for ExitCodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            ExitCodeError::NoYabridge => write!(f, "Could not find yabridge's files"),
+            ExitCodeError::VerificationFailed => write!(f, "The setup checks failed"),
+        }
+    }
+}
+
+impl std::error::Error for ExitCodeError {}
+
+/// The exit code used by `yabridgectl sync --strict` when one of the post-installation setup checks
+/// printed a warning.
+const VERIFICATION_FAILED_EXIT_CODE: i32 = 3;
 
 /// Add a direcotry to the plugin locations. Duplicates get ignord because we're using ordered sets.
-pub fn add_directory(config: &mut Config, path: PathBuf) -> Result<()> {
+/// If `wine_prefix` is set, then the plugins in this directory will be run in that Wine prefix. The
+/// user is asked for confirmation first if the directory overlaps with an existing plugin directory.
+pub fn add_directory(
+    config: &mut Config,
+    path: PathBuf,
+    wine_prefix: Option<PathBuf>,
+) -> Result<()> {
+    if !confirm_overlapping_directory(config, &path) {
+        return Ok(());
+    }
+
+    set_wine_prefix(config, &path, wine_prefix)?;
     config.plugin_dirs.insert(path);
     config.write()
 }
 
-/// Remove a direcotry to the plugin locations. The path is assumed to be part of
-/// `config.plugin_dirs`, otherwise this is silently ignored.
-pub fn remove_directory(config: &mut Config, path: &Path) -> Result<()> {
-    // We've already verified that this path is in `config.plugin_dirs`
-    config.plugin_dirs.remove(path);
+/// Check whether `path` is nested within or contains one of the existing plugin directories, since
+/// that would cause the overlapping part to be indexed twice during every sync. If it does, the
+/// user will be warned and asked whether the directory should be added anyway. When STDIN is not a
+/// terminal we can't ask, so the directory is still added after printing the warning. Returns
+/// `true` if the directory should be added.
+fn confirm_overlapping_directory(config: &Config, path: &Path) -> bool {
+    let overlapping_dirs: Vec<&PathBuf> = config
+        .plugin_dirs
+        .iter()
+        .filter(|directory| {
+            directory.as_path() != path
+                && (path.starts_with(directory) || directory.starts_with(path))
+        })
+        .collect();
+    if overlapping_dirs.is_empty() {
+        return true;
+    }
+
+    eprintln!(
+        "{}",
+        utils::wrap(&format!(
+            "Warning: '{}' overlaps with the following plugin locations. The plugins in the \
+             overlapping directories will be searched for twice during every sync, so adding \
+             only one of these directories is usually enough.",
+            utils::display_path(path)
+        ))
+    );
+    for directory in overlapping_dirs {
+        if path.starts_with(directory) {
+            eprintln!(
+                "- {} (contains this directory)",
+                utils::display_path(directory)
+            );
+        } else {
+            eprintln!(
+                "- {} (inside of this directory)",
+                utils::display_path(directory)
+            );
+        }
+    }
+
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+        return true;
+    }
+
+    matches!(
+        promptly::prompt_default("\nWould you like to add it anyway?", false),
+        Ok(true)
+    )
+}
+
+/// Search a directory for plugins and ask the user which parts of it should be added to the plugin
+/// locations. The found plugins are grouped by the immediate subdirectory of `path` they're in, and
+/// every selected subdirectory is added as its own plugin location. Plugins stored directly in
+/// `path` are offered as individual plugin files instead. If `wine_prefix` is set, then all added
+/// locations will be associated with that Wine prefix. Selected directories that overlap with an
+/// existing plugin directory need to be confirmed separately, like in `add_directory()`.
+pub fn add_directory_interactive(
+    config: &mut Config,
+    path: &Path,
+    wine_prefix: Option<PathBuf>,
+) -> Result<()> {
+    let (blacklist, blacklist_patterns) = config.blacklist_filters();
+    let cache = Mutex::new(Pe32Cache::read());
+    let results = {
+        let progress = utils::Progress::on_stderr();
+        files::index(path, &blacklist, &blacklist_patterns, Some(&progress)).search(
+            config.max_parse_errors,
+            config.parser,
+            &config.vst3_home(),
+            Some(&cache),
+            Some(&progress),
+        )?
+    };
+    cache.into_inner().unwrap().write_or_warn();
+
+    // The number of VST2 and VST3 plugins in each of `path`'s subdirectories
+    let mut groups: BTreeMap<PathBuf, (usize, usize)> = BTreeMap::new();
+    for plugin in &results.plugins {
+        let plugin_path = match plugin {
+            Plugin::Vst2(Vst2Plugin { path, .. }) => path.as_path(),
+            Plugin::Vst3(module) => module.original_path(),
+        };
+        let group = match plugin_path
+            .strip_prefix(path)
+            .ok()
+            .and_then(|suffix| suffix.components().next())
+        {
+            Some(component) => path.join(component),
+            None => continue,
+        };
+
+        let (num_vst2, num_vst3) = groups.entry(group).or_default();
+        match plugin {
+            Plugin::Vst2(_) => *num_vst2 += 1,
+            Plugin::Vst3(_) => *num_vst3 += 1,
+        }
+    }
+    if groups.is_empty() {
+        return Err(anyhow!(
+            "Could not find any plugins in '{}'",
+            utils::display_path(path)
+        ));
+    }
+
+    let groups: Vec<(PathBuf, (usize, usize))> = groups.into_iter().collect();
+    println!(
+        "Found {} plugins in '{}':\n",
+        results.plugins.len(),
+        utils::display_path(path)
+    );
+    for (i, (group, (num_vst2, num_vst3))) in groups.iter().enumerate() {
+        println!(
+            "{:>3}) {}{} ({} VST2, {} VST3)",
+            i + 1,
+            group.strip_prefix(path).unwrap_or(group).display(),
+            if group.is_dir() { "/" } else { "" },
+            num_vst2,
+            num_vst3
+        );
+    }
+
+    let answer = promptly::prompt_opt::<String, &str>(
+        "\nWhich of these should be added? Enter their numbers separated by spaces, or 'all' to add \
+         everything. Entering nothing will not add anything",
+    )?;
+    let selected: Vec<&PathBuf> = match answer.as_deref().map(str::trim) {
+        None | Some("") => return Ok(()),
+        Some("all") => groups.iter().map(|(group, _)| group).collect(),
+        Some(answer) => answer
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|number| !number.is_empty())
+            .map(|number| {
+                number
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|number| number.checked_sub(1))
+                    .and_then(|i| groups.get(i))
+                    .map(|(group, _)| group)
+                    .ok_or_else(|| anyhow!("'{}' is not one of the listed numbers", number))
+            })
+            .collect::<Result<_>>()?,
+    };
+
+    // Plugins stored directly in `path` are added as individual files so we don't also pick up
+    // the subdirectories the user did not select
+    let mut num_added = 0;
+    for group in &selected {
+        if group.is_dir() {
+            if !confirm_overlapping_directory(config, group) {
+                continue;
+            }
+
+            set_wine_prefix(config, group, wine_prefix.clone())?;
+            config.plugin_dirs.insert((*group).clone());
+        } else {
+            set_wine_prefix(config, group, wine_prefix.clone())?;
+            config.plugin_files.insert((*group).clone());
+        }
+
+        num_added += 1;
+    }
+    config.write()?;
+
+    println!("\nAdded {} plugin locations", num_added);
+
+    Ok(())
+}
+
+/// Add the common plugin install locations within a Wine prefix to the plugin locations. Locations
+/// that don't exist are skipped, and we'll print a warning if none of them exist. Like with
+/// `add_directory()`, the user is asked for confirmation before adding a location that overlaps
+/// with an existing plugin directory.
+pub fn add_prefix(config: &mut Config, prefix: &Path) -> Result<()> {
+    if !utils::is_wine_prefix(prefix) {
+        return Err(anyhow!(
+            "'{}' is not a Wine prefix, it does not contain a 'system.reg' file",
+            prefix.display()
+        ));
+    }
+
+    let plugin_dirs: Vec<PathBuf> = WINE_PREFIX_PLUGIN_DIRS
+        .iter()
+        .map(|directory| prefix.join(directory))
+        .filter(|directory| directory.is_dir())
+        .collect();
+    let num_found = plugin_dirs.len();
+    let plugin_dirs: Vec<PathBuf> = plugin_dirs
+        .into_iter()
+        .filter(|directory| confirm_overlapping_directory(config, directory))
+        .collect();
+    if num_found == 0 {
+        eprintln!(
+            "{}",
+            utils::wrap(&format!(
+                "Warning: Could not find any plugin directories in '{}'. You can use 'yabridgectl \
+                 add <path>' to add plugin directories in non-standard locations.",
+                utils::display_path(prefix)
+            ))
+        );
+
+        return Ok(());
+    } else if plugin_dirs.is_empty() {
+        return Ok(());
+    }
+
+    println!("Adding the following plugin locations:");
+    for directory in plugin_dirs {
+        println!("- {}", utils::display_path(&directory));
+        config.plugin_dirs.insert(directory);
+    }
+
+    config.write()
+}
+
+/// Add an individual plugin file to the plugin locations. Only `.dll` and `.vst3` files can be
+/// added this way. Like with `add_directory()`, the plugin can be associated with a Wine prefix.
+pub fn add_file(config: &mut Config, path: PathBuf, wine_prefix: Option<PathBuf>) -> Result<()> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("dll") | Some("vst3") => {
+            set_wine_prefix(config, &path, wine_prefix)?;
+            config.plugin_files.insert(path);
+            config.write()
+        }
+        _ => Err(anyhow!(
+            "'{}' is not a '.dll' or '.vst3' file",
+            path.display()
+        )),
+    }
+}
+
+/// Associate a plugin location with a Wine prefix. Nothing changes if `wine_prefix` is not set, so
+/// adding a location again without a prefix keeps the existing association.
+fn set_wine_prefix(config: &mut Config, path: &Path, wine_prefix: Option<PathBuf>) -> Result<()> {
+    if let Some(wine_prefix) = wine_prefix {
+        if !utils::is_wine_prefix(&wine_prefix) {
+            return Err(anyhow!(
+                "'{}' is not a Wine prefix, it does not contain a 'system.reg' file",
+                wine_prefix.display()
+            ));
+        }
+
+        config.wine_prefixes.insert(path.to_owned(), wine_prefix);
+    }
+
+    Ok(())
+}
+
+/// Remove a plugin directory or an individual plugin file from the plugin locations. This only
+/// changes `config` in memory, so multiple locations can be removed before calling
+/// `finish_removing_locations()`. Returns an error if `path` is not a plugin location.
+pub fn remove_location(config: &mut Config, path: &Path) -> Result<()> {
+    if !config.plugin_dirs.remove(path) && !config.plugin_files.remove(path) {
+        return Err(anyhow!(
+            "'{}' is not a plugin location, use 'yabridgectl list' to list the plugin locations",
+            path.display()
+        ));
+    }
+    config.wine_prefixes.remove(path);
+
+    Ok(())
+}
+
+/// Write the config after removing plugin locations using `remove_location()`, and then ask the
+/// user once whether the leftover files from all of those locations should be removed.
+pub fn finish_removing_locations(
+    config: &Config,
+    locations: &[PathBuf],
+    quiet: bool,
+) -> Result<()> {
     config.write()?;
 
     // Ask the user to remove any leftover files to prevent possible future problems and out of date
     // copies
-    let orphan_files = files::index(path, &HashSet::new()).so_files;
+    let orphan_files: Vec<NativeFile> = locations
+        .iter()
+        .flat_map(|path| {
+            if path.is_file() {
+                files::index_file(path).so_files
+            } else {
+                files::index(path, &HashSet::new(), &[], None).so_files
+            }
+        })
+        .collect();
+    let location_str = match locations {
+        [path] if path.is_file() => "this file",
+        [_] => "this directory",
+        _ => "these locations",
+    };
+    let locations: Vec<&Path> = locations.iter().map(|path| path.as_path()).collect();
+    remove_orphan_files(config, orphan_files, &locations, location_str, quiet)
+}
+
+/// Remove all plugin locations matching a glob pattern. The user will only be asked once whether
+/// the leftover files from all of those locations should be removed.
+pub fn remove_matching(config: &mut Config, pattern: &glob::Pattern, quiet: bool) -> Result<()> {
+    let matching_dirs: Vec<PathBuf> = config
+        .plugin_dirs
+        .iter()
+        .filter(|path| pattern.matches_path(path))
+        .cloned()
+        .collect();
+    let matching_files: Vec<PathBuf> = config
+        .plugin_files
+        .iter()
+        .filter(|path| pattern.matches_path(path))
+        .cloned()
+        .collect();
+    if matching_dirs.is_empty() && matching_files.is_empty() {
+        return Err(anyhow!("No plugin locations match '{}'", pattern.as_str()));
+    }
+
+    remove_locations(config, &matching_dirs, &matching_files, quiet)
+}
+
+/// The shared part of `remove_matching()`. This prints and removes the plugin directories and
+/// files, and then asks the user once whether the leftover files from all of those locations should
+/// be removed.
+fn remove_locations(
+    config: &mut Config,
+    matching_dirs: &[PathBuf],
+    matching_files: &[PathBuf],
+    quiet: bool,
+) -> Result<()> {
+    if !quiet {
+        println!("Removing the following plugin locations:");
+    }
+    let locations: Vec<PathBuf> = matching_dirs
+        .iter()
+        .chain(matching_files.iter())
+        .cloned()
+        .collect();
+    for path in &locations {
+        if !quiet {
+            println!("- {}", utils::display_path(path));
+        }
+        remove_location(config, path)?;
+    }
+    if !quiet {
+        println!();
+    }
+
+    finish_removing_locations(config, &locations, quiet)
+}
+
+/// Ask the user whether they want to remove the leftover `.so` files from a plugin location that
+/// has just been removed, as well as the merged VST3 bundles in `~/.vst3/yabridge` that were set
+/// up for the plugins in `locations`. `location_str` is used to refer to the removed locations in
+/// the prompt. Files in the `keep` list are left alone. The prompt is still shown when `quiet` is
+/// set, but the confirmation afterwards is not.
+fn remove_orphan_files(
+    config: &Config,
+    mut orphan_files: Vec<NativeFile>,
+    locations: &[&Path],
+    location_str: &str,
+    quiet: bool,
+) -> Result<()> {
+    let mut vst3_orphan_files = files::index_vst3_location_orphans(&config.vst3_home(), locations);
+    orphan_files.retain(|file| !config.is_kept(file.path()));
+    vst3_orphan_files.retain(|file| !config.is_kept(file.path()));
+
     if !orphan_files.is_empty() {
         println!(
-            "Warning: Found {} leftover .so files still in this directory:",
-            orphan_files.len()
+            "Warning: Found {} leftover .so files still in {}:",
+            orphan_files.len(),
+            location_str
         );
 
         for file in &orphan_files {
-            println!("- {}", file.path().display());
+            println!("- {}", utils::display_path(file.path()));
+        }
+    }
+    if !vst3_orphan_files.is_empty() {
+        // These merged bundles would otherwise only be cleaned up during the next
+        // `yabridgectl sync --prune`
+        if !orphan_files.is_empty() {
+            println!();
+        }
+        println!(
+            "Warning: Found {} VST3 files in '{}' that were set up for {}:",
+            vst3_orphan_files.len(),
+            utils::display_path(&config.vst3_home()),
+            location_str
+        );
+
+        for file in &vst3_orphan_files {
+            println!("- {}", utils::display_path(file.path()));
         }
+    }
 
+    if !orphan_files.is_empty() || !vst3_orphan_files.is_empty() {
         match promptly::prompt_opt::<String, &str>(
             "\nWould you like to remove these files? Entering anything other than YES will leave \
              these files intact",
         ) {
             Ok(Some(answer)) if answer == "YES" => {
-                for file in &orphan_files {
-                    utils::remove_file(file.path())?;
+                for file in orphan_files.iter().chain(vst3_orphan_files.iter()) {
+                    match file {
+                        NativeFile::Regular(path) | NativeFile::Symlink(path) => {
+                            utils::remove_file(path)?;
+                        }
+                        NativeFile::Directory(path) => {
+                            utils::remove_dir_all(path)?;
+                        }
+                    }
                 }
 
-                println!("\nRemoved {} files", orphan_files.len());
+                if !quiet {
+                    println!(
+                        "\nRemoved {} files",
+                        orphan_files.len() + vst3_orphan_files.len()
+                    );
+                }
             }
             _ => {}
         }
@@ -74,106 +554,1241 @@ pub fn remove_directory(config: &mut Config, path: &Path) -> Result<()> {
     Ok(())
 }
 
-/// List the plugin locations.
-pub fn list_directories(config: &Config) -> Result<()> {
-    for directory in &config.plugin_dirs {
-        println!("{}", directory.display());
+/// List the plugin locations. If `null_separated` is set, then the paths are printed as is and
+/// separated by null bytes. If `show_index` is set, then every location is prefixed by the number
+/// that can be passed to `yabridgectl rm` instead of the path.
+pub fn list_directories(config: &Config, null_separated: bool, show_index: bool) -> Result<()> {
+    if null_separated {
+        return utils::print_null_separated(
+            config
+                .plugin_dirs
+                .iter()
+                .chain(config.plugin_files.iter())
+                .map(|path| path.as_path()),
+        );
+    }
+
+    // `yabridgectl rm` resolves these numbers in the same order
+    for (index, path) in config
+        .plugin_dirs
+        .iter()
+        .chain(config.plugin_files.iter())
+        .enumerate()
+    {
+        if show_index {
+            println!("{:>3}  {}", index + 1, utils::display_path(path));
+        } else {
+            println!("{}", utils::display_path(path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Options passed to `yabridgectl status`, see `main()` for the definitions of these options.
+pub struct StatusOptions<'a> {
+    pub fail_if_no_yabridge: bool,
+    pub formats: &'a [PluginFormat],
+    pub no_cache: bool,
+    pub show_conflicts: bool,
+    pub outdated: bool,
+    pub quiet: bool,
+}
+
+/// Checks whether copies of `libyabridge-{vst2,vst3}.so` are outdated by comparing the hashes
+/// recorded in the manifest during the last sync to the hashes of the current files. This can
+/// happen when a sync was interrupted or failed after updating yabridge.
+struct OutdatedFiles {
+    manifest: Option<Manifest>,
+    libyabridge_vst2_hash: Option<i64>,
+    libyabridge_vst3_hash: Option<i64>,
+}
+
+impl OutdatedFiles {
+    fn new(files: Option<&YabridgeFiles>) -> Self {
+        OutdatedFiles {
+            manifest: Manifest::read(),
+            libyabridge_vst2_hash: files
+                .and_then(|files| utils::hash_file(&files.libyabridge_vst2).ok()),
+            libyabridge_vst3_hash: files
+                .and_then(|files| files.libyabridge_vst3.as_ref())
+                .and_then(|(path, _)| utils::hash_file(path).ok()),
+        }
+    }
+
+    /// Whether `status`, as returned by `SearchResults::installation_status()`, is a copy of an
+    /// older version of `libyabridge-{vst2,vst3}.so`. Symlinks are never outdated, and files we
+    /// don't have a recorded hash for are assumed to be up to date.
+    fn is_outdated(&self, plugin: &Plugin, status: &Option<NativeFile>) -> bool {
+        let (manifest, path) = match (&self.manifest, status) {
+            (Some(manifest), Some(NativeFile::Regular(path))) => (manifest, path),
+            _ => return false,
+        };
+        let current_hash = match plugin {
+            Plugin::Vst2(_) => self.libyabridge_vst2_hash,
+            Plugin::Vst3(_) => self.libyabridge_vst3_hash,
+        };
+
+        match (manifest.libyabridge_hashes.get(path), current_hash) {
+            (Some(recorded_hash), Some(current_hash)) => *recorded_hash != current_hash,
+            _ => false,
+        }
+    }
+}
+
+/// Print the current configuration and the installation status for all found plugins. If
+/// `fail_if_no_yabridge` is set, then we'll return `ExitCodeError::NoYabridge` after printing the
+/// status if yabridge's files could not be found.
+pub fn show_status(config: &Config, options: &StatusOptions) -> Result<()> {
+    let results = config
+        .search_directories(options.formats, !options.no_cache)
+        .context("Failure while searching for plugins")?;
+
+    // With `--quiet` only the warnings about missing Visual C++ runtimes are printed
+    let files = config.files();
+    if !options.quiet {
+        print_status_header(config, &files);
+    }
+
+    // Plugins that import Visual C++ runtime libraries that are not installed in their Wine prefix
+    // will fail to load, so we'll warn about those after printing the status
+    let mut missing_runtimes: Vec<(PathBuf, Vec<(String, &str)>)> = Vec::new();
+    // If the VST3 directory was changed without pruning, then hosts that scan both directories will
+    // list those plugins twice
+    let other_vst3_homes = config.other_vst3_homes(Manifest::read().as_ref());
+    // A bundle can contain both a 32-bit and a 64-bit module, so this should not list bundles twice
+    let mut duplicate_vst3_bundles: BTreeSet<PathBuf> = BTreeSet::new();
+    let outdated_files = OutdatedFiles::new(files.as_ref().ok());
+    // These need to be gathered before we consume the search results below
+    let clashes = if options.show_conflicts {
+        Some(files::find_vst3_clashes(results.values()))
+    } else {
+        None
+    };
+    // With `--outdated` we'll only print the plugins that need to be synced, and the locations they
+    // are in
+    let mut num_outdated_plugins = 0;
+    for (path, search_results) in results {
+        let (location_path, relative_root) = plugin_location_paths(path);
+        let location_header = match config.wine_prefixes.get(path) {
+            Some(wine_prefix) => format!(
+                "\n{} (Wine prefix: {})",
+                utils::display_path(&location_path),
+                utils::display_path(wine_prefix)
+            ),
+            None => format!("\n{}", utils::display_path(&location_path)),
+        };
+        let mut printed_location_header = false;
+        if !options.quiet && !options.outdated {
+            println!("{}", location_header);
+            printed_location_header = true;
+        }
+
+        for (plugin_path, (plugin, status)) in
+            search_results.installation_status(files.as_ref().ok())
+        {
+            let (module_path, architecture) = match plugin {
+                Plugin::Vst2(plugin) => (plugin.path.clone(), plugin.architecture),
+                Plugin::Vst3(module) => (module.original_module_path(), module.architecture),
+            };
+            match utils::find_missing_vc_runtimes(config, &module_path, architecture) {
+                Ok(runtimes) if !runtimes.is_empty() => {
+                    missing_runtimes.push((plugin_path.clone(), runtimes))
+                }
+                _ => (),
+            }
+
+            if let (Plugin::Vst3(module), Some(_)) = (plugin, &status) {
+                for vst3_home in &other_vst3_homes {
+                    let other_module = Vst3Module {
+                        vst3_home: vst3_home.clone(),
+                        ..module.clone()
+                    };
+                    let other_native_module_path =
+                        other_module.target_native_module_path(files.as_ref().ok());
+                    if utils::get_file_type(other_native_module_path).is_some() {
+                        duplicate_vst3_bundles.insert(other_module.target_bundle_home());
+                    }
+                }
+            }
+
+            if options.quiet {
+                continue;
+            }
+
+            let is_outdated = outdated_files.is_outdated(plugin, &status);
+            if options.outdated {
+                let is_synced = matches!(
+                    status,
+                    Some(NativeFile::Regular(_)) | Some(NativeFile::Symlink(_))
+                );
+                if is_synced && !is_outdated {
+                    continue;
+                }
+
+                num_outdated_plugins += 1;
+            }
+            if !printed_location_header {
+                println!("{}", location_header);
+                printed_location_header = true;
+            }
+
+            let plugin_type = match plugin {
+                Plugin::Vst2(Vst2Plugin {
+                    architecture,
+                    is_shell: true,
+                    ..
+                }) => format!("{}, {}, {}", "VST2".cyan(), "shell".yellow(), architecture),
+                Plugin::Vst2(Vst2Plugin { architecture, .. }) => {
+                    format!("{}, {}", "VST2".cyan(), architecture)
+                }
+                Plugin::Vst3(module) => format!(
+                    "{}, {}, {}",
+                    "VST3".magenta(),
+                    module.type_str(),
+                    module.architecture
+                ),
+            };
+
+            let outdated_str = if is_outdated {
+                format!(", {}", "outdated".yellow())
+            } else {
+                String::new()
+            };
+            let status_str = match status {
+                Some(file @ NativeFile::Regular(_)) if file.is_hard_link() => "hard link".green(),
+                Some(NativeFile::Regular(_)) => "copy".green(),
+                Some(NativeFile::Symlink(_)) => "symlink".green(),
+                Some(NativeFile::Directory(_)) => "invalid".red(),
+                None => "not yet installed".into(),
+            };
+
+            // The plugin's actual name is only shown if it's not already obvious from the file name
+            let display_name_str = match plugin.display_name() {
+                Some(display_name)
+                    if plugin_path.file_stem().and_then(|stem| stem.to_str())
+                        != Some(display_name) =>
+                {
+                    format!(" ({})", display_name)
+                }
+                _ => String::new(),
+            };
+
+            println!(
+                "  {}{} :: {}, {}{}",
+                utils::display_path(
+                    plugin_path
+                        .strip_prefix(relative_root)
+                        .unwrap_or(&plugin_path)
+                ),
+                display_name_str,
+                plugin_type,
+                status_str,
+                outdated_str
+            );
+        }
+
+        // These plugins are not set up by yabridgectl since they don't need to be bridged
+        if !options.quiet && !options.outdated {
+            for bundle in &search_results.native_vst3_bundles {
+                println!(
+                    "  {} :: {}, {}",
+                    utils::display_path(bundle.strip_prefix(relative_root).unwrap_or(bundle)),
+                    "VST3".magenta(),
+                    "native Linux plugin, not bridged".yellow()
+                );
+            }
+        }
+    }
+
+    if options.outdated && num_outdated_plugins == 0 && !options.quiet {
+        println!("\nAll plugins are up to date");
+    }
+
+    match clashes {
+        Some(clashes) if clashes.is_empty() && options.quiet => (),
+        Some(clashes) if clashes.is_empty() => println!("\nNo conflicting plugins found"),
+        Some(clashes) => {
+            println!("\nConflicting plugins:");
+            print_vst3_clashes(clashes);
+        }
+        None => (),
+    }
+
+    if !duplicate_vst3_bundles.is_empty() {
+        eprintln!(
+            "\n{}",
+            utils::wrap(&format!(
+                "Warning: Found {} VST3 plugins that are also set up in a VST3 directory \
+                 yabridgectl no longer uses. Hosts that search both directories will list these \
+                 plugins twice. yabridgectl won't remove these bundles, so remove them by hand if \
+                 you no longer need them:",
+                duplicate_vst3_bundles.len()
+            ))
+        );
+        for bundle in &duplicate_vst3_bundles {
+            eprintln!("- {}", utils::display_path(bundle));
+        }
+    }
+
+    for (plugin_path, runtimes) in missing_runtimes {
+        let dll_names: Vec<String> = runtimes
+            .iter()
+            .map(|(dll_name, _)| format!("'{}'", dll_name))
+            .collect();
+        let mut winetricks_verbs: Vec<&str> = runtimes.iter().map(|(_, verb)| *verb).collect();
+        winetricks_verbs.dedup();
+
+        eprintln!(
+            "\n{}",
+            utils::wrap(&format!(
+                "Warning: '{}' requires {}, which {} not installed in its Wine prefix. This plugin \
+                 will likely fail to load until you install {} using '{}'.",
+                utils::display_path(&plugin_path),
+                dll_names.join(", "),
+                if dll_names.len() == 1 { "is" } else { "are" },
+                if dll_names.len() == 1 { "it" } else { "them" },
+                format!("winetricks {}", winetricks_verbs.join(" ")).bright_white()
+            ))
+        );
+    }
+
+    if options.fail_if_no_yabridge && files.is_err() {
+        return Err(ExitCodeError::NoYabridge.into());
+    }
+
+    Ok(())
+}
+
+/// Print the yabridge paths and the configuration at the top of `show_status()`'s output.
+fn print_status_header(config: &Config, files: &Result<YabridgeFiles>) {
+    println!(
+        "yabridge path: {}",
+        match &config.yabridge_home {
+            Some(path) => format!("'{}'", path.display()),
+            None if !config.yabridge_home_candidates.is_empty() => config
+                .yabridge_home_candidates
+                .iter()
+                .map(|candidate| format!("'{}'", candidate))
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => String::from("<auto>"),
+        }
+    );
+    if let Some(path) = &config.yabridge_host_exe {
+        println!("{} path: '{}'", YABRIDGE_HOST_EXE_NAME, path.display());
+    }
+    if let Some(path) = &config.yabridge_host_32_exe {
+        println!("{} path: '{}'", YABRIDGE_HOST_32_EXE_NAME, path.display());
+    }
+
+    match files {
+        Ok(files) => {
+            println!(
+                "libyabridge-vst2.so: '{}'",
+                files.libyabridge_vst2.display()
+            );
+            println!(
+                "libyabridge-vst3.so: {}\n",
+                files
+                    .libyabridge_vst3
+                    .as_ref()
+                    .map(|(path, arch)| format!("'{}' ({})", path.display(), arch))
+                    .unwrap_or_else(|| "<not found>".red().to_string())
+            );
+            for (pinned, found) in config.mismatched_pinned_hosts(files) {
+                let warning = match found {
+                    Some(found) => format!(
+                        "yabridge will use '{}' instead of the pinned '{}'",
+                        found.display(),
+                        pinned.display()
+                    ),
+                    None => format!(
+                        "yabridge won't be able to find the pinned '{}'",
+                        pinned.display()
+                    ),
+                };
+                println!("{}\n", warning.yellow());
+            }
+        }
+        Err(err) => {
+            println!("Could not find yabridge's files: {:#}\n", err);
+        }
+    }
+    println!("installation method: {}", config.method);
+    match config.last_sync_time() {
+        Some(last_sync) => println!(
+            "last synced: {}",
+            utils::format_time_ago(
+                SystemTime::now()
+                    .duration_since(last_sync)
+                    .unwrap_or_default()
+            )
+        ),
+        None => println!("last synced: {}", "never".yellow()),
+    }
+    if config.parser != Pe32Parser::Auto {
+        println!("parser: {}", config.parser);
+    }
+    if config.vst3_home.is_some() {
+        println!("vst3 directory: '{}'", config.vst3_home().display());
+    }
+    match &config.flatpak_app_id {
+        Some(app_id) => println!("flatpak application: {}", app_id),
+        None => {
+            // Sandboxed applications with their own `~/.vst3` directory won't see our VST3 plugins
+            let flatpak_apps = utils::find_flatpak_vst3_apps();
+            if !flatpak_apps.is_empty() {
+                println!(
+                    "\n{}",
+                    utils::wrap(&format!(
+                        "Found Flatpak applications with their own VST3 directory: {}. These \
+                         applications can't see the plugins in '~/.vst3/yabridge'. Use \
+                         'yabridgectl set --flatpak <app-id>' to set up VST3 plugins for one of \
+                         them instead.",
+                        flatpak_apps.join(", ")
+                    ))
+                );
+            }
+        }
+    }
+}
+
+/// Keep redrawing the output from `show_status()` until the user presses Ctrl+C. This doesn't
+/// change anything, so it can be used to see plugins appear while they're being installed.
+pub fn watch_status(config: &Config, options: &StatusOptions) -> Result<()> {
+    loop {
+        // This clears the terminal and moves the cursor back to the top left corner
+        print!("\x1B[2J\x1B[1;1H");
+        show_status(config, options)?;
+
+        thread::sleep(STATUS_WATCH_INTERVAL);
+    }
+}
+
+/// The version of the document printed by `yabridgectl status --json`. This should be incremented
+/// whenever existing fields are changed or removed.
+const STATUS_JSON_SCHEMA_VERSION: u32 = 1;
+
+/// The document printed by `yabridgectl status --json`.
+#[derive(Serialize)]
+struct StatusJson<'a> {
+    schema_version: u32,
+    yabridge_home: Option<&'a Path>,
+    yabridge_home_candidates: &'a [String],
+    installation_method: InstallationMethod,
+    /// The RFC 3339 timestamp of the last successful full sync, if any. See `Config::last_sync`.
+    last_sync: Option<&'a str>,
+    /// Will be `None` if yabridge's files could not be found.
+    files: Option<FilesJson<'a>>,
+    locations: Vec<LocationJson<'a>>,
+}
+
+/// The resolved `YabridgeFiles` in `StatusJson`.
+#[derive(Serialize)]
+struct FilesJson<'a> {
+    libyabridge_vst2: &'a Path,
+    libyabridge_vst3: Option<&'a Path>,
+    libyabridge_vst3_architecture: Option<String>,
+    yabridge_host_exe: Option<&'a Path>,
+    yabridge_host_32_exe: Option<&'a Path>,
+}
+
+/// A plugin location in `StatusJson`, along with all plugins found there. `wine_prefix` is the Wine
+/// prefix associated with this location, if any.
+#[derive(Serialize)]
+struct LocationJson<'a> {
+    path: &'a Path,
+    wine_prefix: Option<&'a Path>,
+    plugins: Vec<PluginJson>,
+}
+
+/// A single plugin in `LocationJson`. `type` is either `vst2` or `vst3`, and `vst3_module_type` is
+/// only set for VST3 plugins. `vst2_shell` is set for VST2 shell plugins like Waves' WaveShell.
+/// `display_name` is the product name stored in the plugin file, if any. `status` is one of `copy`,
+/// `hardlink`, `symlink`, `invalid` or `not_installed`. `outdated` is set for copies of an older
+/// version of `libyabridge-{vst2,vst3}.so`.
+#[derive(Serialize)]
+struct PluginJson {
+    path: PathBuf,
+    display_name: Option<String>,
+    #[serde(rename = "type")]
+    plugin_type: &'static str,
+    architecture: String,
+    vst3_module_type: Option<&'static str>,
+    vst2_shell: bool,
+    status: &'static str,
+    outdated: bool,
+    installed_path: Option<PathBuf>,
+}
+
+/// The same as `show_status()`, but the status is printed as a JSON document instead. Nothing else
+/// is printed to STDOUT.
+pub fn show_status_json(config: &Config, options: &StatusOptions) -> Result<()> {
+    let results = config
+        .search_directories(options.formats, !options.no_cache)
+        .context("Failure while searching for plugins")?;
+    let files = config.files();
+    let outdated_files = OutdatedFiles::new(files.as_ref().ok());
+
+    let mut locations = Vec::new();
+    for (path, search_results) in results {
+        let plugins = search_results
+            .installation_status(files.as_ref().ok())
+            .into_iter()
+            .map(|(plugin_path, (plugin, status))| {
+                let (plugin_type, architecture, vst3_module_type) = match plugin {
+                    Plugin::Vst2(plugin) => ("vst2", plugin.architecture, None),
+                    Plugin::Vst3(module) => ("vst3", module.architecture, Some(module.type_str())),
+                };
+                let status_str = match &status {
+                    Some(file @ NativeFile::Regular(_)) if file.is_hard_link() => "hardlink",
+                    Some(NativeFile::Regular(_)) => "copy",
+                    Some(NativeFile::Symlink(_)) => "symlink",
+                    Some(NativeFile::Directory(_)) => "invalid",
+                    None => "not_installed",
+                };
+
+                PluginJson {
+                    path: plugin_path,
+                    display_name: plugin.display_name().map(String::from),
+                    plugin_type,
+                    architecture: architecture.to_string(),
+                    vst3_module_type,
+                    vst2_shell: matches!(plugin, Plugin::Vst2(Vst2Plugin { is_shell: true, .. })),
+                    status: status_str,
+                    outdated: outdated_files.is_outdated(plugin, &status),
+                    installed_path: status.map(|file| file.path().to_owned()),
+                }
+            })
+            .collect();
+
+        locations.push(LocationJson {
+            path,
+            wine_prefix: config
+                .wine_prefixes
+                .get(path)
+                .map(|prefix| prefix.as_path()),
+            plugins,
+        });
+    }
+
+    let status = StatusJson {
+        schema_version: STATUS_JSON_SCHEMA_VERSION,
+        yabridge_home: config.yabridge_home.as_deref(),
+        yabridge_home_candidates: &config.yabridge_home_candidates,
+        installation_method: config.method,
+        last_sync: config.last_sync.as_deref(),
+        files: files.as_ref().ok().map(|files| FilesJson {
+            libyabridge_vst2: &files.libyabridge_vst2,
+            libyabridge_vst3: files
+                .libyabridge_vst3
+                .as_ref()
+                .map(|(path, _)| path.as_path()),
+            libyabridge_vst3_architecture: files
+                .libyabridge_vst3
+                .as_ref()
+                .map(|(_, architecture)| architecture.to_string()),
+            yabridge_host_exe: files.yabridge_host_exe.as_deref(),
+            yabridge_host_32_exe: files.yabridge_host_32_exe.as_deref(),
+        }),
+        locations,
+    };
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&status).context("Could not serialize the status")?
+    );
+
+    if options.fail_if_no_yabridge && files.is_err() {
+        return Err(ExitCodeError::NoYabridge.into());
+    }
+
+    Ok(())
+}
+
+/// Print everything yabridgectl knows about a single plugin file or VST3 bundle, regardless of
+/// whether it's part of one of the plugin locations. This parses the file directly without using
+/// the cache.
+pub fn show_plugin_info(config: &Config, path: &Path) -> Result<()> {
+    // VST3 bundles are directories, so we'll need to search those for the actual modules
+    let index = if path.is_dir() {
+        files::index(path, &HashSet::new(), &[], None)
+    } else {
+        files::index_file(path)
+    };
+    let module_paths: Vec<&PathBuf> = index.dll_files.iter().chain(&index.vst3_files).collect();
+    if module_paths.is_empty() && path.is_dir() {
+        return Err(anyhow!(
+            "'{}' does not contain any '.dll' or '.vst3' files",
+            path.display()
+        ));
+    } else if module_paths.is_empty() {
+        return Err(anyhow!(
+            "'{}' is not a '.dll' or '.vst3' file",
+            path.display()
+        ));
+    }
+
+    let location = config
+        .plugin_dirs
+        .iter()
+        .chain(config.plugin_files.iter())
+        .filter(|location| path.starts_with(location))
+        .max_by_key(|location| location.components().count());
+    println!(
+        "plugin location: {}",
+        location
+            .map(|location| format!("'{}'", location.display()))
+            .unwrap_or_else(|| "<none>".red().to_string())
+    );
+    let (blacklist, blacklist_patterns) = config.blacklist_filters();
+    if path.ancestors().any(|path| {
+        blacklist.contains(path)
+            || blacklist_patterns
+                .iter()
+                .any(|pattern| pattern.matches_path(path))
+    }) {
+        println!("blacklisted: {}", "yes".red());
+    }
+    println!(
+        "wine prefix: {}",
+        utils::get_plugin_wine_prefix(config, path)
+            .map(|prefix| format!("'{}'", prefix.display()))
+            .unwrap_or_else(|| String::from("<default>"))
+    );
+
+    for module_path in &module_paths {
+        let info = files::parse_pe32_info(module_path, config.parser)?;
+        let yes_no = |value: bool| if value { "yes".green() } else { "no".red() };
+
+        println!("\n{}", utils::display_path(module_path));
+        println!("  PE32(+) binary: {}", yes_no(info.is_pe32));
+        println!(
+            "  architecture: {}",
+            if info.is_32_bit {
+                LibArchitecture::Lib32
+            } else {
+                LibArchitecture::Lib64
+            }
+        );
+        println!(
+            "  product name: {}",
+            info.product_name.as_deref().unwrap_or("<none>")
+        );
+        println!(
+            "  VST2 entry point: {}",
+            yes_no(info.exports_vst2_entry_point)
+        );
+        println!(
+            "  VST3 entry point: {}",
+            yes_no(info.exports_vst3_entry_point)
+        );
+    }
+
+    let files = config.files().ok();
+    let outdated_files = OutdatedFiles::new(files.as_ref());
+    let results = index.search(None, config.parser, &config.vst3_home(), None, None)?;
+    for (plugin_path, (plugin, status)) in results.installation_status(files.as_ref()) {
+        println!("\n{}", utils::display_path(&plugin_path));
+        let target_path = match plugin {
+            Plugin::Vst2(plugin) => {
+                if plugin.is_shell {
+                    println!("  format: {}, {}", "VST2".cyan(), "shell".yellow());
+                } else {
+                    println!("  format: {}", "VST2".cyan());
+                }
+                if plugin.exports_vst3_entry_point {
+                    println!(
+                        "  note: {}",
+                        "also exports the VST3 entry point, but it will only be set up as a VST2 \
+                         plugin"
+                            .yellow()
+                    );
+                }
+                plugin_path.with_extension("so")
+            }
+            Plugin::Vst3(module) => {
+                println!("  format: {}, {}", "VST3".magenta(), module.type_str());
+                println!(
+                    "  merged bundle: '{}'",
+                    module.target_bundle_home().display()
+                );
+                module.target_native_module_path(files.as_ref())
+            }
+        };
+        println!("  target: '{}'", target_path.display());
+        println!(
+            "  status: {}{}",
+            match &status {
+                Some(file @ NativeFile::Regular(_)) if file.is_hard_link() => "hard link".green(),
+                Some(NativeFile::Regular(_)) => "copy".green(),
+                Some(NativeFile::Symlink(_)) => "symlink".green(),
+                Some(NativeFile::Directory(_)) => "invalid".red(),
+                None => "not yet installed".into(),
+            },
+            if outdated_files.is_outdated(plugin, &status) {
+                format!(", {}", "outdated".yellow())
+            } else {
+                String::new()
+            }
+        );
+    }
+    if results.plugins.is_empty() {
+        println!("\n{}", "This is not a VST2 or VST3 plugin".red());
+    }
+
+    Ok(())
+}
+
+/// List all VST3 plugins that would be set up in the same location in `~/.vst3/yabridge` by
+/// `yabridgectl sync`. This happens when the same plugin is installed in multiple Wine prefixes.
+/// Only the first of those plugins will actually be set up. This doesn't change anything.
+pub fn show_clashes(config: &Config) -> Result<()> {
+    // Only VST3 plugins are set up in a shared location
+    let results = config
+        .search_directories(&[PluginFormat::Vst3], true)
+        .context("Failure while searching for plugins")?;
+    let clashes = files::find_vst3_clashes(results.values());
+    if clashes.is_empty() {
+        println!("No clashing plugins found");
+        return Ok(());
+    }
+
+    print_vst3_clashes(clashes);
+
+    Ok(())
+}
+
+/// List the Wine prefixes yabridge will run the plugins in, along with the plugins that will use
+/// each prefix. This uses the same rules as yabridge itself, see `utils::get_plugin_wine_prefix()`.
+pub fn show_prefixes(config: &Config) -> Result<()> {
+    let results = config
+        .search_directories(&[PluginFormat::Vst2, PluginFormat::Vst3], true)
+        .context("Failure while searching for plugins")?;
+
+    // `None` is the default Wine prefix, which will be listed first
+    let mut prefixes: BTreeMap<Option<PathBuf>, Vec<(PathBuf, String)>> = BTreeMap::new();
+    for search_results in results.values() {
+        for plugin in &search_results.plugins {
+            let (plugin_path, plugin_type) = match plugin {
+                Plugin::Vst2(plugin) => (
+                    plugin.path.clone(),
+                    format!("{}, {}", "VST2".cyan(), plugin.architecture),
+                ),
+                Plugin::Vst3(module) => (
+                    module.original_path().to_owned(),
+                    format!("{}, {}", "VST3".magenta(), module.architecture),
+                ),
+            };
+
+            prefixes
+                .entry(utils::get_plugin_wine_prefix(config, &plugin_path))
+                .or_default()
+                .push((plugin_path, plugin_type));
+        }
+    }
+
+    if prefixes.is_empty() {
+        println!("No plugins found");
+        return Ok(());
+    }
+
+    let default_prefix = utils::default_wine_prefix();
+    for (i, (prefix, plugins)) in prefixes.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+
+        match prefix {
+            Some(prefix) => println!(
+                "{} ({}):",
+                utils::display_path(prefix),
+                utils::get_wine_prefix_arch(prefix)
+            ),
+            None => println!(
+                "{} ({}, {}):",
+                utils::display_path(&default_prefix),
+                "default prefix".yellow(),
+                utils::get_default_wine_prefix_arch()
+            ),
+        }
+        for (plugin_path, plugin_type) in plugins {
+            println!("- {} :: {}", utils::display_path(plugin_path), plugin_type);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the clashing VST3 modules found by `files::find_vst3_clashes()`, grouped by the bundle
+/// they would be set up in. The first module in each group is the one that will actually be used.
+fn print_vst3_clashes(clashes: BTreeMap<(PathBuf, LibArchitecture), Vec<PathBuf>>) {
+    for ((target_bundle_home, architecture), sources) in clashes {
+        println!(
+            "{} ({}):",
+            utils::display_path(&target_bundle_home),
+            architecture
+        );
+        for (i, source) in sources.iter().enumerate() {
+            if i == 0 {
+                println!("- {} {}", utils::display_path(source), "(used)".green());
+            } else {
+                println!("- {} {}", utils::display_path(source), "(skipped)".red());
+            }
+        }
+    }
+}
+
+/// Show how many copies, hard links, and symlinks yabridgectl has set up for VST2 plugins and in
+/// `~/.vst3/yabridge`, and how much space the copies take up. This doesn't change anything.
+pub fn show_disk_usage(config: &Config) -> Result<()> {
+    // The copies for VST2 plugins live next to the plugins, so we need to search for those
+    let results = config
+        .search_directories(&[PluginFormat::Vst2], true)
+        .context("Failure while searching for plugins")?;
+    let mut vst2_usage = files::DiskUsage::default();
+    for search_results in results.values() {
+        for plugin in &search_results.plugins {
+            if let Plugin::Vst2(Vst2Plugin { path, .. }) = plugin {
+                vst2_usage.add(&path.with_extension("so"));
+            }
+        }
+    }
+
+    let vst3_home = config.vst3_home();
+    let vst3_usage = files::vst3_home_disk_usage(&vst3_home);
+    let mut total_usage = vst2_usage;
+    total_usage.extend(&vst3_usage);
+
+    let format_usage = |usage: &files::DiskUsage| {
+        format!(
+            "{} copies ({}), {} hard links, {} symlinks",
+            usage.copies,
+            utils::format_size(usage.copies_size),
+            usage.hard_links,
+            usage.symlinks
+        )
+    };
+    println!("VST2 plugins: {}", format_usage(&vst2_usage));
+    println!(
+        "'{}': {}",
+        utils::display_path(&vst3_home),
+        format_usage(&vst3_usage)
+    );
+    println!("total: {}", format_usage(&total_usage));
+
+    if config.method == InstallationMethod::Copy && total_usage.copies > 0 {
+        println!(
+            "\n{}",
+            utils::wrap(
+                "The hard link based installation method does not use any additional disk space \
+                 for these files when your plugins are on the same file system as yabridge's \
+                 files. Use 'yabridgectl set --method=hardlink' to switch to that method."
+            )
+        );
+    }
+
+    Ok(())
+}
+
+/// Show which plugin formats are supported by the version of yabridge yabridgectl found, based on
+/// which of yabridge's libraries are installed. yabridge can be compiled without VST3 support.
+pub fn list_formats(config: &Config) -> Result<()> {
+    let files = config.files()?;
+    let vst2_architecture = config.libyabridge_architecture(&files.libyabridge_vst2)?;
+
+    println!(
+        "{}: '{}' ({})",
+        "VST2".cyan(),
+        files.libyabridge_vst2.display(),
+        vst2_architecture
+    );
+    println!(
+        "{}: {}",
+        "VST3".magenta(),
+        files
+            .libyabridge_vst3
+            .as_ref()
+            .map(|(path, arch)| format!("'{}' ({})", path.display(), arch))
+            .unwrap_or_else(|| "<not found>".red().to_string())
+    );
+    // This is listed here to avoid confusion, since there are no CLAP libraries to look for
+    println!(
+        "CLAP: {}",
+        "<not supported by this version of yabridge>".red()
+    );
+
+    Ok(())
+}
+
+/// Verify that yabridge has been installed correctly without needing any plugins. This sets up
+/// `libyabridge-vst2.so` in a temporary directory using the same process `yabridgectl sync` uses,
+/// checks the result, and then runs the Wine plugin host in the default Wine prefix. yabridge
+/// doesn't come with a Windows plugin we could load here, so actually loading plugins is left to
+/// `yabridgectl sync --check-wine-prefix`. Returns an error if any of the checks fail.
+pub fn self_test(config: &Config) -> Result<()> {
+    let files = config.files()?;
+    let libyabridge_vst2_hash = utils::hash_file(&files.libyabridge_vst2)?;
+    let vst2_architecture = config.libyabridge_architecture(&files.libyabridge_vst2)?;
+    println!(
+        "Found '{}' ({})",
+        files.libyabridge_vst2.display(),
+        vst2_architecture
+    );
+
+    // The test plugin is set up in yabridgectl's cache directory so an interrupted run won't leave
+    // any files behind in a location hosts scan for plugins. We'll always clean up the test
+    // directory, even if setting up the plugin failed.
+    let test_dir = yabridgectl_directories()?
+        .get_cache_home()
+        .join(format!("self-test-{}", process::id()));
+    let target_path = test_dir.join("yabridgectl-self-test.so");
+    let install_result = utils::create_dir_all(&test_dir).and_then(|_| {
+        install_file(
+            true,
+            false,
+            false,
+            config.method,
+            &files.libyabridge_vst2,
+            Some(libyabridge_vst2_hash),
+            &target_path,
+        )?;
+
+        let installed_correctly = match config.method {
+            InstallationMethod::Copy => utils::hash_file(&target_path)? == libyabridge_vst2_hash,
+            InstallationMethod::Symlink => target_path.read_link()? == files.libyabridge_vst2,
+            InstallationMethod::HardLink => {
+                utils::is_same_file(&target_path, &files.libyabridge_vst2)?
+            }
+        };
+        if !installed_correctly {
+            return Err(anyhow!(
+                "'{}' does not match '{}'",
+                target_path.display(),
+                files.libyabridge_vst2.display()
+            ));
+        }
+
+        config.libyabridge_architecture(&target_path)
+    });
+    // If creating the directory failed, then there's nothing to remove and the error from setting
+    // up the plugin is the one that matters
+    let cleanup_result = if test_dir.exists() {
+        utils::remove_dir_all(&test_dir)
+    } else {
+        Ok(())
+    };
+    let installed_architecture = install_result.context("Could not set up a test plugin")?;
+    cleanup_result?;
+    if installed_architecture != vst2_architecture {
+        return Err(anyhow!(
+            "The test plugin is {} while '{}' is {}",
+            installed_architecture,
+            files.libyabridge_vst2.display(),
+            vst2_architecture
+        ));
+    }
+    println!("Set up a test plugin using {}", config.method.plural_name());
+
+    // If the default Wine prefix is a 32-bit prefix, then we need to run the 32-bit plugin host
+    let wine_version = utils::get_wine_version()?;
+    let host_binary_path =
+        utils::yabridge_host_path(&files, utils::get_default_wine_prefix_arch())?;
+    if !utils::verify_yabridge_host(host_binary_path, &wine_version)? {
+        return Err(anyhow!("Could not run '{}'", host_binary_path.display()));
     }
+    println!(
+        "Ran '{}' using {}",
+        host_binary_path.display(),
+        wine_version
+    );
+
+    println!("\nEverything seems to be working correctly");
 
     Ok(())
 }
 
-/// Print the current configuration and the installation status for all found plugins.
-pub fn show_status(config: &Config) -> Result<()> {
-    let results = config
-        .search_directories()
-        .context("Failure while searching for plugins")?;
-
-    println!(
-        "yabridge path: {}",
-        config
-            .yabridge_home
-            .as_ref()
-            .map(|path| format!("'{}'", path.display()))
-            .unwrap_or_else(|| String::from("<auto>"))
-    );
+/// Run the same setup checks `yabridgectl sync` performs after setting up plugins and print the
+/// results as a checklist, with a hint on how to fix every failed check. This doesn't change
+/// anything and it doesn't require any plugins to be set up. Returns an error if any of the
+/// critical checks failed.
+pub fn doctor(config: &Config) -> Result<()> {
+    let mut num_failed = 0;
+    let mut report = |status: CheckStatus, description: String, hint: Option<&str>| {
+        let status_str = match status {
+            CheckStatus::Ok => "[ok]".green(),
+            CheckStatus::Warning => "[warning]".yellow(),
+            CheckStatus::Failed => {
+                num_failed += 1;
+                "[failed]".red()
+            }
+        };
+        println!("{} {}", status_str, description);
+        if let Some(hint) = hint {
+            println!("{}", utils::wrap(&format!("  {}", hint)));
+        }
+    };
 
     let files = config.files();
     match &files {
         Ok(files) => {
-            println!(
-                "libyabridge-vst2.so: '{}'",
-                files.libyabridge_vst2.display()
-            );
-            println!(
-                "libyabridge-vst3.so: {}\n",
-                files
-                    .libyabridge_vst3
-                    .as_ref()
-                    .map(|(path, arch)| format!("'{}' ({})", path.display(), arch))
-                    .unwrap_or_else(|| "<not found>".red().to_string())
-            );
+            match config.libyabridge_architecture(&files.libyabridge_vst2) {
+                Ok(architecture) => report(
+                    CheckStatus::Ok,
+                    format!(
+                        "Found '{}' ({})",
+                        utils::display_path(&files.libyabridge_vst2),
+                        architecture
+                    ),
+                    None,
+                ),
+                Err(err) => report(
+                    CheckStatus::Failed,
+                    format!(
+                        "Could not read '{}': {}",
+                        utils::display_path(&files.libyabridge_vst2),
+                        err
+                    ),
+                    Some("Try reinstalling yabridge."),
+                ),
+            }
+            match &files.libyabridge_vst3 {
+                Some((path, architecture)) => report(
+                    CheckStatus::Ok,
+                    format!("Found '{}' ({})", utils::display_path(path), architecture),
+                    None,
+                ),
+                None => report(
+                    CheckStatus::Warning,
+                    String::from("Could not find 'libyabridge-vst3.so'"),
+                    Some("This version of yabridge was compiled without VST3 support."),
+                ),
+            }
+            match &files.yabridge_host_exe {
+                Some(path) => report(
+                    CheckStatus::Ok,
+                    format!("Found '{}'", utils::display_path(path)),
+                    None,
+                ),
+                None => report(
+                    CheckStatus::Failed,
+                    format!("Could not find '{}'", YABRIDGE_HOST_EXE_NAME),
+                    Some("Try reinstalling yabridge."),
+                ),
+            }
+            match &files.yabridge_host_32_exe {
+                Some(path) => report(
+                    CheckStatus::Ok,
+                    format!("Found '{}'", utils::display_path(path)),
+                    None,
+                ),
+                None => report(
+                    CheckStatus::Warning,
+                    format!("Could not find '{}'", YABRIDGE_HOST_32_EXE_NAME),
+                    Some("32-bit plugins and 32-bit Wine prefixes won't work without it."),
+                ),
+            }
+            let has_host =
+                files.yabridge_host_exe.is_some() || files.yabridge_host_32_exe.is_some();
+            match utils::yabridge_version_mismatch(files) {
+                // There's nothing to compare against without the plugin hosts, and we already
+                // reported those as missing above
+                None if !has_host => (),
+                None => report(
+                    CheckStatus::Ok,
+                    String::from("yabridge's libraries and plugin hosts are from the same version"),
+                    None,
+                ),
+                Some(mismatch) => report(
+                    CheckStatus::Failed,
+                    mismatch,
+                    Some(
+                        "This can happen after a partial upgrade, and plugins may crash in subtle \
+                         ways. Reinstall yabridge so all of its files are from the same version.",
+                    ),
+                ),
+            }
         }
-        Err(err) => {
-            println!("Could not find yabridge's files: {}\n", err);
+        Err(err) => report(
+            CheckStatus::Failed,
+            format!("Could not find yabridge's files: {:#}", err),
+            Some(
+                "Install yabridge to '~/.local/share/yabridge', or use 'yabridgectl set \
+                 --path=<path>' to point yabridgectl to your yabridge installation.",
+            ),
+        ),
+    }
+
+    // The search path only matters for the copy and hard link based installation methods. Any
+    // problems will have been explained in detail by `verify_path_setup()` already.
+    if config.method != InstallationMethod::Symlink {
+        match verify_path_setup(config) {
+            Ok(true) => report(
+                CheckStatus::Ok,
+                format!("'{}' is in the search path", YABRIDGE_HOST_EXE_NAME),
+                None,
+            ),
+            Ok(false) => report(
+                CheckStatus::Failed,
+                format!("'{}' is not in the search path", YABRIDGE_HOST_EXE_NAME),
+                Some("See the warning above for instructions on how to fix this."),
+            ),
+            Err(err) => report(
+                CheckStatus::Failed,
+                format!("Could not check the search path: {}", err),
+                None,
+            ),
         }
     }
-    println!("installation method: {}", config.method);
 
-    for (path, search_results) in results {
-        // Always print these paths with trailing slashes for consistency's sake because paths can
-        // be added both with and without a trailing slash
-        println!("\n{}", path.join("").display());
+    let wine_version = utils::get_wine_version();
+    match &wine_version {
+        Ok(wine_version) => report(CheckStatus::Ok, format!("Found {}", wine_version), None),
+        Err(err) => report(
+            CheckStatus::Failed,
+            err.to_string(),
+            Some(
+                "Install Wine, or use the '--wine' option or $WINELOADER to select the Wine \
+                 binary you want to use.",
+            ),
+        ),
+    }
 
-        for (plugin_path, (plugin, status)) in
-            search_results.installation_status(files.as_ref().ok())
-        {
-            let plugin_type = match plugin {
-                Plugin::Vst2(Vst2Plugin { architecture, .. }) => {
-                    format!("{}, {}", "VST2".cyan(), architecture)
-                }
-                Plugin::Vst3(module) => format!(
-                    "{}, {}, {}",
-                    "VST3".magenta(),
-                    module.type_str(),
-                    module.architecture
+    // These are the same checks `self_test()` performs, but unlike `verify_wine_setup()` this won't
+    // update the last known config
+    if let (Ok(files), Ok(wine_version)) = (&files, &wine_version) {
+        let prefix_architecture = utils::get_default_wine_prefix_arch();
+        match utils::yabridge_host_path(files, prefix_architecture) {
+            Ok(path) => match utils::verify_yabridge_host(path, wine_version) {
+                Ok(true) => report(
+                    CheckStatus::Ok,
+                    format!(
+                        "Ran '{}' in the default {} Wine prefix",
+                        utils::display_path(path),
+                        prefix_architecture
+                    ),
+                    None,
                 ),
-            };
-
-            let status_str = match status {
-                Some(NativeFile::Regular(_)) => "copy".green(),
-                Some(NativeFile::Symlink(_)) => "symlink".green(),
-                Some(NativeFile::Directory(_)) => "invalid".red(),
-                None => "not yet installed".into(),
-            };
-
-            println!(
-                "  {} :: {}, {}",
-                plugin_path
-                    .strip_prefix(path)
-                    .unwrap_or(&plugin_path)
-                    .display(),
-                plugin_type,
-                status_str
-            );
+                Ok(false) => report(
+                    CheckStatus::Failed,
+                    format!("Could not run '{}'", utils::display_path(path)),
+                    Some("See the warning above for instructions on how to fix this."),
+                ),
+                Err(err) => report(
+                    CheckStatus::Failed,
+                    format!("Could not run '{}': {}", utils::display_path(path), err),
+                    Some("Try reinstalling yabridge."),
+                ),
+            },
+            Err(err) => report(
+                CheckStatus::Failed,
+                format!(
+                    "{} for the default {} Wine prefix",
+                    err, prefix_architecture
+                ),
+                Some("Try reinstalling yabridge."),
+            ),
         }
     }
 
+    if num_failed > 0 {
+        return Err(anyhow!(
+            "{} {} failed",
+            num_failed,
+            if num_failed == 1 { "check" } else { "checks" }
+        ));
+    }
+
+    println!("\nEverything seems to be set up correctly");
+
     Ok(())
 }
 
+/// The result of one of the checks performed by `doctor()`.
+enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+}
+
 /// Options passed to `yabridgectl set`, see `main()` for the definitions of these options.
 pub struct SetOptions<'a> {
     pub method: Option<&'a str>,
     pub path: Option<PathBuf>,
+    pub path_candidates: Vec<String>,
     pub path_auto: bool,
+    pub host_exe: Option<PathBuf>,
+    pub host_32_exe: Option<PathBuf>,
+    pub host_auto: bool,
     pub no_verify: Option<bool>,
+    pub no_centralized_warning: Option<bool>,
+    pub quiet_skipped: Option<bool>,
+    pub max_parse_errors: Option<usize>,
+    pub parser: Option<&'a str>,
+    pub flatpak: Option<&'a str>,
+    pub no_flatpak: bool,
+    pub vst3_home: Option<PathBuf>,
+    pub vst3_home_auto: bool,
+    pub lib_arch: Option<&'a str>,
+    pub lib_arch_auto: bool,
+}
+
+/// Print the settings that can be changed with `yabridgectl set` as `key=value` pairs using the
+/// same keys as the config file. Unset values are printed as an empty string, and every candidate
+/// in `yabridge_home_candidates` gets its own line.
+pub fn show_settings(config: &Config) -> Result<()> {
+    let optional_path = |path: &Option<PathBuf>| match path {
+        Some(path) => path.display().to_string(),
+        None => String::new(),
+    };
+
+    println!("method={}", config.method);
+    println!("yabridge_home={}", optional_path(&config.yabridge_home));
+    for candidate in &config.yabridge_home_candidates {
+        println!("yabridge_home_candidates={}", candidate);
+    }
+    println!(
+        "yabridge_host_exe={}",
+        optional_path(&config.yabridge_host_exe)
+    );
+    println!(
+        "yabridge_host_32_exe={}",
+        optional_path(&config.yabridge_host_32_exe)
+    );
+    println!("no_verify={}", config.no_verify);
+    println!("no_centralized_warning={}", config.no_centralized_warning);
+    println!("quiet_skipped={}", config.quiet_skipped);
+    println!(
+        "max_parse_errors={}",
+        config
+            .max_parse_errors
+            .map(|limit| limit.to_string())
+            .unwrap_or_default()
+    );
+    println!("parser={}", config.parser);
+    println!(
+        "flatpak_app_id={}",
+        config.flatpak_app_id.as_deref().unwrap_or_default()
+    );
+    println!("vst3_home={}", optional_path(&config.vst3_home));
+    println!(
+        "lib_architecture={}",
+        match config.lib_architecture {
+            Some(LibArchitecture::Lib32) => "x86",
+            Some(LibArchitecture::Lib64) => "x86_64",
+            None => "",
+        }
+    );
+
+    Ok(())
 }
 
 /// Change configuration settings. The actual options are defined in the clap [app](clap::App).
 pub fn set_settings(config: &mut Config, options: &SetOptions) -> Result<()> {
     match options.method {
         Some("copy") => config.method = InstallationMethod::Copy,
+        Some("hardlink") => config.method = InstallationMethod::HardLink,
         Some("symlink") => config.method = InstallationMethod::Symlink,
         Some(s) => unimplemented!("Unexpected installation method '{}'", s),
         None => (),
@@ -181,30 +1796,427 @@ pub fn set_settings(config: &mut Config, options: &SetOptions) -> Result<()> {
 
     if let Some(path) = &options.path {
         config.yabridge_home = Some(path.clone());
+        config.yabridge_home_candidates.clear();
+    }
+
+    if !options.path_candidates.is_empty() {
+        config.yabridge_home = None;
+        config.yabridge_home_candidates = options.path_candidates.clone();
     }
 
     if options.path_auto {
         config.yabridge_home = None;
+        config.yabridge_home_candidates.clear();
+    }
+
+    if let Some(host_exe) = &options.host_exe {
+        config.yabridge_host_exe = Some(host_exe.clone());
+    }
+
+    if let Some(host_32_exe) = &options.host_32_exe {
+        config.yabridge_host_32_exe = Some(host_32_exe.clone());
+    }
+
+    if options.host_auto {
+        config.yabridge_host_exe = None;
+        config.yabridge_host_32_exe = None;
     }
 
     if let Some(no_verify) = options.no_verify {
         config.no_verify = no_verify;
     }
 
+    if let Some(no_centralized_warning) = options.no_centralized_warning {
+        config.no_centralized_warning = no_centralized_warning;
+    }
+
+    if let Some(quiet_skipped) = options.quiet_skipped {
+        config.quiet_skipped = quiet_skipped;
+    }
+
+    match options.max_parse_errors {
+        Some(0) => config.max_parse_errors = None,
+        Some(max_parse_errors) => config.max_parse_errors = Some(max_parse_errors),
+        None => (),
+    }
+
+    match options.parser {
+        Some("auto") => config.parser = Pe32Parser::Auto,
+        Some("builtin") => config.parser = Pe32Parser::Builtin,
+        Some(s) => unimplemented!("Unexpected parser '{}'", s),
+        None => (),
+    }
+
+    if let Some(app_id) = options.flatpak {
+        config.flatpak_app_id = Some(app_id.to_owned());
+    }
+
+    if options.no_flatpak {
+        config.flatpak_app_id = None;
+    }
+
+    if let Some(vst3_home) = &options.vst3_home {
+        config.vst3_home = Some(vst3_home.clone());
+    }
+
+    if options.vst3_home_auto {
+        config.vst3_home = None;
+    }
+
+    match options.lib_arch {
+        Some("x86") => config.lib_architecture = Some(LibArchitecture::Lib32),
+        Some("x86_64") => config.lib_architecture = Some(LibArchitecture::Lib64),
+        Some(s) => unimplemented!("Unexpected library architecture '{}'", s),
+        None => (),
+    }
+
+    if options.lib_arch_auto {
+        config.lib_architecture = None;
+    }
+
     config.write()
 }
 
+/// Print the config as TOML so it can be imported on another machine using `yabridgectl
+/// import-config`.
+pub fn export_config(config: &Config) -> Result<()> {
+    print!("{}", config.export_toml()?);
+
+    Ok(())
+}
+
+/// Import a config exported using `yabridgectl export-config` from `path`, or from STDIN if `path`
+/// is not set. The imported config is merged into the current config unless `replace` is set.
+/// Relative paths and invalid glob patterns are rejected, but paths that don't exist on this
+/// machine are only warned about so they can be fixed afterwards.
+pub fn import_config(config: &mut Config, path: Option<&Path>, replace: bool) -> Result<()> {
+    let toml_str = match path {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("Could not read '{}'", path.display()))?,
+        None => {
+            let mut toml_str = String::new();
+            io::stdin()
+                .read_to_string(&mut toml_str)
+                .context("Could not read from STDIN")?;
+            toml_str
+        }
+    };
+    let imported: Config =
+        toml::from_str(&toml_str).context("Could not parse the imported config")?;
+
+    // The VST3 directory does not have to exist yet since it will be created during the sync
+    let mut paths: Vec<&Path> = imported
+        .plugin_dirs
+        .iter()
+        .chain(imported.plugin_files.iter())
+        .chain(imported.blacklist.iter())
+        .chain(imported.keep.iter())
+        .chain(imported.yabridge_home.iter())
+        .chain(imported.yabridge_host_exe.iter())
+        .chain(imported.yabridge_host_32_exe.iter())
+        .chain(imported.wine_prefixes.keys())
+        .chain(imported.wine_prefixes.values())
+        .map(|path| path.as_path())
+        .collect();
+    paths.sort();
+    paths.dedup();
+    let relative_paths: Vec<&Path> = paths
+        .iter()
+        .chain(imported.vst3_home.as_deref().iter())
+        .filter(|path| path.is_relative())
+        .cloned()
+        .collect();
+    if !relative_paths.is_empty() {
+        return Err(anyhow!(
+            "The imported config contains relative paths, all paths should be absolute: {}",
+            relative_paths
+                .iter()
+                .map(|path| format!("'{}'", path.display()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    for pattern in &imported.blacklist_patterns {
+        glob::Pattern::new(pattern).with_context(|| {
+            format!(
+                "The imported config contains an invalid pattern '{}'",
+                pattern
+            )
+        })?;
+    }
+
+    let missing_paths: Vec<PathBuf> = paths
+        .into_iter()
+        .filter(|path| !path.exists())
+        .map(Path::to_path_buf)
+        .collect();
+
+    *config = if replace {
+        Config {
+            last_known_config: config.last_known_config.take(),
+            ..imported
+        }
+    } else {
+        config.merge_toml(&toml_str)?
+    };
+    config.write()?;
+
+    if !missing_paths.is_empty() {
+        eprintln!(
+            "{}",
+            utils::wrap(
+                "Warning: The following paths from the imported config don't exist on this \
+                 machine. Use 'yabridgectl rm', 'yabridgectl blacklist rm', or 'yabridgectl keep \
+                 rm' to remove them, or create them before syncing:"
+            )
+        );
+        for path in &missing_paths {
+            eprintln!("- {}", utils::display_path(path));
+        }
+        eprintln!();
+    }
+
+    println!(
+        "Imported the config from {}, rerun 'yabridgectl sync' to set up the imported plugin \
+         locations",
+        match path {
+            Some(path) => format!("'{}'", path.display()),
+            None => String::from("STDIN"),
+        }
+    );
+
+    Ok(())
+}
+
+/// Fix the merged VST3 bundles in `~/.vst3/yabridge` that contain broken symlinks, for instance
+/// because the original plugin has been moved. Broken symlinks are pointed at the plugin's current
+/// location if it can still be found in one of the plugin locations, and they're removed otherwise.
+/// Bundles that no longer contain any Windows VST3 modules after that are removed entirely. Healthy
+/// bundles are left alone. With `dry_run` nothing is changed.
+pub fn repair(config: &Config, dry_run: bool) -> Result<()> {
+    let vst3_home = config.vst3_home();
+    let broken_symlinks = files::index_broken_vst3_symlinks(&vst3_home);
+    if broken_symlinks.is_empty() {
+        println!(
+            "No broken merged VST3 bundles found in '{}'",
+            utils::display_path(&vst3_home)
+        );
+        return Ok(());
+    }
+
+    // These are the paths `yabridgectl sync` would currently symlink the files in the merged
+    // bundles to. Just like during a sync, the first plugin providing a file wins, and the paths
+    // are normalized the same way so the next sync doesn't consider these symlinks outdated.
+    let results = config
+        .search_directories(&[PluginFormat::Vst3], true)
+        .context("Failure while searching for plugins")?;
+    let mut normalized_paths: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut symlink_sources: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for search_results in results.values() {
+        for plugin in &search_results.plugins {
+            if let Plugin::Vst3(module) = plugin {
+                let sources = vec![
+                    (
+                        module.target_windows_module_path(),
+                        Some(module.original_module_path()),
+                    ),
+                    (
+                        module.target_resources_dir(),
+                        module.original_resources_dir(),
+                    ),
+                    (module.target_presets_dir(), module.original_presets_dir()),
+                ];
+                for (target, source) in sources {
+                    if let Some(source) = source {
+                        symlink_sources.entry(target).or_insert_with(|| {
+                            utils::normalize_path_cached(&source, &mut normalized_paths)
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // In a dry run the relinked symlinks will still be broken, so we need to keep track of them to
+    // know which bundles would be left without any Windows modules
+    let mut relinked_symlinks: HashSet<PathBuf> = HashSet::new();
+    let mut affected_bundles: BTreeSet<PathBuf> = BTreeSet::new();
+    let num_broken_symlinks = broken_symlinks.len();
+    for (bundle, symlink) in broken_symlinks {
+        match symlink_sources.get(&symlink) {
+            Some(source) => {
+                // We don't know whether the last sync used `--relative-symlinks`, but the broken
+                // symlink tells us
+                let relative = symlink
+                    .read_link()
+                    .map(|target| target.is_relative())
+                    .unwrap_or(false);
+                let source = symlink_source(relative, source, &symlink);
+
+                println!(
+                    "{} {} -> {}",
+                    if dry_run { "Would relink" } else { "Relinking" },
+                    utils::display_path(&symlink),
+                    utils::display_path(&source)
+                );
+                if !dry_run {
+                    utils::remove_file(&symlink)?;
+                    utils::symlink(&source, &symlink)?;
+                }
+                relinked_symlinks.insert(symlink);
+            }
+            None => {
+                println!(
+                    "{} {}",
+                    if dry_run { "Would remove" } else { "Removing" },
+                    utils::display_path(&symlink)
+                );
+                if !dry_run {
+                    utils::remove_file(&symlink)?;
+                }
+            }
+        }
+
+        affected_bundles.insert(bundle);
+    }
+
+    // A bundle containing only yabridge's library would still show up as a broken plugin
+    let num_affected_bundles = affected_bundles.len();
+    for bundle in affected_bundles {
+        let contains_windows_module = fs::read_dir(bundle.join("Contents"))
+            .map(|arch_dirs| {
+                arch_dirs.filter_map(|entry| entry.ok()).any(|arch_dir| {
+                    arch_dir.file_name().to_string_lossy().ends_with("-win")
+                        && fs::read_dir(arch_dir.path())
+                            .map(|modules| {
+                                modules.filter_map(|entry| entry.ok()).any(|module| {
+                                    module.path().exists()
+                                        || relinked_symlinks.contains(&module.path())
+                                })
+                            })
+                            .unwrap_or(false)
+                })
+            })
+            .unwrap_or(false);
+        if !contains_windows_module {
+            println!(
+                "{} {}, it no longer contains any plugins",
+                if dry_run { "Would remove" } else { "Removing" },
+                utils::display_path(&bundle)
+            );
+            if !dry_run {
+                utils::remove_dir_all(&bundle)?;
+            }
+        }
+    }
+
+    if dry_run {
+        println!("\nThis was a dry run, no files have been changed");
+    } else {
+        println!(
+            "\nFixed {} broken symlinks in {} merged bundles",
+            num_broken_symlinks, num_affected_bundles
+        );
+    }
+
+    Ok(())
+}
+
 /// Options passed to `yabridgectl sync`, see `main()` for the definitions of these options.
 pub struct SyncOptions {
     pub force: bool,
     pub no_verify: bool,
+    pub check_wine_prefix: bool,
+    pub no_centralized_warning: bool,
+    pub quiet_skipped: bool,
+    pub print_plan: bool,
+    pub no_summary: bool,
+    pub relative_symlinks: bool,
+    pub formats: Vec<PluginFormat>,
+    pub no_cache: bool,
     pub prune: bool,
+    /// Only prune leftover files that haven't been modified for at least this long when set.
+    pub prune_older_than: Option<Duration>,
     pub verbose: bool,
+    pub dry_run: bool,
+    pub preserve_timestamps: bool,
+    pub quiet: bool,
+    pub strict: bool,
+    /// Only sync these plugin locations if not empty. Leftover VST3 files in `~/.vst3/yabridge`
+    /// won't be detected in that case since we don't know which files belong to the other
+    /// locations.
+    pub only: Vec<PathBuf>,
+    /// Write a `SyncReport` describing everything the sync did to this file when set.
+    pub report: Option<PathBuf>,
+    /// Don't ask for confirmation before pruning leftover files.
+    pub yes: bool,
+    /// Symlink the user preset directories for bridged VST3 plugins in their Wine prefixes to
+    /// `~/.vst3/presets`.
+    pub link_presets: bool,
+    /// Set up the merged VST3 bundles in this directory instead of in `~/.vst3/yabridge` when set.
+    /// VST2 plugins are skipped, and leftover files are neither detected nor recorded in the
+    /// manifest since this directory is not part of the regular installation.
+    pub output_dir: Option<PathBuf>,
+    /// Set when syncing repeatedly with `yabridgectl sync --watch`. The time of the last sync is then
+    /// only kept in memory, and `watch_sync()` writes it to the config once it stops watching.
+    pub watch: bool,
+}
+
+/// The version of the document written by `yabridgectl sync --report`. This should be incremented
+/// whenever existing fields are changed or removed.
+const SYNC_REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// The document written by `yabridgectl sync --report <file>`. Where `yabridgectl status --json`
+/// describes the current state, this describes the changes made by a single sync. For a dry run
+/// these are the changes that would have been made, and `pruned_files` lists the files that would
+/// have been removed.
+#[derive(Serialize)]
+struct SyncReport {
+    schema_version: u32,
+    dry_run: bool,
+    installation_method: InstallationMethod,
+    /// Plugins we created a new copy or link of `libyabridge-{vst2,vst3}.so` for.
+    new_plugins: Vec<SyncReportPlugin>,
+    /// Plugins that were already set up with the current version of yabridge.
+    up_to_date_plugins: Vec<SyncReportPlugin>,
+    skipped_plugins: Vec<SyncReportSkippedPlugin>,
+    /// `.dll` and `.vst3` files that turned out not to be plugins.
+    skipped_files: Vec<PathBuf>,
+    /// All leftover files that were found, including the ones that were pruned.
+    leftover_files: Vec<PathBuf>,
+    pruned_files: Vec<PathBuf>,
+}
+
+/// A plugin in `SyncReport`. `path` is the Windows plugin, and `installed_path` is the copy of or
+/// the link to `libyabridge-{vst2,vst3}.so` that was set up for it.
+#[derive(Serialize)]
+struct SyncReportPlugin {
+    path: PathBuf,
+    installed_path: PathBuf,
+}
+
+/// A plugin that was skipped during a sync. `reason` is either `unavailable` if the plugin could
+/// not be read, `name_conflict` if another plugin would be set up in the same location, or
+/// `other_wine_prefix` if the same plugin from another Wine prefix has already been set up.
+#[derive(Serialize)]
+struct SyncReportSkippedPlugin {
+    path: PathBuf,
+    reason: &'static str,
 }
 
 /// Set up yabridge for all Windows VST2 plugins in the plugin directories. Will also remove orphan
 /// `.so` files if the prune option is set.
 pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
+    for path in &options.only {
+        if !(config.plugin_dirs.contains(path) || config.plugin_files.contains(path)) {
+            return Err(anyhow!(
+                "'{}' is not a plugin location, use 'yabridgectl list' to list the plugin \
+                 locations",
+                path.display()
+            ));
+        }
+    }
+
     let files: YabridgeFiles = config.files()?;
     let libyabridge_vst2_hash = utils::hash_file(&files.libyabridge_vst2)?;
     let libyabridge_vst3_hash = match &files.libyabridge_vst3 {
@@ -212,18 +2224,82 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
         None => None,
     };
 
-    if let Some((libyabridge_vst3_path, _)) = &files.libyabridge_vst3 {
-        println!("Setting up VST2 and VST3 plugins using:");
-        println!("- {}", files.libyabridge_vst2.display());
-        println!("- {}\n", libyabridge_vst3_path.display());
-    } else {
-        println!("Setting up VST2 plugins using:");
-        println!("- {}\n", files.libyabridge_vst2.display());
+    if !(options.no_summary || options.quiet) {
+        if let Some((libyabridge_vst3_path, _)) = &files.libyabridge_vst3 {
+            println!("Setting up VST2 and VST3 plugins using:");
+            println!("- {}", files.libyabridge_vst2.display());
+            println!("- {}\n", libyabridge_vst3_path.display());
+        } else {
+            println!("Setting up VST2 plugins using:");
+            println!("- {}\n", files.libyabridge_vst2.display());
+        }
     }
 
-    let results = config
-        .search_directories()
+    // Symlinks to yabridge's files won't resolve from within a Flatpak sandbox since those files are
+    // outside of the sandbox
+    if let (Some(app_id), InstallationMethod::Symlink) = (&config.flatpak_app_id, config.method) {
+        eprintln!(
+            "{}\n",
+            utils::wrap(&format!(
+                "Warning: VST3 plugins are being set up for the Flatpak application '{}', but \
+                     the symlink-based installation method is being used. The sandboxed \
+                     application will likely not be able to load these plugins. Use \
+                     'yabridgectl set --method=copy' to switch to the copy-based installation \
+                     method.",
+                app_id
+            ))
+        );
+    }
+
+    // The `.so` files for VST2 plugins need to be placed next to the plugin's `.dll` file, so they
+    // can't be set up in a separate output directory
+    let (formats, vst3_home) = match &options.output_dir {
+        Some(output_dir) => {
+            if options.formats.contains(&PluginFormat::Vst2) && !options.quiet {
+                eprintln!(
+                    "{}\n",
+                    utils::wrap(&format!(
+                        "Warning: Only VST3 plugins will be set up in '{}'. VST2 plugins are \
+                         skipped since yabridge's VST2 plugin libraries need to be placed right \
+                         next to the plugin's '.dll' file.",
+                        utils::display_path(output_dir)
+                    ))
+                );
+            }
+
+            (vec![PluginFormat::Vst3], output_dir.clone())
+        }
+        None => (options.formats.clone(), config.vst3_home()),
+    };
+
+    let only = if options.only.is_empty() {
+        None
+    } else {
+        Some(options.only.as_slice())
+    };
+    let cache = if options.no_cache {
+        None
+    } else {
+        Some(Mutex::new(Pe32Cache::read()))
+    };
+    let (results, failed_locations) = config
+        .search_locations(only, &formats, &vst3_home, cache.as_ref())
         .context("Failure while searching for plugins")?;
+    // Dry runs shouldn't write anything to disk, not even the cache
+    if let (Some(cache), false) = (cache, options.dry_run) {
+        cache.into_inner().unwrap().write_or_warn();
+    }
+
+    if options.print_plan {
+        print_install_plan(
+            config,
+            &files,
+            &results,
+            options.force,
+            libyabridge_vst2_hash,
+            libyabridge_vst3_hash,
+        )?;
+    }
 
     // Keep track of some global statistics
     // The plugin files we installed. This tracks copies of/symlinks to `libabyrdge-*.so` managed.
@@ -232,15 +2308,39 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
     // directory A containing a symlink to plugin directory B, as well as VST3 plugisn that come in
     // both x86 and x86_64 flavours.
     // Paths added to this and to the `new_plugins` set below should be normalized with
-    // `utils::normalize_path()` so that the reported numbers are still correct when encountering
-    // overlapping symlinked paths.
+    // `utils::normalize_path_cached()` so that the reported numbers are still correct when
+    // encountering overlapping symlinked paths.
     let mut managed_plugins: HashSet<PathBuf> = HashSet::new();
+    // Most of the paths we normalize share the same ancestors, so we'll avoid canonicalizing those
+    // over and over again
+    let mut normalized_paths: HashMap<PathBuf, PathBuf> = HashMap::new();
     // The plugins we created a new copy of `libyabridge-{vst2,vst3}.so` for. We don't touch these
     // files if they're already up to date to prevent hosts from unnecessarily rescanning the
     // plugins.
     let mut new_plugins: HashSet<PathBuf> = HashSet::new();
+    // The hashes of the `libyabridge-{vst2,vst3}.so` files used to set up every managed `.so` file.
+    // These are stored in the manifest so `yabridgectl status` can show which copies are outdated.
+    let mut libyabridge_hashes: BTreeMap<PathBuf, i64> = BTreeMap::new();
     // The files we skipped during the scan because they turned out to not be plugins
     let mut skipped_dll_files: Vec<PathBuf> = Vec::new();
+    // Managed .NET assemblies are skipped before parsing, and they're listed separately so they
+    // don't clutter up the list above
+    let mut skipped_managed_files: Vec<PathBuf> = Vec::new();
+    // The errors for skipped files the built-in parser could not parse when it's used instead of
+    // `winedump`. These files are also part of `skipped_dll_files`.
+    let mut parse_errors: HashMap<PathBuf, String> = HashMap::new();
+    // Directories and files we couldn't read because of their permissions. Plugins in these
+    // directories won't have been set up, so we'll always mention these.
+    let mut unreadable_paths: Vec<PathBuf> = Vec::new();
+    // Broken symlinks to plugins and symlinks to plugins on other file systems. Broken symlinks
+    // often point to a drive that's not mounted, and these would otherwise silently be ignored.
+    let mut plugin_symlinks: Vec<PluginSymlink> = Vec::new();
+    // VST3 bundles that also contain a native Linux version of the plugin. These are skipped since
+    // there's no need to use yabridge for them, but we'll mention them so the user knows why.
+    let mut native_vst3_bundles: Vec<PathBuf> = Vec::new();
+    // VST2 plugins whose `.dll` file also exports the VST3 entry point. These are only ever set up
+    // as VST2 plugins, so in verbose mode we'll point out that there may be a VST3 version as well.
+    let mut vst3_capable_vst2_plugins: Vec<PathBuf> = Vec::new();
     // `.so` files and unused VST3 modules we found during scanning that didn't have a corresponding
     // copy or symlink of `libyabridge-vst2.so`
     let mut orphan_files: Vec<NativeFile> = Vec::new();
@@ -250,44 +2350,132 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
     // during the syncing process, so we'll keep track of which VST3 files we touched per-bundle. We
     // can then at the end remove all unkonwn bundles, and all unkonwn files within a bundle.
     let mut known_vst3_files: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
+    // The Windows VST3 modules we symlinked into the merged bundles, mapped to the original module
+    // and the module's Wine prefix. Plugins from different subdirectories outside of a `VST3`
+    // directory end up in the same location in `~/.vst3/yabridge`, so we need this to tell those
+    // apart from the same plugin being installed to multiple Wine prefixes.
+    let mut vst3_module_sources: HashMap<PathBuf, (PathBuf, Option<PathBuf>)> = HashMap::new();
+    // When the `--check-wine-prefix` option is set we'll try to load the first plugin from every
+    // plugin directory in its Wine prefix after syncing
+    let mut prefix_test_plugins: Vec<Plugin> = Vec::new();
+    // The paths to all plugins we set up, used to check whether the Wine plugin host for the
+    // architectures of those plugins' Wine prefixes has been installed
+    let mut plugin_paths: Vec<(PathBuf, LibArchitecture)> = Vec::new();
+    // With `--link-presets` we'll look for preset directories for the bridged VST3 plugins in their
+    // Wine prefixes after syncing. These are the lowercase product and module names of those
+    // plugins, indexed by Wine prefix.
+    let mut vst3_preset_plugins: BTreeMap<PathBuf, BTreeSet<String>> = BTreeMap::new();
+    // Everything we did during the sync, written to a file at the end when `--report` is used
+    let mut report = SyncReport {
+        schema_version: SYNC_REPORT_SCHEMA_VERSION,
+        dry_run: options.dry_run,
+        installation_method: config.method,
+        new_plugins: Vec::new(),
+        up_to_date_plugins: Vec::new(),
+        skipped_plugins: Vec::new(),
+        skipped_files: Vec::new(),
+        leftover_files: Vec::new(),
+        pruned_files: Vec::new(),
+    };
     for (path, search_results) in results {
-        orphan_files.extend(search_results.vst2_orphans().into_iter().cloned());
+        if options.output_dir.is_none() {
+            orphan_files.extend(search_results.vst2_orphans().into_iter().cloned());
+        }
         skipped_dll_files.extend(search_results.skipped_files);
+        skipped_managed_files.extend(search_results.skipped_managed_files);
+        parse_errors.extend(search_results.parse_errors);
+        unreadable_paths.extend(search_results.unreadable_paths);
+        plugin_symlinks.extend(search_results.plugin_symlinks);
+        native_vst3_bundles.extend(search_results.native_vst3_bundles);
+        if options.check_wine_prefix {
+            prefix_test_plugins.extend(search_results.plugins.first().cloned());
+        }
 
+        let (location_path, relative_root) = plugin_location_paths(path);
         if options.verbose {
-            // Always print these paths with trailing slashes for consistency's sake because paths
-            // can be added both with and without a trailing slash
-            println!("{}", path.join("").display());
+            println!("{}", utils::display_path(&location_path));
         }
 
         for plugin in search_results.plugins {
+            let architecture = plugin.architecture();
+            // In verbose mode we'll also show where the plugin will be set up. For VST3 plugins
+            // this depends on the subdirectory of the `VST3` directory the module was found in.
+            let target_description = if options.verbose {
+                Some(match &plugin {
+                    Plugin::Vst2(Vst2Plugin { path, .. }) => {
+                        utils::display_path(&path.with_extension("so"))
+                    }
+                    Plugin::Vst3(module) => format!(
+                        "{} ({})",
+                        utils::display_path(&module.target_bundle_home()),
+                        match &module.subdirectory {
+                            Some(subdirectory) if subdirectory.as_os_str().is_empty() => {
+                                String::from("directly in a VST3 directory")
+                            }
+                            Some(subdirectory) => format!(
+                                "subdirectory '{}' of a VST3 directory",
+                                subdirectory.display()
+                            ),
+                            None => String::from("not inside of a VST3 directory"),
+                        }
+                    ),
+                })
+            } else {
+                None
+            };
             // If verbose mode is enabled we'll print the path to the plugin after setting it up
             let plugin_path: PathBuf = match plugin {
                 // We'll set up the copies or symlinks for VST2 plugins
                 Plugin::Vst2(Vst2Plugin {
-                    path: plugin_path, ..
+                    path: plugin_path,
+                    exports_vst3_entry_point,
+                    ..
                 }) => {
+                    if exports_vst3_entry_point {
+                        vst3_capable_vst2_plugins.push(plugin_path.clone());
+                    }
+
                     let target_path = plugin_path.with_extension("so");
                     let normalized_target_path = if config.method == InstallationMethod::Symlink {
                         // We should probably remove the symlink option altogether, but the count
                         // will at least be soemwhat correct-ish this way
                         target_path.clone()
                     } else {
-                        utils::normalize_path(&target_path)
+                        utils::normalize_path_cached(&target_path, &mut normalized_paths)
                     };
 
                     // Since we skip some files, we'll also keep track of how many new file we've
                     // actually set up
-                    if install_file(
+                    let is_new = install_file(
                         options.force,
+                        options.dry_run,
+                        options.preserve_timestamps,
                         config.method,
                         &files.libyabridge_vst2,
                         Some(libyabridge_vst2_hash),
                         &target_path,
-                    )? {
+                    )?;
+                    if is_new {
                         new_plugins.insert(normalized_target_path.clone());
                     }
-                    managed_plugins.insert(normalized_target_path);
+                    // Overlapping plugin locations can contain the same plugin more than once
+                    if managed_plugins.insert(normalized_target_path) {
+                        let report_plugin = SyncReportPlugin {
+                            path: plugin_path.clone(),
+                            installed_path: target_path.clone(),
+                        };
+                        if is_new {
+                            report.new_plugins.push(report_plugin);
+                        } else {
+                            report.up_to_date_plugins.push(report_plugin);
+                        }
+                    }
+                    libyabridge_hashes.insert(target_path.clone(), libyabridge_vst2_hash);
+                    write_wine_prefix_file(
+                        options.dry_run,
+                        &target_path.with_extension(WINE_PREFIX_FILE_EXTENSION),
+                        config.associated_wine_prefix(&plugin_path),
+                    )?;
 
                     plugin_path.clone()
                 }
@@ -299,6 +2487,27 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
                         continue;
                     }
 
+                    // The Windows module gets symlinked into the merged bundle. If it's stored on
+                    // a network share that has been unmounted since it was indexed, then that
+                    // symlink would be dangling and the plugin would silently fail to load.
+                    if let Err(err) = fs::File::open(module.original_module_path()) {
+                        eprintln!(
+                            "{}",
+                            utils::wrap(&format!(
+                                "Warning: '{}' is not available, skipping this plugin. Make sure \
+                                 the drive it's stored on is mounted and readable: {}\n",
+                                utils::display_path(&module.original_module_path()),
+                                err
+                            ))
+                        );
+                        report.skipped_plugins.push(SyncReportSkippedPlugin {
+                            path: module.original_path().to_owned(),
+                            reason: "unavailable",
+                        });
+
+                        continue;
+                    }
+
                     let target_bundle_home = module.target_bundle_home();
                     let target_native_module_path = module.target_native_module_path(Some(&files));
                     let target_windows_module_path = module.target_windows_module_path();
@@ -308,7 +2517,10 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
                             // count will at least be soemwhat correct-ish this way
                             target_native_module_path.clone()
                         } else {
-                            utils::normalize_path(&target_native_module_path)
+                            utils::normalize_path_cached(
+                                &target_native_module_path,
+                                &mut normalized_paths,
+                            )
                         };
 
                     // 32-bit and 64-bit versions of the plugin can live inside of the same bundle),
@@ -317,7 +2529,62 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
                     let managed_vst3_bundle_files = known_vst3_files
                         .entry(target_bundle_home.clone())
                         .or_insert_with(HashSet::new);
+                    let wine_prefix = config.associated_wine_prefix(module.original_path());
+                    // This is the prefix yabridge will actually run the plugin in, which also
+                    // accounts for plugins installed inside of a Wine prefix
+                    let plugin_wine_prefix =
+                        utils::get_plugin_wine_prefix(config, module.original_path());
                     if managed_vst3_bundle_files.contains(&target_windows_module_path) {
+                        let (existing_module_path, existing_wine_prefix) =
+                            &vst3_module_sources[&target_windows_module_path];
+                        // The same plugin will be found twice when plugin locations overlap, and
+                        // that's not a problem
+                        if utils::normalize_path_cached(existing_module_path, &mut normalized_paths)
+                            == utils::normalize_path_cached(
+                                &module.original_module_path(),
+                                &mut normalized_paths,
+                            )
+                        {
+                            continue;
+                        }
+
+                        if *existing_wine_prefix == plugin_wine_prefix {
+                            // Two different plugins with the same name would otherwise silently
+                            // overwrite each other
+                            eprintln!(
+                                "{}",
+                                utils::wrap(&format!(
+                                    "{}: '{}' and '{}' would both be set up as the {} version of \
+                                     '{}', skipping '{}'\n",
+                                    "WARNING".red(),
+                                    utils::display_path(existing_module_path),
+                                    utils::display_path(&module.original_module_path()),
+                                    module.architecture,
+                                    utils::display_path(&module.target_bundle_home()),
+                                    utils::display_path(&module.original_module_path()),
+                                ))
+                            );
+                            report.skipped_plugins.push(SyncReportSkippedPlugin {
+                                path: module.original_path().to_owned(),
+                                reason: "name_conflict",
+                            });
+
+                            continue;
+                        }
+
+                        report.skipped_plugins.push(SyncReportSkippedPlugin {
+                            path: module.original_path().to_owned(),
+                            reason: "other_wine_prefix",
+                        });
+
+                        // Some users have intentionally set things up this way, so this warning
+                        // can be limited to verbose mode
+                        if (options.no_centralized_warning || config.no_centralized_warning)
+                            && !options.verbose
+                        {
+                            continue;
+                        }
+
                         eprintln!(
                             "{}",
                             utils::wrap(&format!(
@@ -325,42 +2592,87 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
                              prefix, skipping '{}'\n",
                             "WARNING".red(),
                             module.architecture,
-                            module.target_bundle_home().display(),
-                            module.original_module_path().display(),
+                            utils::display_path(&module.target_bundle_home()),
+                            utils::display_path(&module.original_module_path()),
                         ))
                         );
 
                         continue;
                     }
+                    vst3_module_sources.insert(
+                        target_windows_module_path.clone(),
+                        (module.original_module_path(), plugin_wine_prefix),
+                    );
 
                     // We're building a merged VST3 bundle containing both a copy or symlink to
                     // `libyabridge-vst3.so` and the Windows VST3 plugin. The path to this native
                     // module will depend on whether `libyabridge-vst3.so` is a 32-bit or a 64-bit
                     // library file.
-                    utils::create_dir_all(target_native_module_path.parent().unwrap())?;
-                    if install_file(
+                    if !options.dry_run {
+                        utils::create_dir_all(target_native_module_path.parent().unwrap())?;
+                    }
+                    let is_new = install_file(
                         options.force,
+                        options.dry_run,
+                        options.preserve_timestamps,
                         config.method,
                         &files.libyabridge_vst3.as_ref().unwrap().0,
                         libyabridge_vst3_hash,
                         &target_native_module_path,
-                    )? {
+                    )?;
+                    if is_new {
                         // We're counting the native `.so` files and not the Windows VST3 plugins
                         // because even though the 32-bit and 64-bit versions of a plugin are
                         // technically separate plugins, we can only use one at a time anyways
                         // because of how these bundles work
                         new_plugins.insert(normalized_native_module_path.clone());
                     }
-                    managed_plugins.insert(normalized_native_module_path.clone());
+                    if managed_plugins.insert(normalized_native_module_path.clone()) {
+                        let report_plugin = SyncReportPlugin {
+                            path: module.original_path().to_owned(),
+                            installed_path: target_native_module_path.clone(),
+                        };
+                        if is_new {
+                            report.new_plugins.push(report_plugin);
+                        } else {
+                            report.up_to_date_plugins.push(report_plugin);
+                        }
+                    }
+                    libyabridge_hashes.insert(
+                        target_native_module_path.clone(),
+                        libyabridge_vst3_hash.unwrap(),
+                    );
+
+                    let target_wine_prefix_path =
+                        target_native_module_path.with_extension(WINE_PREFIX_FILE_EXTENSION);
+                    write_wine_prefix_file(options.dry_run, &target_wine_prefix_path, wine_prefix)?;
+                    if wine_prefix.is_some() {
+                        managed_vst3_bundle_files.insert(target_wine_prefix_path);
+                    }
                     managed_vst3_bundle_files.insert(target_native_module_path);
 
                     // We'll then symlink the Windows VST3 module to that bundle to create a merged
                     // bundle: https://developer.steinberg.help/display/VST/Plug-in+Format+Structure#PluginFormatStructure-MergedBundle
-                    utils::create_dir_all(target_windows_module_path.parent().unwrap())?;
+                    // Bundles are sometimes stored in one place and symlinked into multiple Wine
+                    // prefixes. These symlinks point to the actual bundle so the merged bundle
+                    // doesn't depend on which of those prefixes we happened to find first, and so
+                    // `index_vst3_location_orphans()` sees the same path we set up here.
+                    if !options.dry_run {
+                        utils::create_dir_all(target_windows_module_path.parent().unwrap())?;
+                    }
                     install_file(
                         true,
+                        options.dry_run,
+                        false,
                         InstallationMethod::Symlink,
-                        &module.original_module_path(),
+                        &symlink_source(
+                            options.relative_symlinks,
+                            &utils::normalize_path_cached(
+                                &module.original_module_path(),
+                                &mut normalized_paths,
+                            ),
+                            &target_windows_module_path,
+                        ),
                         None,
                         &target_windows_module_path,
                     )?;
@@ -368,32 +2680,84 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
 
                     // If `module` is a bundle, then it may contain a `Resources` directory with
                     // screenshots and documentation
-                    // TODO: Also symlink presets, but this is a bit more involved. See
-                    //       https://developer.steinberg.help/display/VST/Preset+Locations
                     if let Some(original_resources_dir) = module.original_resources_dir() {
                         let target_resources_dir = module.target_resources_dir();
 
                         install_file(
+                            false,
+                            options.dry_run,
                             false,
                             InstallationMethod::Symlink,
-                            &original_resources_dir,
+                            &symlink_source(
+                                options.relative_symlinks,
+                                &utils::normalize_path_cached(
+                                    &original_resources_dir,
+                                    &mut normalized_paths,
+                                ),
+                                &target_resources_dir,
+                            ),
                             None,
                             &target_resources_dir,
                         )?;
                         managed_vst3_bundle_files.insert(target_resources_dir);
                     }
 
+                    // Bundles can also come with factory presets in a `Presets` directory. Hosts
+                    // will only pick those up if they're part of the merged bundle. Presets stored
+                    // in the user's preset locations don't need any special handling. See
+                    // https://developer.steinberg.help/display/VST/Preset+Locations
+                    if let Some(original_presets_dir) = module.original_presets_dir() {
+                        let target_presets_dir = module.target_presets_dir();
+
+                        install_file(
+                            false,
+                            options.dry_run,
+                            false,
+                            InstallationMethod::Symlink,
+                            &symlink_source(
+                                options.relative_symlinks,
+                                &utils::normalize_path_cached(
+                                    &original_presets_dir,
+                                    &mut normalized_paths,
+                                ),
+                                &target_presets_dir,
+                            ),
+                            None,
+                            &target_presets_dir,
+                        )?;
+                        managed_vst3_bundle_files.insert(target_presets_dir);
+                    }
+
+                    if options.link_presets {
+                        let wine_prefix =
+                            utils::get_plugin_wine_prefix(config, module.original_path())
+                                .unwrap_or_else(utils::default_wine_prefix);
+                        let plugin_names = vst3_preset_plugins.entry(wine_prefix).or_default();
+                        if let Some(display_name) = &module.display_name {
+                            plugin_names.insert(display_name.to_lowercase());
+                        }
+                        if let Some(module_name) = Path::new(module.original_module_name())
+                            .file_stem()
+                            .and_then(|stem| stem.to_str())
+                        {
+                            plugin_names.insert(module_name.to_lowercase());
+                        }
+                    }
+
                     module.original_path().to_path_buf()
                 }
             };
 
-            if options.verbose {
+            plugin_paths.push((plugin_path.clone(), architecture));
+            if let Some(target_description) = target_description {
                 println!(
-                    "  {}",
-                    plugin_path
-                        .strip_prefix(path)
-                        .unwrap_or(&plugin_path)
-                        .display()
+                    "  {}\n    -> {}",
+                    utils::display_path(
+                        plugin_path
+                            .strip_prefix(relative_root)
+                            .unwrap_or(&plugin_path)
+                    ),
+                    target_description
                 );
             }
         }
@@ -403,79 +2767,237 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
         }
     }
 
-    // We'll print the skipped files all at once to prevetn clutter
-    let num_skipped_files = skipped_dll_files.len();
+    let linked_preset_dirs = if options.link_presets {
+        link_vst3_presets(config, options, &vst3_preset_plugins)?
+    } else {
+        BTreeSet::new()
+    };
+
+    // We'll print the skipped files all at once to prevetn clutter. Some plugins come with a lot of
+    // non-plugin libraries, so the number of skipped files in the summary can also be disabled.
+    // Verbose mode always lists them.
+    let num_skipped_files = skipped_dll_files.len() + skipped_managed_files.len();
+    report
+        .skipped_files
+        .extend(skipped_dll_files.iter().cloned());
+    report
+        .skipped_files
+        .extend(skipped_managed_files.iter().cloned());
+    let report_skipped_files = !(options.quiet_skipped || config.quiet_skipped);
     if options.verbose && !skipped_dll_files.is_empty() {
         println!("Skipped files:");
         for path in skipped_dll_files {
-            println!("- {}", path.display());
+            // Files that don't export any of the VST2 entry points can't be loaded by yabridge, but
+            // preset files next to one of those files may indicate that something's wrong
+            let is_dll_file =
+                path.extension().and_then(|extension| extension.to_str()) == Some("dll");
+            if let Some(err) = parse_errors.get(&path) {
+                println!(
+                    "- {} {}",
+                    utils::display_path(&path),
+                    format!("({})", err).yellow()
+                );
+            } else if is_dll_file && files::has_vst2_preset_siblings(&path) {
+                println!(
+                    "- {} {}",
+                    utils::display_path(&path),
+                    "(does not export a VST2 entry point, but there are VST2 presets next to it)"
+                        .yellow()
+                );
+            } else {
+                println!("- {}", utils::display_path(&path));
+            }
         }
         println!();
     }
-
-    // We want to remove both unmanaged VST3 bundles in `~/.vst3/yabridge` as well as
-    // unmanged files within managed bundles. That's why we'll immediately filter out
-    // kown files within VST3 bundles.
-    // TODO: Move this elsewhere
-    let installed_vst3_bundles = WalkDir::new(yabridge_vst3_home())
-        .follow_links(true)
-        .same_file_system(true)
-        .into_iter()
-        .filter_entry(|entry| entry.file_type().is_dir())
-        .filter_map(|e| e.ok())
-        .filter(|entry| {
-            entry
-                .path()
-                .extension()
-                .and_then(|extension| extension.to_str())
-                == Some("vst3")
-        });
-    for bundle in installed_vst3_bundles {
-        match known_vst3_files.get(bundle.path()) {
-            None => orphan_files.push(NativeFile::Directory(bundle.path().to_owned())),
-            Some(managed_vst3_bundle_files) => {
-                // Find orphan files and symlinks within this bundle. We need this to be able to
-                // switch between 32-bit and 64-bit versions of both yabridge and the Windows plugin
-                orphan_files.extend(
-                    WalkDir::new(bundle.path())
-                        .follow_links(false)
-                        .into_iter()
-                        .filter_map(|e| e.ok())
-                        .filter_map(|entry| {
-                            let managed_file = managed_vst3_bundle_files.contains(entry.path());
-                            match get_file_type(entry.path().to_owned()).unwrap() {
-                                // Don't remove directories, since we're not tracking the
-                                // directories within the bundle
-                                NativeFile::Directory(_) => None,
-                                unknown_file if !managed_file => Some(unknown_file),
-                                _ => None,
-                            }
-                        }),
+    if options.verbose && !skipped_managed_files.is_empty() {
+        println!("Skipped managed .NET assemblies, these are not plugins:");
+        for path in skipped_managed_files {
+            println!("- {}", utils::display_path(&path));
+        }
+        println!();
+    }
+    if !unreadable_paths.is_empty() {
+        if options.verbose {
+            println!("Skipped unreadable files and directories:");
+            for path in &unreadable_paths {
+                println!("- {}", utils::display_path(path));
+            }
+            println!();
+        } else {
+            eprintln!(
+                "{}",
+                utils::wrap(&format!(
+                    "{}: Skipped {} files or directories because of their permissions, rerun with \
+                     the '--verbose' option to list them\n",
+                    "WARNING".red(),
+                    unreadable_paths.len()
+                ))
+            );
+        }
+    }
+    let (broken_symlinks, cross_device_symlinks): (Vec<PluginSymlink>, Vec<PluginSymlink>) =
+        plugin_symlinks
+            .into_iter()
+            .partition(|symlink| matches!(symlink, PluginSymlink::Broken { .. }));
+    if options.verbose && !cross_device_symlinks.is_empty() {
+        println!("Symlinked plugins on other file systems:");
+        for symlink in &cross_device_symlinks {
+            if let PluginSymlink::CrossDevice { path, target } = symlink {
+                println!(
+                    "- {} -> {}",
+                    utils::display_path(path),
+                    utils::display_path(target)
+                );
+            }
+        }
+        println!();
+    }
+    if options.verbose && !vst3_capable_vst2_plugins.is_empty() {
+        println!(
+            "{}",
+            utils::wrap(
+                "VST2 plugins that also export the VST3 entry point. These were set up as VST2 \
+                 plugins, and the VST3 version may be installed as a separate '.vst3' file \
+                 elsewhere:"
+            )
+        );
+        for path in &vst3_capable_vst2_plugins {
+            println!("- {}", utils::display_path(path));
+        }
+        println!();
+    }
+    if !broken_symlinks.is_empty() {
+        // These are always printed in full since the target usually tells you what's wrong
+        eprintln!(
+            "{}",
+            utils::wrap(&format!(
+                "{}: Skipped {} broken symlinks to plugins. The drives these symlinks point to may \
+                 not be mounted:",
+                "WARNING".red(),
+                broken_symlinks.len()
+            ))
+        );
+        for symlink in &broken_symlinks {
+            if let PluginSymlink::Broken { path, target } = symlink {
+                eprintln!(
+                    "- {} -> {}",
+                    utils::display_path(path),
+                    utils::display_path(target)
                 );
             }
         }
+        eprintln!();
     }
+    if !native_vst3_bundles.is_empty() {
+        eprintln!(
+            "{}",
+            utils::wrap(&format!(
+                "Warning: Skipped {} VST3 plugins that are native Linux plugins or that already \
+                 contain a native Linux version. These plugins don't need to be bridged with \
+                 yabridge, you can use them directly by copying or symlinking them to \
+                 '~/.vst3':",
+                native_vst3_bundles.len()
+            ))
+        );
+        for bundle in &native_vst3_bundles {
+            eprintln!("- {}", utils::display_path(bundle));
+        }
+        eprintln!();
+    }
+
+    // We want to remove both unmanaged VST3 bundles in `~/.vst3/yabridge` as well as unmanaged
+    // files within managed bundles. If we have a manifest from the last sync then we can find
+    // these files without having to walk all of `~/.vst3/yabridge`. We'll still do that if there's
+    // no manifest, if the manifest was written for a different VST3 directory, or when the
+    // `--force` option is set. If VST3 plugins were not included in the sync then we don't know
+    // which files are still in use, so we'll leave them alone. The same applies when only some of
+    // the plugin locations were synced using `--only`, or when some of the locations could not be
+    // searched. The output directory used with `--output-dir` is assumed to be fresh, so we won't
+    // look for leftover files there.
+    let sync_vst3 = formats.contains(&PluginFormat::Vst3);
+    let partial_sync = only.is_some() || !failed_locations.is_empty();
+    let previous_manifest = Manifest::read();
+    let vst3_orphans = match previous_manifest
+        .as_ref()
+        .filter(|manifest| !options.force && manifest.describes_vst3_home(&vst3_home))
+    {
+        _ if !sync_vst3 || partial_sync || options.output_dir.is_some() => Vec::new(),
+        Some(manifest) => manifest.vst3_orphans(&known_vst3_files),
+        None => files::index_vst3_orphans(&vst3_home, &known_vst3_files),
+    };
+    orphan_files.extend(vst3_orphans.iter().map(|(_, file)| file.clone()));
+    // Preset links are only managed when `--link-presets` is used, and after a partial sync we
+    // don't know which links belong to the other plugin locations
+    if options.link_presets && !partial_sync {
+        if let Some(manifest) = &previous_manifest {
+            orphan_files.extend(manifest.vst3_preset_link_orphans(&linked_preset_dirs));
+        }
+    }
+    // Files the user explicitly wants to keep are never reported or pruned
+    orphan_files.retain(|file| !config.is_kept(file.path()));
+    // With `--prune-older-than`, leftover files that have been modified recently are left alone
+    // until they're old enough. Files with an unknown modification time can be pruned right away.
+    let (orphan_files, recent_orphan_files): (Vec<NativeFile>, Vec<NativeFile>) =
+        match options.prune_older_than {
+            Some(min_age) if options.prune => {
+                let now = SystemTime::now();
+                orphan_files
+                    .into_iter()
+                    .partition(|file| match file.modified() {
+                        Some(modified) => match now.duration_since(modified) {
+                            Ok(age) => age >= min_age,
+                            // This file was modified in the future, so it's definitely recent
+                            Err(_) => false,
+                        },
+                        None => true,
+                    })
+            }
+            _ => (orphan_files, Vec::new()),
+        };
+
+    // Pruning can't be undone, so we'll ask for confirmation first unless `--yes` was passed
+    let prune = options.prune && (options.dry_run || options.yes || confirm_prune(&orphan_files));
 
     // Always warn about leftover files since those might cause warnings or errors when a VST host
-    // tries to load them
+    // tries to load them. With `--no-summary` or `--quiet` these warnings are printed to STDERR
+    // instead, and files that are being pruned are not listed at all.
+    let print_orphans_line = |line: String| match (options.no_summary || options.quiet, prune) {
+        (false, _) => println!("{}", line),
+        (true, false) => eprintln!("{}", line),
+        (true, true) => (),
+    };
+    report.leftover_files.extend(
+        orphan_files
+            .iter()
+            .chain(recent_orphan_files.iter())
+            .map(|file| file.path().to_owned()),
+    );
     if !orphan_files.is_empty() {
         let leftover_files_str = if orphan_files.len() == 1 {
             format!("{} leftover file", orphan_files.len())
         } else {
             format!("{} leftover files", orphan_files.len())
         };
-        if options.prune {
-            println!("Removing {}:", leftover_files_str);
+        if prune && options.dry_run {
+            print_orphans_line(format!("Would remove {}:", leftover_files_str));
+        } else if prune {
+            print_orphans_line(format!("Removing {}:", leftover_files_str));
+        } else if options.prune {
+            print_orphans_line(format!("Keeping {}:", leftover_files_str));
         } else {
-            println!(
+            print_orphans_line(format!(
                 "Found {}, rerun with the '--prune' option to remove them:",
                 leftover_files_str
-            );
+            ));
         }
 
         for file in orphan_files {
-            println!("- {}", file.path().display());
-            if options.prune {
+            print_orphans_line(format!("- {}", utils::display_path(file.path())));
+            if prune {
+                report.pruned_files.push(file.path().to_owned());
+            }
+            if prune && !options.dry_run {
                 match &file {
                     NativeFile::Regular(path) | NativeFile::Symlink(path) => {
                         utils::remove_file(path)?;
@@ -496,39 +3018,553 @@ pub fn do_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
             }
         }
 
-        println!();
+        print_orphans_line(String::new());
+    }
+
+    if !recent_orphan_files.is_empty() {
+        let print_line = |line: String| {
+            if options.no_summary || options.quiet {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+        };
+
+        print_line(format!(
+            "Keeping {} recently modified leftover files until they're older than the \
+             '--prune-older-than' duration:",
+            recent_orphan_files.len()
+        ));
+        for file in &recent_orphan_files {
+            print_line(format!("- {}", utils::display_path(file.path())));
+        }
+        print_line(String::new());
+    }
+
+    // Orphan files that weren't pruned should still show up during the next sync. The bundles are
+    // only updated when VST3 plugins were part of this sync, and after a partial sync the bundles
+    // from the other plugin locations are kept as is. The manifest only describes the regular
+    // installation, so it's left alone when syncing to another directory with `--output-dir`.
+    if !options.dry_run && options.output_dir.is_none() {
+        let mut manifest = previous_manifest.unwrap_or_default();
+        if sync_vst3 && partial_sync {
+            manifest.merge_vst3_bundles(&vst3_home, &known_vst3_files);
+        } else if sync_vst3 {
+            manifest.set_vst3_bundles(&vst3_home, &known_vst3_files);
+            let unpruned_vst3_orphans: Vec<(PathBuf, NativeFile)> = vst3_orphans
+                .into_iter()
+                .filter(|(_, file)| !prune || recent_orphan_files.contains(file))
+                .collect();
+            manifest.add_orphans(&unpruned_vst3_orphans);
+        }
+        manifest.update_libyabridge_hashes(libyabridge_hashes);
+        if options.link_presets {
+            manifest.update_vst3_preset_links(linked_preset_dirs);
+        }
+        manifest.write()?;
+    }
+
+    let finished_str = if options.dry_run {
+        "Would set up"
+    } else {
+        "Finished setting up"
+    };
+    let summary_str = if report_skipped_files {
+        format!(
+            "{} {} plugins using {} ({} new), skipped {} non-plugin .dll files",
+            finished_str,
+            managed_plugins.len(),
+            config.method.plural_name(),
+            new_plugins.len(),
+            num_skipped_files
+        )
+    } else {
+        format!(
+            "{} {} plugins using {} ({} new)",
+            finished_str,
+            managed_plugins.len(),
+            config.method.plural_name(),
+            new_plugins.len()
+        )
+    };
+    if !(options.no_summary || options.quiet) {
+        println!("{}", summary_str);
+    }
+
+    if let Some(report_path) = &options.report {
+        fs::write(
+            report_path,
+            serde_json::to_string_pretty(&report).context("Could not serialize the sync report")?,
+        )
+        .with_context(|| {
+            format!(
+                "Could not write the sync report to '{}'",
+                report_path.display()
+            )
+        })?;
+    }
+
+    // Nothing has been set up, so there's also nothing to verify yet
+    if options.dry_run {
+        if !options.quiet {
+            println!("This was a dry run, no files have been changed");
+        }
+
+        return Ok(());
+    }
+
+    // With `--quiet` the summary is only printed when one of the checks below fails
+    let result = verify_sync(config, options, &files, &plugin_paths, &prefix_test_plugins);
+    if !matches!(result, Ok(true)) && options.quiet && !options.no_summary {
+        println!("{}", summary_str);
+    }
+
+    // The warnings will have already been printed, but with `--strict` they should also cause
+    // yabridgectl to exit with a non-zero exit code
+    let verified = result?;
+    if !verified && options.strict {
+        return Err(ExitCodeError::VerificationFailed.into());
+    }
+
+    // Only full syncs of the regular installation that passed the setup checks count as the last
+    // sync, even if nothing had to be changed. `yabridgectl sync --watch` syncs over and over
+    // again, so in that case the config is only written once we stop watching.
+    let full_sync = !partial_sync
+        && options.output_dir.is_none()
+        && PluginFormat::ALL
+            .iter()
+            .all(|format| options.formats.contains(format));
+    if verified && full_sync {
+        config.set_last_sync_time();
+        if !options.watch {
+            config.write()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The number of leftover files listed when asking for confirmation before pruning. The full list
+/// is printed afterwards.
+const PRUNE_CONFIRMATION_EXAMPLES: usize = 5;
+
+/// Ask the user whether the leftover files in `orphan_files` should be removed. When STDIN is not a
+/// terminal, for instance when yabridgectl is run from a script, we can't ask and the files are
+/// kept. Returns `true` if the files should be removed.
+fn confirm_prune(orphan_files: &[NativeFile]) -> bool {
+    if orphan_files.is_empty() {
+        return true;
+    }
+
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+        eprintln!(
+            "{}\n",
+            utils::wrap(&format!(
+                "Warning: Not removing {} leftover files since there's no terminal to ask for \
+                 confirmation. Pass the '--yes' option to 'yabridgectl sync --prune' to remove \
+                 them without asking.",
+                orphan_files.len()
+            ))
+        );
+
+        return false;
     }
 
     println!(
-        "Finished setting up {} plugins using {} ({} new), skipped {} non-plugin .dll files",
-        managed_plugins.len(),
-        config.method.plural_name(),
-        new_plugins.len(),
-        num_skipped_files
+        "Found {} leftover files to remove, including:",
+        orphan_files.len()
     );
+    for file in orphan_files.iter().take(PRUNE_CONFIRMATION_EXAMPLES) {
+        println!("- {}", utils::display_path(file.path()));
+    }
+    if orphan_files.len() > PRUNE_CONFIRMATION_EXAMPLES {
+        println!(
+            "- and {} more",
+            orphan_files.len() - PRUNE_CONFIRMATION_EXAMPLES
+        );
+    }
+
+    let confirmed = matches!(
+        promptly::prompt_opt::<String, &str>(
+            "\nWould you like to remove these files? Entering anything other than YES will leave \
+             these files intact",
+        ),
+        Ok(Some(answer)) if answer == "YES"
+    );
+    println!();
+
+    confirmed
+}
+
+/// Run `do_sync()`, and then keep syncing again whenever `.dll` or `.vst3` files are added to,
+/// changed in, or removed from the plugin locations until the user presses Ctrl+C. The plugin
+/// locations are watched using inotify, and we'll wait for the files to stop changing for
+/// `SYNC_WATCH_DELAY` before syncing so we don't sync in the middle of a plugin's installation.
+/// Pressing Ctrl+C during a sync will let that sync finish first, and pressing it again exits
+/// immediately.
+pub fn watch_sync(config: &mut Config, options: &SyncOptions) -> Result<()> {
+    utils::catch_interrupts();
+
+    do_sync(config, options)?;
+
+    // The debounced watcher only sends events once the files haven't been changed for a while
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::watcher(sender, SYNC_WATCH_DELAY)
+        .context("Could not set up file system notifications")?;
+    for path in config.plugin_dirs.iter().chain(config.plugin_files.iter()) {
+        // Plugin locations on drives that aren't mounted shouldn't stop us from watching the others
+        if let Err(err) = watcher.watch(path, RecursiveMode::Recursive) {
+            eprintln!(
+                "{}",
+                utils::wrap(&format!(
+                    "Warning: Could not watch '{}' for changes: {}",
+                    utils::display_path(path),
+                    err
+                ))
+            );
+        }
+    }
+    if !options.quiet {
+        println!("\nWatching the plugin locations for changes, press Ctrl+C to stop");
+    }
+
+    // `do_sync()` needs mutable access to the config, so we can't borrow from it here
+    let blacklist = config.blacklist.clone();
+    let (_, blacklist_patterns) = config.blacklist_filters();
+    let is_blacklisted = |path: &Path| {
+        blacklist.iter().any(|entry| path.starts_with(entry))
+            || blacklist_patterns
+                .iter()
+                .any(|pattern| pattern.matches_path(path))
+    };
+    let result = loop {
+        let event = match receiver.recv_timeout(INTERRUPT_POLL_INTERVAL) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) if utils::interrupted() => break Ok(()),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                break Err(anyhow!("Stopped receiving file system notifications"));
+            }
+        };
+        let changed_paths: Vec<&Path> = match &event {
+            DebouncedEvent::Create(path)
+            | DebouncedEvent::Write(path)
+            | DebouncedEvent::Chmod(path)
+            | DebouncedEvent::Remove(path) => vec![path],
+            DebouncedEvent::Rename(from, to) => vec![from, to],
+            // Notifications have been lost, so anything could have changed
+            DebouncedEvent::Rescan => vec![],
+            DebouncedEvent::Error(err, path) => {
+                eprintln!("Error while watching for changes: {}", err);
+                path.iter().map(|path| path.as_path()).collect()
+            }
+            DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => continue,
+        };
+        if !changed_paths.is_empty()
+            && !changed_paths
+                .iter()
+                .any(|path| is_plugin_change(path) && !is_blacklisted(path))
+        {
+            continue;
+        }
+
+        // The other events from the same batch of changes would otherwise trigger another sync
+        while receiver.try_recv().is_ok() {}
+
+        if !options.quiet {
+            println!("\nThe plugin locations have changed, syncing again");
+        }
+
+        // A failed sync shouldn't stop us from watching for the changes that may fix it
+        if let Err(err) = do_sync(config, options) {
+            eprintln!("Error: {:?}", err);
+        }
+        if utils::interrupted() {
+            break Ok(());
+        }
+
+        // The files changed by the sync itself should not cause another sync
+        while receiver.try_recv().is_ok() {}
+    };
+
+    // The time of the last successful sync was only updated in memory while watching
+    config.write()?;
+
+    result
+}
+
+/// Whether a change to `path` in a plugin location could affect which plugins get set up. The
+/// `.so` files we set up next to VST2 plugins also live in the plugin locations, so those and other
+/// regular files should be ignored. Directories are included since removing or moving a directory
+/// only results in a single event for that directory. Paths that no longer exist are included
+/// unless they're obviously not a plugin or a directory.
+fn is_plugin_change(path: &Path) -> bool {
+    match path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_lowercase())
+        .as_deref()
+    {
+        Some("dll") | Some("vst3") => true,
+        _ if path.is_dir() => true,
+        Some(_) => false,
+        None => !path.exists(),
+    }
+}
 
+/// Check whether the plugins set up by `do_sync()` can actually be loaded. These checks can be
+/// skipped with the `--no-verify` option or through the corresponding config file option. Returns
+/// `false` if one of the checks printed a warning.
+fn verify_sync(
+    config: &mut Config,
+    options: &SyncOptions,
+    files: &YabridgeFiles,
+    plugin_paths: &[(PathBuf, LibArchitecture)],
+    prefix_test_plugins: &[Plugin],
+) -> Result<bool> {
     // Skipping the post-installation seting checks can be done only for this invocation of
     // `yabridgectl sync`, or it can be skipped permanently through a config file option
     if options.no_verify || config.no_verify {
-        return Ok(());
+        return Ok(true);
+    }
+
+    let mut success = true;
+
+    // The path setup is to make sure that the `libyabridge-{vst2,vst3}.so` copies or hard links can
+    // find `yabridge-host.exe`
+    if config.method != InstallationMethod::Symlink {
+        success &= verify_path_setup(config)?;
+    }
+
+    // A partial upgrade can leave files from an older version of yabridge behind
+    if let Some(mismatch) = utils::yabridge_version_mismatch(files) {
+        eprintln!(
+            "\n{}",
+            utils::wrap(&format!(
+                "{}: {}. This can happen after a partial upgrade, and plugins may crash in subtle \
+                 ways. Reinstall yabridge so all of its files are from the same version.\n",
+                "WARNING".red(),
+                mismatch
+            ))
+        );
+        success = false;
     }
 
-    // The path setup is to make sure that the `libyabridge-{vst2,vst3}.so` copies can find
-    // `yabridge-host.exe`
-    if config.method == InstallationMethod::Copy {
-        verify_path_setup(config)?;
+    // The checks below use the pinned plugin host binaries, but yabridge itself doesn't know about
+    // those
+    for (pinned, found) in config.mismatched_pinned_hosts(files) {
+        eprintln!(
+            "\n{}",
+            utils::wrap(&format!(
+                "{}: '{}' was pinned using 'yabridgectl set', but yabridge will {}. Pinned plugin \
+                 hosts are only used for yabridgectl's own checks. Make sure yabridge can find \
+                 this binary through the search path, or use 'yabridgectl set --host-auto'.\n",
+                "WARNING".red(),
+                pinned.display(),
+                match found {
+                    Some(found) => format!("use '{}' instead", found.display()),
+                    None => String::from("not be able to find a plugin host at all"),
+                }
+            ))
+        );
+        success = false;
+    }
+
+    // 32-bit plugins and plugins installed in 32-bit Wine prefixes need the 32-bit version of the
+    // Wine plugin host
+    let host_architectures = verify_wine_prefix_architectures(
+        config,
+        files,
+        plugin_paths
+            .iter()
+            .map(|(path, architecture)| (path.as_path(), *architecture)),
+    );
+
+    // This check is only performed once per combination of Wine and yabridge versions and the
+    // needed plugin hosts
+    success &= verify_wine_setup(config, &host_architectures)?;
+
+    if options.check_wine_prefix {
+        for plugin in prefix_test_plugins {
+            success &= verify_wine_prefix(config, files, plugin)?;
+        }
+    }
+
+    Ok(success)
+}
+
+/// Print the action `do_sync()` is going to take for every plugin as a table, along with the
+/// plugin's path and the path to the file or merged VST3 bundle that will be set up for it. This
+/// doesn't change anything.
+fn print_install_plan(
+    config: &Config,
+    files: &YabridgeFiles,
+    results: &BTreeMap<&Path, SearchResults>,
+    force: bool,
+    libyabridge_vst2_hash: i64,
+    libyabridge_vst3_hash: Option<i64>,
+) -> Result<()> {
+    let install_action =
+        |from: &Path, from_hash: Option<i64>, to: &Path| -> Result<ColoredString> {
+            if !force && file_is_up_to_date(config.method, from, from_hash, to)? {
+                Ok("up to date".normal())
+            } else {
+                Ok(config.method.to_string().green())
+            }
+        };
+
+    // VST3 plugins provided by multiple Wine prefixes get skipped during the sync, so we'll need to
+    // keep track of those in the same way
+    let mut planned_vst3_modules: HashSet<PathBuf> = HashSet::new();
+    let mut plan: Vec<(ColoredString, PathBuf, PathBuf)> = Vec::new();
+    for search_results in results.values() {
+        for plugin in &search_results.plugins {
+            match plugin {
+                Plugin::Vst2(Vst2Plugin { path, .. }) => {
+                    let target_path = path.with_extension("so");
+                    let action = install_action(
+                        &files.libyabridge_vst2,
+                        Some(libyabridge_vst2_hash),
+                        &target_path,
+                    )?;
+
+                    plan.push((action, path.clone(), target_path));
+                }
+                Plugin::Vst3(module) => {
+                    let action = match &files.libyabridge_vst3 {
+                        None => "skip (no VST3 support)".red(),
+                        Some(_)
+                            if !planned_vst3_modules
+                                .insert(module.target_windows_module_path()) =>
+                        {
+                            "skip (duplicate)".red()
+                        }
+                        Some((libyabridge_vst3_path, _)) => install_action(
+                            libyabridge_vst3_path,
+                            libyabridge_vst3_hash,
+                            &module.target_native_module_path(Some(files)),
+                        )?,
+                    };
+
+                    plan.push((
+                        action,
+                        module.original_path().to_owned(),
+                        module.target_bundle_home(),
+                    ));
+                }
+            }
+        }
     }
 
-    // This check is only performed once per combination of Wine and yabridge versions
-    verify_wine_setup(config)?;
+    // `ColoredString`'s length doesn't include the escape codes, so we'll pad these manually
+    let action_width = plan
+        .iter()
+        .map(|(action, _, _)| action.len())
+        .max()
+        .unwrap_or(0);
+    println!("Installation plan:");
+    for (action, plugin_path, target_path) in plan {
+        println!(
+            "  {}{}  {} -> {}",
+            action,
+            " ".repeat(action_width - action.len()),
+            utils::display_path(&plugin_path),
+            utils::display_path(&target_path)
+        );
+    }
+    println!();
 
     Ok(())
 }
 
-/// Create a copy or symlink of `from` to `to`. Depending on `force`, we might not actually create a
-/// new copy or symlink if `to` matches `from_hash`.
+/// Get the path a plugin location should be printed as, and the path the plugins in that location
+/// should be printed relative to. Directories are always printed with trailing slashes for
+/// consistency's sake because they can be added both with and without a trailing slash. Individual
+/// plugin files are printed relative to the directory they're in.
+fn plugin_location_paths(path: &Path) -> (PathBuf, &Path) {
+    if path.is_file() {
+        (path.to_owned(), path.parent().unwrap_or(path))
+    } else {
+        (path.join(""), path)
+    }
+}
+
+/// Symlink the user preset directories for the bridged VST3 plugins from their Wine prefixes to
+/// `~/.vst3/presets/<vendor>/<plugin>` for `yabridgectl sync --link-presets`. `plugin_names`
+/// contains the lowercase names of the bridged VST3 plugins indexed by their Wine prefix, and a
+/// preset directory is only linked if its name matches one of those plugins. Existing files and
+/// directories in `~/.vst3/presets` are left alone. Returns the symlinks that are now managed by
+/// yabridgectl.
+fn link_vst3_presets(
+    config: &Config,
+    options: &SyncOptions,
+    plugin_names: &BTreeMap<PathBuf, BTreeSet<String>>,
+) -> Result<BTreeSet<PathBuf>> {
+    let presets_home = vst3_presets_home(config.flatpak_app_id.as_deref());
+    let mut linked_preset_dirs = BTreeSet::new();
+    for (wine_prefix, plugin_names) in plugin_names {
+        for (vendor, plugin, preset_dir) in files::wine_vst3_preset_dirs(wine_prefix) {
+            if !plugin_names.contains(&plugin.to_lowercase()) {
+                continue;
+            }
+
+            let target_path = presets_home.join(&vendor).join(&plugin);
+            match utils::get_file_type(target_path.clone()) {
+                None => {
+                    if !options.dry_run {
+                        utils::create_dir_all(target_path.parent().unwrap())?;
+                    }
+                    install_file(
+                        false,
+                        options.dry_run,
+                        false,
+                        InstallationMethod::Symlink,
+                        &symlink_source(options.relative_symlinks, &preset_dir, &target_path),
+                        None,
+                        &target_path,
+                    )?;
+                    if options.verbose {
+                        println!(
+                            "Linked VST3 presets: {}\n    -> {}",
+                            utils::display_path(&preset_dir),
+                            utils::display_path(&target_path)
+                        );
+                    }
+                }
+                // This link may have been created by an earlier sync
+                Some(NativeFile::Symlink(_))
+                    if fs::canonicalize(&target_path).ok()
+                        == fs::canonicalize(&preset_dir).ok() => {}
+                // The user's own presets should never be touched
+                Some(_) => continue,
+            }
+
+            linked_preset_dirs.insert(target_path);
+        }
+    }
+
+    Ok(linked_preset_dirs)
+}
+
+/// Get the path a symlink at `to` should point to in order to link to `from`. If `relative` is set,
+/// then this will be a path relative to the directory `to` is in so the symlink keeps working when
+/// both files are moved together.
+fn symlink_source(relative: bool, from: &Path, to: &Path) -> PathBuf {
+    match (relative, to.parent()) {
+        (true, Some(to_dir)) => utils::relative_path(from, to_dir),
+        _ => from.to_path_buf(),
+    }
+}
+
+/// Create a copy, symlink, or hard link of `from` to `to`. Depending on `force`, we might not
+/// actually create a new file if `to` is already up to date. If `dry_run` is set, then we'll only
+/// print what we would have done, and the return value will be the same as if the file had been
+/// installed. If `preserve_timestamps` is set, then copies that replace an existing file will keep
+/// that file's modification time.
 fn install_file(
     force: bool,
+    dry_run: bool,
+    preserve_timestamps: bool,
     method: InstallationMethod,
     from: &Path,
     from_hash: Option<i64>,
@@ -539,39 +3575,110 @@ fn install_file(
     // exists and does not require updating, we'll just skip the file since some DAWs will otherwise
     // unnecessarily reindex the file. We check `std::fs::symlink_metadata` instead of
     // `Path::exists()` because the latter reports false for broken symlinks.
-    if let Ok(metadata) = fs::symlink_metadata(&to) {
-        match (force, &method) {
-            (false, InstallationMethod::Copy) => {
-                // If the target file is already a real file (not a symlink) and its hash is the
-                // same as that of the `from` file we're trying to copy there, then we don't have to
-                // do anything
-                if let Some(hash) = from_hash {
-                    if metadata.file_type().is_file() && utils::hash_file(to)? == hash {
-                        return Ok(false);
-                    }
-                }
-            }
-            (false, InstallationMethod::Symlink) => {
-                // If the target file is already a symlink to `from`, then we can skip this file
-                if metadata.file_type().is_symlink() && to.read_link()? == from {
-                    return Ok(false);
-                }
-            }
-            // With the force option we always want to recreate existing .so files
-            (true, _) => (),
+    let mut original_mtime = None;
+    if let Ok(metadata) = fs::symlink_metadata(to) {
+        // With the force option we always want to recreate existing .so files
+        if !force && file_is_up_to_date(method, from, from_hash, to)? {
+            return Ok(false);
+        }
+
+        // Some DAWs will rescan every plugin with a changed modification time, even though updating
+        // yabridge doesn't change anything about the plugins themselves
+        if preserve_timestamps && metadata.file_type().is_file() {
+            original_mtime = Some(FileTime::from_last_modification_time(&metadata));
         }
 
-        utils::remove_file(&to)?;
+        if !dry_run {
+            utils::remove_file(to)?;
+        }
     };
 
+    if dry_run {
+        println!(
+            "Would {} '{}' to '{}'",
+            method,
+            utils::display_path(from),
+            utils::display_path(to)
+        );
+
+        return Ok(true);
+    }
+
     match method {
         InstallationMethod::Copy => {
             utils::copy_or_reflink(from, to)?;
+            if let Some(mtime) = original_mtime {
+                filetime::set_file_mtime(to, mtime).with_context(|| {
+                    format!("Could not set the modification time of '{}'", to.display())
+                })?;
+            }
         }
         InstallationMethod::Symlink => {
             utils::symlink(from, to)?;
         }
+        // Hard links share their modification time with `from`, so there's nothing to preserve
+        InstallationMethod::HardLink => {
+            utils::hard_link(from, to)?;
+        }
     }
 
     Ok(true)
 }
+
+/// Write the path to the Wine prefix associated with a plugin to `path`, so yabridge will use that
+/// prefix for the plugin. If there's no associated Wine prefix, then any existing file at `path` will
+/// be removed instead. The file is only written when its contents change.
+fn write_wine_prefix_file(dry_run: bool, path: &Path, wine_prefix: Option<&Path>) -> Result<()> {
+    let contents = wine_prefix.map(|prefix| format!("{}\n", prefix.display()));
+    let current_contents = fs::read_to_string(path).ok();
+    if contents == current_contents {
+        return Ok(());
+    }
+
+    match (dry_run, contents) {
+        (true, Some(_)) => println!("Would write '{}'", utils::display_path(path)),
+        (true, None) => println!("Would remove '{}'", utils::display_path(path)),
+        (false, Some(contents)) => fs::write(path, contents)
+            .with_context(|| format!("Could not write '{}'", path.display()))?,
+        (false, None) => utils::remove_file(path)?,
+    }
+
+    Ok(())
+}
+
+/// Check whether `to` is already an up to date copy of, symlink to, or hard link to `from`, in
+/// which case `install_file()` doesn't have to do anything. Returns `false` if `to` does not exist.
+fn file_is_up_to_date(
+    method: InstallationMethod,
+    from: &Path,
+    from_hash: Option<i64>,
+    to: &Path,
+) -> Result<bool> {
+    let metadata = match fs::symlink_metadata(to) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(false),
+    };
+
+    match method {
+        InstallationMethod::Copy => {
+            // If the target file is already a real file (not a symlink) and its hash is the same as
+            // that of the `from` file we're trying to copy there, then we don't have to do anything.
+            // Hard links created with the hard link installation method should be replaced though.
+            match from_hash {
+                Some(hash) => Ok(metadata.file_type().is_file()
+                    && !utils::is_same_file(from, to)?
+                    && utils::hash_file(to)? == hash),
+                None => Ok(false),
+            }
+        }
+        InstallationMethod::Symlink => {
+            // If the target file is already a symlink to `from`, then we can skip this file
+            Ok(metadata.file_type().is_symlink() && to.read_link()? == from)
+        }
+        InstallationMethod::HardLink => {
+            // Hard links are the same file as `from`, so we don't need to compare their contents.
+            // Updating yabridge replaces its files, in which case the inodes will no longer match.
+            Ok(metadata.file_type().is_file() && utils::is_same_file(from, to)?)
+        }
+    }
+}
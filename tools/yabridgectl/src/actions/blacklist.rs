@@ -17,9 +17,12 @@
 //! Handlers for the blacklist subcommands, just to keep `main.rs` clean.
 
 use anyhow::Result;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
 use crate::config::Config;
+use crate::files::{self, NativeFile};
+use crate::utils;
 
 /// Add a path to the blacklist. Duplicates get ignord because we're using ordered sets.
 pub fn add_path(config: &mut Config, path: PathBuf) -> Result<()> {
@@ -27,25 +30,74 @@ pub fn add_path(config: &mut Config, path: PathBuf) -> Result<()> {
     config.write()
 }
 
-/// Remove a path from the blacklist. The path is assumed to be part of `config.blacklist`,
-/// otherwise this is silently ignored.
-pub fn remove_path(config: &mut Config, path: &Path) -> Result<()> {
-    // We've already verified that this path is in `config.blacklist`
-    config.blacklist.remove(path);
+/// Ask the user whether they want to remove the files yabridgectl has already set up for the plugins
+/// in the newly blacklisted `paths`. This includes the `.so` files next to the plugins and the
+/// merged VST3 bundles in `~/.vst3/yabridge`. These files would otherwise stick around until the
+/// next `yabridgectl sync --prune`.
+pub fn remove_managed_files(config: &Config, paths: &[PathBuf], quiet: bool) -> Result<()> {
+    let orphan_files: Vec<NativeFile> = paths
+        .iter()
+        .flat_map(|path| {
+            if path.is_dir() {
+                files::index(path, &HashSet::new(), &[], None).so_files
+            } else {
+                files::index_file(path).so_files
+            }
+        })
+        .collect();
+    let locations: Vec<&Path> = paths.iter().map(|path| path.as_path()).collect();
+
+    super::remove_orphan_files(
+        config,
+        orphan_files,
+        &locations,
+        "the blacklisted paths",
+        quiet,
+    )
+}
+
+/// Add a glob pattern to the blacklist. The pattern should already have been validated.
+pub fn add_pattern(config: &mut Config, pattern: &str) -> Result<()> {
+    config.blacklist_patterns.insert(pattern.to_owned());
+    config.write()
+}
+
+/// Remove a path or a glob pattern from the blacklist. The entry is assumed to be part of either
+/// `config.blacklist` or `config.blacklist_patterns`, otherwise this is silently ignored.
+pub fn remove_entry(config: &mut Config, entry: &str) -> Result<()> {
+    // We've already verified that this entry is in one of the two sets
+    if !config.blacklist_patterns.remove(entry) {
+        config.blacklist.remove(Path::new(entry));
+    }
     config.write()
 }
 
-/// List the paths in the blacklist.
-pub fn list_paths(config: &Config) -> Result<()> {
+/// List the paths and glob patterns in the blacklist. Glob patterns are marked as such. If
+/// `null_separated` is set, then the entries are printed as is and separated by null bytes.
+pub fn list_paths(config: &Config, null_separated: bool) -> Result<()> {
+    if null_separated {
+        return utils::print_null_separated(
+            config
+                .blacklist
+                .iter()
+                .map(|path| path.as_path())
+                .chain(config.blacklist_patterns.iter().map(Path::new)),
+        );
+    }
+
     for directory in &config.blacklist {
-        println!("{}", directory.display());
+        println!("{}", utils::display_path(directory));
+    }
+    for pattern in &config.blacklist_patterns {
+        println!("{} (glob pattern)", pattern);
     }
 
     Ok(())
 }
 
-/// Clear the entire blacklist.
+/// Clear the entire blacklist, including all glob patterns.
 pub fn clear(config: &mut Config) -> Result<()> {
     config.blacklist.clear();
+    config.blacklist_patterns.clear();
     config.write()
 }
@@ -0,0 +1,57 @@
+// yabridge: a Wine VST bridge
+// Copyright (C) 2020-2021 Robbert van der Helm
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Handlers for the keep list subcommands, just to keep `main.rs` clean.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::utils;
+
+/// Add a path to the keep list. Duplicates get ignored because we're using ordered sets.
+pub fn add_path(config: &mut Config, path: PathBuf) -> Result<()> {
+    config.keep.insert(path);
+    config.write()
+}
+
+/// Remove a path from the keep list. The path is assumed to be part of `config.keep`,
+/// otherwise this is silently ignored.
+pub fn remove_path(config: &mut Config, path: &Path) -> Result<()> {
+    // We've already verified that this path is in `config.keep`
+    config.keep.remove(path);
+    config.write()
+}
+
+/// List the paths in the keep list. If `null_separated` is set, then the paths are printed as is
+/// and separated by null bytes.
+pub fn list_paths(config: &Config, null_separated: bool) -> Result<()> {
+    if null_separated {
+        return utils::print_null_separated(config.keep.iter().map(|path| path.as_path()));
+    }
+
+    for directory in &config.keep {
+        println!("{}", utils::display_path(directory));
+    }
+
+    Ok(())
+}
+
+/// Clear the entire keep list.
+pub fn clear(config: &mut Config) -> Result<()> {
+    config.keep.clear();
+    config.write()
+}
@@ -19,19 +19,36 @@
 use anyhow::{anyhow, Context, Result};
 use rayon::prelude::*;
 use serde_derive::{Deserialize, Serialize};
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::fmt::Display;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
 use which::which;
 use xdg::BaseDirectories;
 
-use crate::files::{self, LibArchitecture, SearchResults};
-use crate::utils;
+use crate::files::{self, LibArchitecture, NativeFile, Pe32Info, PluginFormat, SearchResults};
+use crate::utils::{self, Progress};
 
 /// The name of the config file, relative to `$XDG_CONFIG_HOME/YABRIDGECTL_PREFIX`.
 pub const CONFIG_FILE_NAME: &str = "config.toml";
+/// An environment variable that can be set to the path of a config file to use instead of
+/// `$XDG_CONFIG_HOME/yabridgectl/config.toml`. Useful for testing and for keeping multiple
+/// independent configurations around. The manifest and the cache are then also stored next to
+/// that file, see `override_file_path()`.
+const CONFIG_PATH_ENV_VAR: &str = "YABRIDGECTL_CONFIG";
+/// The name of the file yabridgectl uses to keep track of the files it has set up in
+/// `~/.vst3/yabridge`, relative to `$XDG_DATA_HOME/YABRIDGECTL_PREFIX`.
+pub const MANIFEST_FILE_NAME: &str = "manifest.toml";
+/// The name of the file yabridgectl uses to cache the information parsed from `.dll` and `.vst3`
+/// files, relative to `$XDG_CACHE_HOME/YABRIDGECTL_PREFIX`.
+pub const PE32_CACHE_FILE_NAME: &str = "pe32-cache.toml";
+/// The version of the `Pe32Cache` format. This should be incremented whenever `Pe32Info` gains new
+/// information, so entries cached by older versions of yabridgectl are parsed again instead of
+/// missing that information.
+const PE32_CACHE_VERSION: u32 = 1;
 /// The name of the XDG base directory prefix for yabridgectl, relative to `$XDG_CONFIG_HOME` and
 /// `$XDG_DATA_HOME`.
 const YABRIDGECTL_PREFIX: &str = "yabridgectl";
@@ -54,10 +71,21 @@ const YABRIDGE_PREFIX: &str = "yabridge";
 /// in a subdirectory we can easily clean up any orphan files without interfering with other native
 /// plugins.
 const YABRIDGE_VST3_HOME: &str = ".vst3/yabridge";
+/// The path relative to `$HOME` where Linux VST3 hosts look for user presets, organized as
+/// `<vendor>/<plugin>`. See https://developer.steinberg.help/display/VST/Preset+Locations.
+const VST3_PRESETS_HOME: &str = ".vst3/presets";
+/// The path relative to `$HOME` containing the per-application data directories for Flatpak
+/// applications. A Flatpak application that persists `~/.vst3` will see
+/// `~/.var/app/<app-id>/.vst3` as its `~/.vst3` directory.
+pub const FLATPAK_APPS_HOME: &str = ".var/app";
+/// The extension of the file yabridgectl writes next to a copy or symlink of
+/// `libyabridge-{vst2,vst3}.so` when the plugin's location has been associated with a Wine prefix.
+/// yabridge will read the path to the Wine prefix from this file.
+pub const WINE_PREFIX_FILE_EXTENSION: &str = "wineprefix";
 
 /// The configuration used for yabridgectl. This will be serialized to and deserialized from
 /// `$XDG_CONFIG_HOME/yabridge/config.toml`.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(default)]
 pub struct Config {
     /// The installation method to use. We will default to creating copies since that works
@@ -65,27 +93,122 @@ pub struct Config {
     pub method: InstallationMethod,
     /// The path to the directory containing `libyabridge-{vst2,vst3}.so`. If not set, then
     /// yabridgectl will look in `/usr/lib` and `$XDG_DATA_HOME/yabridge` since those are the
-    /// expected locations for yabridge to be installed in.
+    /// expected locations for yabridge to be installed in, followed by the `yabridge` directories in
+    /// `$XDG_DATA_DIRS`.
     pub yabridge_home: Option<PathBuf>,
+    /// Directories or glob patterns to search for yabridge's files when `yabridge_home` is not
+    /// set, in order. This is useful when yabridge is installed to a versioned path like
+    /// `/opt/yabridge-3.4.0`. If a pattern matches multiple directories containing yabridge's
+    /// files, then the most recently updated one will be used. Literal paths are stored as escaped
+    /// glob patterns.
+    pub yabridge_home_candidates: Vec<String>,
+    /// The path to `yabridge-host.exe`. If not set, then yabridgectl will search for it in the
+    /// search path and in `~/.local/share/yabridge`. This is only used for yabridgectl's own setup
+    /// checks, yabridge itself will still look for the plugin host next to `libyabridge-*.so` and
+    /// in the search path.
+    pub yabridge_host_exe: Option<PathBuf>,
+    /// The same as `yabridge_host_exe`, but for `yabridge-host-32.exe`.
+    pub yabridge_host_32_exe: Option<PathBuf>,
     /// Directories to search for Windows VST plugins. These directories can contain both VST2
     /// plugin `.dll` files and VST3 modules (which should be located in `<prefix>/drive_c/Program
     /// Files/Common/VST3`). We're using an ordered set here out of convenience so we can't get
     /// duplicates and the config file is always sorted.
     pub plugin_dirs: BTreeSet<PathBuf>,
+    /// Individual Windows VST2 plugin `.dll` files and legacy VST3 `.vst3` modules added with
+    /// `yabridgectl add <file>`. These are indexed on their own, and they're stored separately
+    /// from `plugin_dirs` so we don't have to walk them like directories.
+    pub plugin_files: BTreeSet<PathBuf>,
     /// Always skip post-installation setup checks. This can be set temporarily by passing the
     /// `--no-verify` option to `yabridgectl sync`.
     pub no_verify: bool,
+    /// Only show the warning about a VST3 plugin being provided by multiple Wine prefixes in
+    /// verbose mode. Only one of those plugins can be set up in `~/.vst3/yabridge`, and some users
+    /// have intentionally set things up this way. This can be set temporarily by passing the
+    /// `--no-centralized-warning` option to `yabridgectl sync`.
+    pub no_centralized_warning: bool,
+    /// Don't report the number of `.dll` files that were skipped because they're not plugins. Some
+    /// plugins ship with a lot of supporting libraries, which would otherwise be reported on every
+    /// sync. `yabridgectl sync --verbose` still lists these files. This can be set temporarily by
+    /// passing the `--quiet-skipped` option to `yabridgectl sync`.
+    pub quiet_skipped: bool,
     /// Files and directories that should be skipped during the indexing process. If this contains a
     /// directory, then everything under that directory will also be skipped. Like with
     /// `plugin_dirs`, we're using a `BTreeSet` here because it looks nicer in the config file, even
     /// though a hash set would make much more sense.
     pub blacklist: BTreeSet<PathBuf>,
+    /// Glob patterns like `/home/user/.wine/**/*_demo.dll` for files and directories that should
+    /// also be skipped during the indexing process. These are kept separate from `blacklist` since
+    /// they're matched against every path encountered while indexing instead of being looked up
+    /// directly. Patterns are validated when they're added through `yabridgectl blacklist add
+    /// --glob`.
+    pub blacklist_patterns: BTreeSet<String>,
+    /// Files and directories that should never be removed as leftover files, either when pruning
+    /// during `yabridgectl sync` or when removing a plugin location. Unlike the blacklist, these
+    /// paths are still indexed like normal. If this contains a directory, then everything under
+    /// that directory will also be kept.
+    pub keep: BTreeSet<PathBuf>,
+    /// Abort searching a plugin location once more than this many files in that location could not
+    /// be parsed. This prevents a mistakenly added directory from resulting in a very slow scan.
+    /// There's no limit when this is not set.
+    pub max_parse_errors: Option<usize>,
+    /// How `.dll` and `.vst3` files should be parsed to determine whether they're plugins. By
+    /// default `winedump` is used, and the built-in parser is opt-in.
+    pub parser: Pe32Parser,
+    /// The ID of a Flatpak application, like `com.bitwig.BitwigStudio`, that should be able to see
+    /// the bridged VST3 plugins. If this is set, then merged VST3 bundles will be set up in
+    /// `~/.var/app/<app-id>/.vst3/yabridge` instead of in `~/.vst3/yabridge`.
+    pub flatpak_app_id: Option<String>,
+    /// The directory merged VST3 bundles should be set up in. The bundles are placed in a `yabridge`
+    /// subdirectory of this directory, just like with `~/.vst3`, so yabridgectl never touches any
+    /// other files in there. This overrides both `~/.vst3/yabridge` and the Flatpak application's
+    /// VST3 directory when set.
+    pub vst3_home: Option<PathBuf>,
+    /// The architecture of yabridge's libraries, set with `yabridgectl set --lib-arch`. This is
+    /// normally detected from the libraries' ELF headers, but that only works for x86 and x86_64
+    /// builds of yabridge. Setting this allows yabridgectl to be used on other platforms, for
+    /// instance when running x86 plugin hosts using box64 or FEX on ARM.
+    pub lib_architecture: Option<LibArchitecture>,
+    /// When the last successful `yabridgectl sync` finished, as an RFC 3339 timestamp. Only full
+    /// syncs that passed the post-installation setup checks count, so dry runs and syncs limited
+    /// with `--only`, `--format` or `--output-dir` don't update this. This is shown in `yabridgectl
+    /// status`. Configs written by older versions of yabridgectl won't have this.
+    pub last_sync: Option<String>,
+    /// Wine prefixes associated with the plugin locations in `plugin_dirs` and `plugin_files`,
+    /// indexed by the plugin location. yabridge will use these prefixes for the plugins in those
+    /// locations instead of detecting the prefix from the plugin's path.
+    pub wine_prefixes: BTreeMap<PathBuf, PathBuf>,
     /// The last known combination of Wine and yabridge versions that would work together properly.
     /// This is mostly to diagnose issues with older Wine versions (such as those in Ubuntu's repos)
     /// early on.
     pub last_known_config: Option<KnownConfig>,
 }
 
+/// Information parsed from `.dll` and `.vst3` files during previous searches, so files that haven't
+/// changed don't have to be parsed again. This is stored in
+/// `$XDG_CACHE_HOME/yabridgectl/pe32-cache.toml`.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct Pe32Cache {
+    /// The `PE32_CACHE_VERSION` this cache was written with. Caches written before this was added
+    /// don't have a version, and those are treated as version 0. This has to come before the
+    /// table below, since TOML can't store plain values after them.
+    #[serde(default)]
+    version: u32,
+    /// The cached information, indexed by the file's absolute path.
+    files: BTreeMap<PathBuf, Pe32CacheEntry>,
+    /// Whether entries have been added since the cache was read. The cache only needs to be
+    /// written when this is the case.
+    #[serde(skip)]
+    modified: bool,
+}
+
+/// A single file in `Pe32Cache`. The hash is used to detect whether the file has changed since
+/// it was cached.
+#[derive(Deserialize, Serialize, Debug)]
+struct Pe32CacheEntry {
+    hash: i64,
+    info: Pe32Info,
+}
+
 /// Specifies how yabridge will be set up for the found plugins.
 #[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
 #[serde(rename_all = "snake_case")]
@@ -98,6 +221,11 @@ pub enum InstallationMethod {
     /// or VST3 module in the plugin directories. Now that yabridge also searches in
     /// `~/.local/share/yabridge` since yabridge 2.1 this option is not really needed anymore.
     Symlink,
+    /// Like `Copy`, but this creates hard links to `libyabridge-{vst2,vst3}.so` instead. This saves
+    /// space on file systems without reflink support, but it only works when the plugins are on
+    /// the same file system as yabridge's files. Since updating yabridge replaces those files,
+    /// `yabridgectl sync` will still need to be rerun after an update.
+    HardLink,
 }
 
 impl InstallationMethod {
@@ -106,6 +234,7 @@ impl InstallationMethod {
         match &self {
             InstallationMethod::Copy => "copies",
             InstallationMethod::Symlink => "symlinks",
+            InstallationMethod::HardLink => "hard links",
         }
     }
 }
@@ -115,6 +244,29 @@ impl Display for InstallationMethod {
         match &self {
             InstallationMethod::Copy => write!(f, "copy"),
             InstallationMethod::Symlink => write!(f, "symlink"),
+            InstallationMethod::HardLink => write!(f, "hardlink"),
+        }
+    }
+}
+
+/// Specifies how `.dll` and `.vst3` files are parsed during a search.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Pe32Parser {
+    /// Parse files using Wine's `winedump` tool. This is what yabridgectl has always done, and it's
+    /// the default.
+    Auto,
+    /// Use yabridgectl's built-in parser instead. This doesn't require Wine's tools to be
+    /// installed, and files that can't be parsed are skipped and reported instead. This is opt-in
+    /// until the parser has seen more real world plugins.
+    Builtin,
+}
+
+impl Display for Pe32Parser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            Pe32Parser::Auto => write!(f, "auto"),
+            Pe32Parser::Builtin => write!(f, "builtin"),
         }
     }
 }
@@ -125,7 +277,7 @@ impl Display for InstallationMethod {
 /// winelibs, and Ubuntu ships with old versions of Wine. To prevent repeating unnecessarily
 /// repeating this check we'll keep track of the last combination of Wine and yabridge that would
 /// work together properly.
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone)]
 pub struct KnownConfig {
     /// The output of `wine --version`, minus the trailing newline.
     pub wine_version: String,
@@ -139,6 +291,42 @@ pub struct KnownConfig {
     ///
     /// https://github.com/alexcrichton/toml-rs/issues/256
     pub yabridge_host_hash: i64,
+    /// The architectures of the Wine plugin hosts that were run during the check. These depend on
+    /// the plugins that have been set up, so the check is repeated when new architectures are
+    /// needed.
+    #[serde(default)]
+    pub host_architectures: Vec<LibArchitecture>,
+}
+
+/// A record of all files yabridgectl set up in `~/.vst3/yabridge` during the last sync. This lets us
+/// find orphan files with a simple set difference instead of having to walk all of
+/// `~/.vst3/yabridge` every time. This will be serialized to and deserialized from
+/// `$XDG_DATA_HOME/yabridgectl/manifest.toml`.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct Manifest {
+    /// The directory the bundles in `vst3_bundles` were set up in, normally `~/.vst3/yabridge`.
+    /// The bundles are only used to find orphan files when this matches the current VST3
+    /// directory. This is `None` for manifests written by older versions of yabridgectl, and after
+    /// a partial sync to a different directory since the bundles are then incomplete. This has to
+    /// come before the tables below, since TOML can't store plain values after them.
+    pub vst3_home: Option<PathBuf>,
+    /// Every directory merged VST3 bundles have been set up in, including the current one. If the
+    /// VST3 directory is changed, then bundles in the previous directories are not touched
+    /// anymore, so `yabridgectl status` uses these to find plugins that are set up twice.
+    /// Directories that no longer exist are removed the next time this is updated.
+    pub vst3_homes: BTreeSet<PathBuf>,
+    /// Symlinks in `~/.vst3/presets` to VST3 preset directories in Wine prefixes, created with
+    /// `yabridgectl sync --link-presets`. Links that are no longer needed are leftover files.
+    /// This has to come before the tables below, since TOML can't store plain values after them.
+    pub vst3_preset_links: BTreeSet<PathBuf>,
+    /// All merged VST3 bundles, along with the files within those bundles. This also contains
+    /// orphan files that have not yet been pruned, so they can still be found during the next sync.
+    pub vst3_bundles: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+    /// The hashes of the `libyabridge-{vst2,vst3}.so` files the managed `.so` files were set up
+    /// with, indexed by the paths to those `.so` files. `yabridgectl status` uses these to show
+    /// which copies are still outdated after an interrupted or failed sync.
+    pub libyabridge_hashes: BTreeMap<PathBuf, i64>,
 }
 
 /// Paths to all of yabridge's files based on the `yabridge_home` setting. Created by
@@ -169,9 +357,24 @@ impl Default for Config {
         Config {
             method: InstallationMethod::Copy,
             yabridge_home: None,
+            yabridge_home_candidates: Vec::new(),
+            yabridge_host_exe: None,
+            yabridge_host_32_exe: None,
             plugin_dirs: BTreeSet::new(),
+            plugin_files: BTreeSet::new(),
             no_verify: false,
+            no_centralized_warning: false,
+            quiet_skipped: false,
             blacklist: BTreeSet::new(),
+            blacklist_patterns: BTreeSet::new(),
+            keep: BTreeSet::new(),
+            max_parse_errors: None,
+            parser: Pe32Parser::Auto,
+            flatpak_app_id: None,
+            vst3_home: None,
+            lib_architecture: None,
+            last_sync: None,
+            wine_prefixes: BTreeMap::new(),
             last_known_config: None,
         }
     }
@@ -179,9 +382,16 @@ impl Default for Config {
 
 impl Config {
     /// Try to read the config file, creating a new default file if necessary. This will fail if the
-    /// file could not be created or if it could not be parsed.
+    /// file could not be created or if it could not be parsed. If `$YABRIDGECTL_CONFIG` is set, then
+    /// that file is used instead.
     pub fn read() -> Result<Config> {
-        match yabridgectl_directories()?.find_config_file(CONFIG_FILE_NAME) {
+        let existing_config_path = match config_path_override() {
+            Some(path) if path.exists() => Some(path),
+            Some(_) => None,
+            None => yabridgectl_directories()?.find_config_file(CONFIG_FILE_NAME),
+        };
+
+        match existing_config_path {
             Some(path) => {
                 let toml_str = fs::read_to_string(&path).with_context(|| {
                     format!("Could not read config file at '{}'", path.display())
@@ -202,17 +412,70 @@ impl Config {
         }
     }
 
-    /// Write the config to disk, creating the file if it does not yet exist.
+    /// Write the config to disk, creating the file if it does not yet exist. This writes to the
+    /// file from `$YABRIDGECTL_CONFIG` instead if that's set.
     pub fn write(&self) -> Result<()> {
         let toml_str = toml::to_string_pretty(&self).context("Could not format TOML")?;
-        let config_path = yabridgectl_directories()?
-            .place_config_file(CONFIG_FILE_NAME)
-            .context("Could not create config file")?;
+        let config_path = match config_path_override() {
+            Some(path) => path,
+            None => yabridgectl_directories()?
+                .place_config_file(CONFIG_FILE_NAME)
+                .context("Could not create config file")?,
+        };
 
         fs::write(&config_path, toml_str)
             .with_context(|| format!("Failed to write config file to '{}'", config_path.display()))
     }
 
+    /// Format the config as TOML so it can be imported on another machine using
+    /// `Config::merge_toml()`. This leaves out `last_known_config` and `last_sync` since those only
+    /// apply to this machine's Wine and yabridge installations.
+    pub fn export_toml(&self) -> Result<String> {
+        let mut config = self.clone();
+        config.last_known_config = None;
+        config.last_sync = None;
+
+        toml::to_string_pretty(&config).context("Could not format TOML")
+    }
+
+    /// Merge a config exported using `Config::export_toml()` into this config. Lists of paths and
+    /// patterns are combined, Wine prefixes are added to the existing ones, and all other settings
+    /// present in `toml_str` replace the current values. Settings that are not present in
+    /// `toml_str` are left as is.
+    pub fn merge_toml(&self, toml_str: &str) -> Result<Config> {
+        let imported: toml::value::Table = toml::from_str(toml_str).context("Invalid TOML")?;
+        let mut merged = match toml::Value::try_from(self).context("Could not format TOML")? {
+            toml::Value::Table(table) => table,
+            _ => unreachable!("The config should always serialize to a table"),
+        };
+
+        for (key, value) in imported {
+            if key == "last_known_config" || key == "last_sync" {
+                continue;
+            }
+
+            match (merged.get_mut(&key), value) {
+                (Some(toml::Value::Array(existing)), toml::Value::Array(values)) => {
+                    for value in values {
+                        if !existing.contains(&value) {
+                            existing.push(value);
+                        }
+                    }
+                }
+                (Some(toml::Value::Table(existing)), toml::Value::Table(values)) => {
+                    existing.extend(values)
+                }
+                (_, value) => {
+                    merged.insert(key, value);
+                }
+            }
+        }
+
+        toml::Value::Table(merged)
+            .try_into()
+            .context("Failed to parse the merged config")
+    }
+
     /// Find all of yabridge's files based on `yabridge_home`. For the binaries we'll search for
     /// them the exact same way as yabridge itself will.
     pub fn files(&self) -> Result<YabridgeFiles> {
@@ -220,6 +483,26 @@ impl Config {
 
         // First find `libyabridge-vst2.so`
         let libyabridge_vst2: PathBuf = match &self.yabridge_home {
+            None if !self.yabridge_home_candidates.is_empty() => {
+                match self
+                    .yabridge_home_candidates
+                    .iter()
+                    .find_map(|candidate| find_in_yabridge_home_candidate(candidate))
+                {
+                    Some(candidate) => candidate,
+                    None => {
+                        return Err(anyhow!(
+                            "Could not find '{}' in any of {}",
+                            LIBYABRIDGE_VST2_NAME,
+                            self.yabridge_home_candidates
+                                .iter()
+                                .map(|candidate| format!("'{}'", candidate))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ));
+                    }
+                }
+            }
             Some(directory) => {
                 let candidate = directory.join(LIBYABRIDGE_VST2_NAME);
                 if candidate.exists() {
@@ -236,9 +519,12 @@ impl Config {
                 // Search in the system library locations and in `~/.local/share/yabridge` if no
                 // path was set explicitely. We'll also search through `/usr/local/lib` just in case
                 // but since we advocate against installing yabridge there we won't list this path
-                // in the error message when `libyabridge-vst2.so` can't be found.
+                // in the error message when `libyabridge-vst2.so` can't be found. The `yabridge`
+                // directories in `$XDG_DATA_DIRS` are searched last, since some distros and
+                // sandboxed setups install yabridge there.
                 let system_path = Path::new("/usr/lib");
                 let user_path = xdg_dirs.get_data_home();
+                let data_dirs = xdg_dirs.get_data_dirs();
                 let lib_directories = [
                     system_path,
                     // Used on Debian based distros
@@ -252,6 +538,8 @@ impl Config {
                 ];
                 let mut candidates = lib_directories
                     .iter()
+                    .copied()
+                    .chain(data_dirs.iter().map(|directory| directory.as_path()))
                     .map(|directory| directory.join(LIBYABRIDGE_VST2_NAME));
                 match candidates.find(|directory| directory.exists()) {
                     Some(candidate) => candidate,
@@ -274,12 +562,7 @@ impl Config {
             path if path.exists() => {
                 // We need to know `libyabridge-vst3.so`'s architecture to be able to set up the
                 // bundle properly
-                let arch = utils::get_elf_architecture(&path).with_context(|| {
-                    format!(
-                        "Could not determine ELF architecture for '{}'",
-                        path.display()
-                    )
-                })?;
+                let arch = self.libyabridge_architecture(&path)?;
 
                 Some((path, arch))
             }
@@ -287,12 +570,21 @@ impl Config {
         };
 
         // `yabridge-host.exe` should either be in the search path, or it should be in
-        // `~/.local/share/yabridge` (which was appended to the `$PATH` at the start of `main()`)
-        let yabridge_host_exe = which(YABRIDGE_HOST_EXE_NAME).ok();
+        // `~/.local/share/yabridge` (which was appended to the `$PATH` at the start of `main()`),
+        // unless the user has pinned the path to the binary
+        let yabridge_host_exe = find_host_binary(
+            self.yabridge_host_exe.as_deref(),
+            YABRIDGE_HOST_EXE_NAME,
+            "--host-exe",
+        )?;
         let yabridge_host_exe_so = yabridge_host_exe
             .as_ref()
             .map(|path| path.with_extension("exe.so"));
-        let yabridge_host_32_exe = which(YABRIDGE_HOST_32_EXE_NAME).ok();
+        let yabridge_host_32_exe = find_host_binary(
+            self.yabridge_host_32_exe.as_deref(),
+            YABRIDGE_HOST_32_EXE_NAME,
+            "--host-32-exe",
+        )?;
         let yabridge_host_32_exe_so = yabridge_host_32_exe
             .as_ref()
             .map(|path| path.with_extension("exe.so"));
@@ -307,20 +599,487 @@ impl Config {
         })
     }
 
-    /// Search for VST2 and VST3 plugins in all of the registered plugins directories. This will
-    /// return an error if `winedump` could not be called.
-    pub fn search_directories(&self) -> Result<BTreeMap<&Path, SearchResults>> {
+    /// Find the plugin host binaries pinned with `yabridgectl set --host-exe` or `--host-32-exe`
+    /// that are not the ones yabridge itself will use. Those pinned paths only affect yabridgectl's
+    /// own checks, since yabridge looks for the plugin host next to `libyabridge-*.so` when using
+    /// symlinks, and in the search path otherwise. Returns `(pinned, found)` pairs, where `found`
+    /// is `None` if yabridge won't be able to find that plugin host at all.
+    pub fn mismatched_pinned_hosts(
+        &self,
+        files: &YabridgeFiles,
+    ) -> Vec<(PathBuf, Option<PathBuf>)> {
+        let library_dir = fs::canonicalize(&files.libyabridge_vst2)
+            .ok()
+            .and_then(|path| path.parent().map(|parent| parent.to_owned()));
+        let find_host = |name: &str| {
+            library_dir
+                .as_ref()
+                .filter(|_| self.method == InstallationMethod::Symlink)
+                .map(|directory| directory.join(name))
+                .filter(|path| path.exists())
+                .or_else(|| which(name).ok())
+        };
+
+        [
+            (&self.yabridge_host_exe, YABRIDGE_HOST_EXE_NAME),
+            (&self.yabridge_host_32_exe, YABRIDGE_HOST_32_EXE_NAME),
+        ]
+        .iter()
+        .filter_map(|(pinned, name)| {
+            let pinned = pinned.as_ref()?;
+            let found = find_host(name);
+            let is_same_file = found
+                .as_ref()
+                .map(|found| fs::canonicalize(found).ok() == fs::canonicalize(pinned).ok())
+                .unwrap_or(false);
+
+            if is_same_file {
+                None
+            } else {
+                Some((pinned.clone(), found))
+            }
+        })
+        .collect()
+    }
+
+    /// Get the directory merged VST3 bundles should be set up in. This is either the `yabridge`
+    /// subdirectory of the directory set with `yabridgectl set --vst3-home`, or the default
+    /// directory from `yabridge_vst3_home()`. Since pruning removes everything in this directory
+    /// that's not a known bundle, this should never be a directory the user installs plugins in.
+    pub fn vst3_home(&self) -> PathBuf {
+        match &self.vst3_home {
+            Some(vst3_home) => vst3_home.join("yabridge"),
+            None => yabridge_vst3_home(self.flatpak_app_id.as_deref()),
+        }
+    }
+
+    /// Get the architecture of one of yabridge's libraries at `path`. The architecture set with
+    /// `yabridgectl set --lib-arch` takes precedence over the architecture from the library's ELF
+    /// header.
+    pub fn libyabridge_architecture(&self, path: &Path) -> Result<LibArchitecture> {
+        match self.lib_architecture {
+            Some(architecture) => Ok(architecture),
+            None => utils::get_elf_architecture(path).with_context(|| {
+                format!(
+                    "Could not determine ELF architecture for '{}'",
+                    path.display()
+                )
+            }),
+        }
+    }
+
+    /// The time the last successful sync finished, if it has been recorded and `last_sync` can be
+    /// parsed.
+    pub fn last_sync_time(&self) -> Option<SystemTime> {
+        self.last_sync
+            .as_deref()
+            .and_then(|timestamp| humantime::parse_rfc3339_weak(timestamp).ok())
+    }
+
+    /// Record the current time as the time of the last successful sync. This does not write the
+    /// config to disk.
+    pub fn set_last_sync_time(&mut self) {
+        self.last_sync = Some(humantime::format_rfc3339_seconds(SystemTime::now()).to_string());
+    }
+
+    /// Get the other directories merged VST3 bundles may have been set up in before the VST3
+    /// directory or the Flatpak application was changed. Bundles in these directories are not
+    /// touched by a regular sync, so a plugin could end up being set up twice. Custom VST3
+    /// directories that were used before are read from `manifest`, if there is one.
+    pub fn other_vst3_homes(&self, manifest: Option<&Manifest>) -> Vec<PathBuf> {
+        let current_vst3_home = self.vst3_home();
+        let mut vst3_homes: BTreeSet<PathBuf> = BTreeSet::new();
+        vst3_homes.insert(yabridge_vst3_home(None));
+        vst3_homes.insert(yabridge_vst3_home(self.flatpak_app_id.as_deref()));
+        if let Some(manifest) = manifest {
+            vst3_homes.extend(manifest.vst3_homes.iter().cloned());
+        }
+        vst3_homes.remove(&current_vst3_home);
+
+        vst3_homes.into_iter().collect()
+    }
+
+    /// Get the Wine prefix associated with the plugin location containing `plugin_path`, if any. If
+    /// the plugin is part of multiple locations, then the most specific location wins.
+    pub fn associated_wine_prefix(&self, plugin_path: &Path) -> Option<&Path> {
+        self.wine_prefixes
+            .iter()
+            .filter(|(location, _)| plugin_path.starts_with(location))
+            .max_by_key(|(location, _)| location.components().count())
+            .map(|(_, prefix)| prefix.as_path())
+    }
+
+    /// Whether `path` should never be removed as a leftover file because it, or one of its parent
+    /// directories, is in the `keep` list. Kept symlinks to files are stored using
+    /// `utils::normalize_parent_path()` and everything else is stored using
+    /// `utils::normalize_path()`, so `path` is compared in both forms.
+    pub fn is_kept(&self, path: &Path) -> bool {
+        let paths = [
+            utils::normalize_parent_path(path),
+            utils::normalize_path(path),
+        ];
+
+        self.keep
+            .iter()
+            .any(|kept_path| paths.iter().any(|path| path.starts_with(kept_path)))
+    }
+
+    /// The blacklisted paths and the compiled blacklisted glob patterns, in the format expected by
+    /// `files::index()`.
+    pub fn blacklist_filters(&self) -> (HashSet<&Path>, Vec<glob::Pattern>) {
         let blacklist: HashSet<&Path> = self.blacklist.iter().map(|p| p.as_path()).collect();
+        // These have already been validated when they were added, but someone could have edited
+        // the config file by hand
+        let blacklist_patterns: Vec<glob::Pattern> = self
+            .blacklist_patterns
+            .iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .collect();
+
+        (blacklist, blacklist_patterns)
+    }
+
+    /// Search for VST2 and VST3 plugins in all of the registered plugins directories and plugin
+    /// files. Only plugins using one of the plugin formats in `formats` will be searched for. Unless
+    /// `use_cache` is disabled, the information parsed from plugin files will be cached in
+    /// `Pe32Cache` to speed up the next search. This will return an error if files are parsed
+    /// using `winedump` and `winedump` could not be called.
+    pub fn search_directories(
+        &self,
+        formats: &[PluginFormat],
+        use_cache: bool,
+    ) -> Result<BTreeMap<&Path, SearchResults>> {
+        let cache = if use_cache {
+            Some(Mutex::new(Pe32Cache::read()))
+        } else {
+            None
+        };
+        let (results, _) =
+            self.search_locations(None, formats, &self.vst3_home(), cache.as_ref())?;
+        if let Some(cache) = cache {
+            cache.into_inner().unwrap().write_or_warn();
+        }
+
+        Ok(results)
+    }
+
+    /// The same as [`search_directories()`](Self::search_directories), but if `only` is set then
+    /// only the plugin locations in that list will be searched. Paths in `only` that are not
+    /// plugin locations are ignored. The merged bundles for the VST3 modules that are found will be
+    /// set up in `vst3_home`, which is normally [`vst3_home()`](Self::vst3_home). If `cache` is
+    /// set, then newly parsed files are added to it, and it's up to the caller to write it to
+    /// disk afterwards.
+    ///
+    /// Plugin locations that could not be searched, for instance because they contain too many
+    /// files that could not be parsed, are skipped with a warning so the other locations can still
+    /// be synced. These locations are returned alongside the search results. An error is only
+    /// returned when none of the plugin locations could be searched.
+    pub fn search_locations(
+        &self,
+        only: Option<&[PathBuf]>,
+        formats: &[PluginFormat],
+        vst3_home: &Path,
+        cache: Option<&Mutex<Pe32Cache>>,
+    ) -> Result<(BTreeMap<&Path, SearchResults>, Vec<&Path>)> {
+        let is_included = |path: &&PathBuf| match only {
+            Some(only) => only.contains(path),
+            None => true,
+        };
+        let (blacklist, blacklist_patterns) = self.blacklist_filters();
+        let progress = Progress::on_stderr();
 
-        self.plugin_dirs
+        let results = self
+            .plugin_dirs
             .par_iter()
+            .filter(is_included)
             .map(|path| {
-                files::index(path, &blacklist)
-                    .search()
+                (
+                    path,
+                    files::index(path, &blacklist, &blacklist_patterns, Some(&progress)),
+                )
+            })
+            .chain(
+                self.plugin_files
+                    .par_iter()
+                    .filter(is_included)
+                    .map(|path| (path, files::index_file(path))),
+            )
+            .map(|(path, index)| {
+                index
+                    .retain_formats(formats)
+                    .search(
+                        self.max_parse_errors,
+                        self.parser,
+                        vst3_home,
+                        cache,
+                        Some(&progress),
+                    )
+                    .with_context(|| format!("Could not search '{}'", path.display()))
                     .map(|search_results| (path.as_path(), search_results))
+                    .map_err(|err| (path.as_path(), err))
+            })
+            .collect::<Vec<_>>();
+
+        let mut search_results = BTreeMap::new();
+        let mut failed_locations = Vec::new();
+        let mut first_error = None;
+        for result in results {
+            match result {
+                Ok((path, location_results)) => {
+                    search_results.insert(path, location_results);
+                }
+                Err((path, err)) => {
+                    eprintln!(
+                        "{}\n",
+                        utils::wrap(&format!("Warning: Skipping a plugin location. {:#}", err))
+                    );
+                    failed_locations.push(path);
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+        if let (true, Some(err)) = (search_results.is_empty(), first_error) {
+            return Err(err);
+        }
+
+        Ok((search_results, failed_locations))
+    }
+}
+
+impl Pe32Cache {
+    /// Try to read the cache from the last search. If the cache does not exist, if it could not be
+    /// parsed, or if it was written with an older `PE32_CACHE_VERSION`, then we'll start with an
+    /// empty cache that will replace the old one.
+    pub fn read() -> Pe32Cache {
+        override_file_path(PE32_CACHE_FILE_NAME)
+            .or_else(|| {
+                yabridgectl_directories()
+                    .ok()
+                    .and_then(|directories| directories.find_cache_file(PE32_CACHE_FILE_NAME))
+            })
+            .and_then(|path| fs::read_to_string(&path).ok())
+            .and_then(|toml_str| toml::from_str::<Pe32Cache>(&toml_str).ok())
+            .filter(|cache| cache.version == PE32_CACHE_VERSION)
+            .unwrap_or_default()
+    }
+
+    /// Write the cache to disk if any new entries have been added. Entries for files that no
+    /// longer exist will be removed. Just like with the manifest, the cache is first written to a
+    /// temporary file so it can never end up partially written.
+    pub fn write(mut self) -> Result<()> {
+        if !self.modified {
+            return Ok(());
+        }
+
+        self.version = PE32_CACHE_VERSION;
+        self.files.retain(|path, _| path.exists());
+        let toml_str = toml::to_string_pretty(&self).context("Could not format TOML")?;
+        let cache_path = match override_file_path(PE32_CACHE_FILE_NAME) {
+            Some(path) => path,
+            None => yabridgectl_directories()?
+                .place_cache_file(PE32_CACHE_FILE_NAME)
+                .context("Could not create cache file")?,
+        };
+        let temporary_path = cache_path.with_extension("toml.tmp");
+
+        fs::write(&temporary_path, toml_str)
+            .with_context(|| format!("Failed to write cache to '{}'", temporary_path.display()))?;
+        fs::rename(&temporary_path, &cache_path)
+            .with_context(|| format!("Failed to write cache to '{}'", cache_path.display()))
+    }
+
+    /// The same as [`write()`](Self::write), but a failure only prints a warning. The cache only
+    /// speeds up the next search, so not being able to write it should never cause a command to
+    /// fail.
+    pub fn write_or_warn(self) {
+        if let Err(err) = self.write() {
+            eprintln!(
+                "{}\n",
+                utils::wrap(&format!("Warning: Could not update the cache: {:#}", err))
+            );
+        }
+    }
+
+    /// Get the cached information for the file at `path`, if the file has not changed since it was
+    /// cached. `hash` should be the file's current hash as returned by `utils::hash_file()`.
+    pub fn get(&self, path: &Path, hash: i64) -> Option<Pe32Info> {
+        self.files
+            .get(path)
+            .filter(|entry| entry.hash == hash)
+            .map(|entry| entry.info.clone())
+    }
+
+    /// Add the information parsed from the file at `path` to the cache.
+    pub fn insert(&mut self, path: PathBuf, hash: i64, info: Pe32Info) {
+        self.files.insert(path, Pe32CacheEntry { hash, info });
+        self.modified = true;
+    }
+}
+
+impl Manifest {
+    /// Whether the VST3 bundles in this manifest are the ones set up in `vst3_home`. If the VST3
+    /// directory or the Flatpak application was changed since the last sync, then the recorded
+    /// files should not be compared against the current directory.
+    pub fn describes_vst3_home(&self, vst3_home: &Path) -> bool {
+        self.vst3_home.as_deref() == Some(vst3_home)
+    }
+
+    /// Replace the recorded VST3 bundles with the files that were set up in `vst3_home` during a
+    /// sync.
+    pub fn set_vst3_bundles(
+        &mut self,
+        vst3_home: &Path,
+        known_vst3_files: &HashMap<PathBuf, HashSet<PathBuf>>,
+    ) {
+        self.vst3_home = Some(vst3_home.to_owned());
+        self.add_vst3_home(vst3_home);
+        self.vst3_bundles = known_vst3_files
+            .iter()
+            .map(|(bundle, files)| (bundle.clone(), files.iter().cloned().collect()))
+            .collect();
+    }
+
+    /// Add the files that were set up in `vst3_home` during a partial sync to the manifest without
+    /// forgetting about the bundles and files from plugin locations that were not part of this
+    /// sync. If the manifest described a different directory, then the old bundles are dropped and
+    /// the manifest won't be used to find orphan files until the next full sync.
+    pub fn merge_vst3_bundles(
+        &mut self,
+        vst3_home: &Path,
+        known_vst3_files: &HashMap<PathBuf, HashSet<PathBuf>>,
+    ) {
+        if !self.describes_vst3_home(vst3_home) {
+            self.vst3_home = None;
+            self.vst3_bundles.clear();
+        }
+        self.add_vst3_home(vst3_home);
+
+        for (bundle, files) in known_vst3_files {
+            self.vst3_bundles
+                .entry(bundle.clone())
+                .or_default()
+                .extend(files.iter().cloned());
+        }
+    }
+
+    /// Record that bundles have been set up in `vst3_home`, and forget about previous directories
+    /// that no longer exist.
+    fn add_vst3_home(&mut self, vst3_home: &Path) {
+        self.vst3_homes.insert(vst3_home.to_owned());
+        self.vst3_homes.retain(|vst3_home| vst3_home.exists());
+    }
+
+    /// Record the hashes of the `libyabridge-{vst2,vst3}.so` files that were used to set up the
+    /// `.so` files during a sync. Hashes for files that no longer exist are removed, while the
+    /// hashes for files that were not part of this sync are kept as is.
+    pub fn update_libyabridge_hashes(&mut self, libyabridge_hashes: BTreeMap<PathBuf, i64>) {
+        self.libyabridge_hashes.extend(libyabridge_hashes);
+        self.libyabridge_hashes
+            .retain(|path, _| fs::symlink_metadata(path).is_ok());
+    }
+
+    /// Find the symlinks created by `yabridgectl sync --link-presets` during the last sync that were
+    /// not set up again during this sync.
+    pub fn vst3_preset_link_orphans(
+        &self,
+        linked_preset_dirs: &BTreeSet<PathBuf>,
+    ) -> Vec<NativeFile> {
+        self.vst3_preset_links
+            .difference(linked_preset_dirs)
+            .filter_map(|path| match utils::get_file_type(path.clone()) {
+                Some(symlink @ NativeFile::Symlink(_)) => Some(symlink),
+                _ => None,
             })
             .collect()
     }
+
+    /// Replace the recorded preset links with the ones from this sync. Links from the last sync
+    /// that still exist, for instance because they were not pruned, are kept so they can still be
+    /// found during the next sync.
+    pub fn update_vst3_preset_links(&mut self, linked_preset_dirs: BTreeSet<PathBuf>) {
+        self.vst3_preset_links.retain(|path| {
+            fs::symlink_metadata(path)
+                .map(|metadata| metadata.file_type().is_symlink())
+                .unwrap_or(false)
+        });
+        self.vst3_preset_links.extend(linked_preset_dirs);
+    }
+
+    /// Try to read the manifest from the last sync. This returns `None` if the manifest does not
+    /// exist or if it could not be parsed, in which case we'll have to fall back to walking
+    /// `~/.vst3/yabridge` and the manifest will be regenerated afterwards.
+    pub fn read() -> Option<Manifest> {
+        let path = match override_file_path(MANIFEST_FILE_NAME) {
+            Some(path) => path,
+            None => yabridgectl_directories()
+                .ok()?
+                .find_data_file(MANIFEST_FILE_NAME)?,
+        };
+        let toml_str = fs::read_to_string(&path).ok()?;
+
+        toml::from_str(&toml_str).ok()
+    }
+
+    /// Write the manifest to disk. The manifest is first written to a temporary file that then
+    /// replaces the old manifest so we can never end up with a partially written manifest.
+    pub fn write(&self) -> Result<()> {
+        let toml_str = toml::to_string_pretty(&self).context("Could not format TOML")?;
+        let manifest_path = match override_file_path(MANIFEST_FILE_NAME) {
+            Some(path) => path,
+            None => yabridgectl_directories()?
+                .place_data_file(MANIFEST_FILE_NAME)
+                .context("Could not create manifest file")?,
+        };
+        let temporary_path = manifest_path.with_extension("toml.tmp");
+
+        fs::write(&temporary_path, toml_str).with_context(|| {
+            format!("Failed to write manifest to '{}'", temporary_path.display())
+        })?;
+        fs::rename(&temporary_path, &manifest_path)
+            .with_context(|| format!("Failed to write manifest to '{}'", manifest_path.display()))
+    }
+
+    /// Add orphan files that were not pruned to the manifest so they will still be found during
+    /// the next sync. These are `(bundle, file)` pairs as returned by `vst3_orphans()`.
+    pub fn add_orphans(&mut self, orphans: &[(PathBuf, NativeFile)]) {
+        for (bundle, file) in orphans {
+            let files = self.vst3_bundles.entry(bundle.clone()).or_default();
+            if !matches!(file, NativeFile::Directory(_)) {
+                files.insert(file.path().to_owned());
+            }
+        }
+    }
+
+    /// Find the files from the last sync that are no longer managed by yabridgectl. This returns
+    /// `(bundle, file)` pairs in the same format as `files::index_vst3_orphans()`. Just like
+    /// there, if an entire bundle is no longer used we'll only return the bundle's directory.
+    pub fn vst3_orphans(
+        &self,
+        known_vst3_files: &HashMap<PathBuf, HashSet<PathBuf>>,
+    ) -> Vec<(PathBuf, NativeFile)> {
+        let mut orphans = Vec::new();
+        for (bundle, files) in &self.vst3_bundles {
+            match known_vst3_files.get(bundle) {
+                None => {
+                    if let Some(directory @ NativeFile::Directory(_)) =
+                        utils::get_file_type(bundle.clone())
+                    {
+                        orphans.push((bundle.clone(), directory));
+                    }
+                }
+                Some(managed_vst3_bundle_files) => orphans.extend(
+                    files
+                        .iter()
+                        .filter(|file| !managed_vst3_bundle_files.contains(*file))
+                        .filter_map(|file| utils::get_file_type(file.clone()))
+                        .filter(|file| !matches!(file, NativeFile::Directory(_)))
+                        .map(|file| (bundle.clone(), file)),
+                ),
+            }
+        }
+
+        orphans
+    }
 }
 
 /// Fetch the XDG base directories for yabridge's own files, converting any error messages if this
@@ -336,9 +1095,261 @@ pub fn yabridgectl_directories() -> Result<BaseDirectories> {
     BaseDirectories::with_prefix(YABRIDGECTL_PREFIX).context("Error while parsing base directories")
 }
 
+/// The config file set through `$YABRIDGECTL_CONFIG`, if that's set to a non-empty value.
+fn config_path_override() -> Option<PathBuf> {
+    env::var_os(CONFIG_PATH_ENV_VAR)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// When `$YABRIDGECTL_CONFIG` is set, the manifest and the cache are stored next to that config
+/// file instead of in the XDG directories. Otherwise syncing with a different config would
+/// overwrite the regular manifest, and pruning with either config would remove the other config's
+/// VST3 bundles. For `/tmp/test.toml` the manifest is stored in `/tmp/test.manifest.toml`.
+fn override_file_path(file_name: &str) -> Option<PathBuf> {
+    config_path_override().map(|path| path.with_extension(file_name))
+}
+
+/// Find `libyabridge-vst2.so` in the directories matching a glob pattern from
+/// `Config::yabridge_home_candidates`. If the pattern matches multiple directories containing
+/// yabridge's files, then the most recently modified `libyabridge-vst2.so` wins.
+fn find_in_yabridge_home_candidate(candidate: &str) -> Option<PathBuf> {
+    glob::glob(candidate)
+        .ok()?
+        .filter_map(|directory| directory.ok())
+        .map(|directory| directory.join(LIBYABRIDGE_VST2_NAME))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()?;
+
+            Some((modified, path))
+        })
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// Find one of the plugin host binaries. If the user pinned the path to the binary using `yabridgectl
+/// set <option>`, then that path is used as is and it's an error if it doesn't exist. Otherwise
+/// we'll search for `name` in the search path, returning `None` if it can't be found.
+fn find_host_binary(pinned: Option<&Path>, name: &str, option: &str) -> Result<Option<PathBuf>> {
+    match pinned {
+        Some(path) if path.exists() => Ok(Some(path.to_owned())),
+        Some(path) => Err(anyhow!(
+            "Could not find '{}' at '{}'. You can change this path using 'yabridgectl set {}=<path>', \
+             or you can go back to searching for it in the search path using 'yabridgectl set \
+             --host-auto'.",
+            name,
+            path.display(),
+            option
+        )),
+        None => Ok(which(name).ok()),
+    }
+}
+
+/// Get the directory Linux VST3 hosts search for user presets in. Like with `yabridge_vst3_home()`,
+/// this will be inside of the Flatpak application's home directory if `flatpak_app_id` is set.
+pub fn vst3_presets_home(flatpak_app_id: Option<&str>) -> PathBuf {
+    let home = PathBuf::from(env::var("HOME").expect("$HOME is not set"));
+    match flatpak_app_id {
+        Some(app_id) => home
+            .join(FLATPAK_APPS_HOME)
+            .join(app_id)
+            .join(VST3_PRESETS_HOME),
+        None => home.join(VST3_PRESETS_HOME),
+    }
+}
+
 /// Get the path where VST3 modules bridged by yabridgectl should be placed in. This is a
 /// subdirectory of `~/.vst3` so we can easily clean up leftover files without interfering with
-/// other native plugins.
-pub fn yabridge_vst3_home() -> PathBuf {
-    Path::new(&env::var("HOME").expect("$HOME is not set")).join(YABRIDGE_VST3_HOME)
+/// other native plugins. If `flatpak_app_id` is set, then this will be the same subdirectory in
+/// that Flatpak application's `~/.vst3` directory instead.
+pub fn yabridge_vst3_home(flatpak_app_id: Option<&str>) -> PathBuf {
+    let home = PathBuf::from(env::var("HOME").expect("$HOME is not set"));
+    match flatpak_app_id {
+        Some(app_id) => home
+            .join(FLATPAK_APPS_HOME)
+            .join(app_id)
+            .join(YABRIDGE_VST3_HOME),
+        None => home.join(YABRIDGE_VST3_HOME),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The manifest written after a sync should let the next sync find the bundles for plugins
+    /// that have been removed since, without touching bundles that no longer exist or bundles in
+    /// a different VST3 directory.
+    #[test]
+    fn manifest_vst3_orphans() {
+        let temp_dir = env::temp_dir().join(format!("yabridgectl-test-{}", std::process::id()));
+        let vst3_home = temp_dir.join("vst3").join("yabridge");
+        let bundle = |name: &str| vst3_home.join(name);
+        let bundle_file = |name: &str| bundle(name).join("Contents/x86_64-linux/plugin.so");
+        let mut known_vst3_files: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
+        for name in &["Kept.vst3", "Removed.vst3", "Deleted.vst3"] {
+            fs::create_dir_all(bundle_file(name).parent().unwrap()).unwrap();
+            fs::write(bundle_file(name), "").unwrap();
+            known_vst3_files.insert(bundle(name), [bundle_file(name)].iter().cloned().collect());
+        }
+
+        // Setting this makes the manifest end up next to this config file instead of in the
+        // user's data directory
+        env::set_var(CONFIG_PATH_ENV_VAR, temp_dir.join("config.toml"));
+        let mut manifest = Manifest::default();
+        manifest.set_vst3_bundles(&vst3_home, &known_vst3_files);
+        manifest.write().unwrap();
+
+        // The plugin for `Removed.vst3` has been uninstalled, and `Deleted.vst3` has been
+        // uninstalled and its bundle has already been removed by hand
+        known_vst3_files.remove(&bundle("Removed.vst3"));
+        known_vst3_files.remove(&bundle("Deleted.vst3"));
+        fs::remove_dir_all(bundle("Deleted.vst3")).unwrap();
+
+        let manifest = Manifest::read().expect("The manifest could not be read");
+        assert!(manifest.describes_vst3_home(&vst3_home));
+        assert!(!manifest.describes_vst3_home(&temp_dir.join("other")));
+        assert!(manifest.vst3_homes.contains(&vst3_home));
+        assert_eq!(
+            manifest.vst3_orphans(&known_vst3_files),
+            vec![(
+                bundle("Removed.vst3"),
+                NativeFile::Directory(bundle("Removed.vst3"))
+            )]
+        );
+
+        env::remove_var(CONFIG_PATH_ENV_VAR);
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Keeping a symlinked directory should also keep the files inside of it, and keeping a symlink
+    /// to a file should keep that symlink without keeping the file it points to.
+    #[test]
+    fn keep_symlinks() {
+        let temp_dir =
+            env::temp_dir().join(format!("yabridgectl-test-keep-{}", std::process::id()));
+        let real_dir = temp_dir.join("real");
+        fs::create_dir_all(&real_dir).unwrap();
+        fs::write(real_dir.join("plugin.so"), "").unwrap();
+        fs::write(temp_dir.join("other.so"), "").unwrap();
+        std::os::unix::fs::symlink(&real_dir, temp_dir.join("linked")).unwrap();
+        std::os::unix::fs::symlink(temp_dir.join("other.so"), temp_dir.join("linked.so")).unwrap();
+
+        // This is what `yabridgectl keep add` does
+        let mut config = Config::default();
+        config
+            .keep
+            .insert(utils::normalize_path(&temp_dir.join("linked")));
+        assert!(config.is_kept(&temp_dir.join("linked")));
+        assert!(config.is_kept(&temp_dir.join("linked/plugin.so")));
+        assert!(config.is_kept(&real_dir.join("plugin.so")));
+        assert!(!config.is_kept(&temp_dir.join("linked.so")));
+
+        config.keep.clear();
+        config
+            .keep
+            .insert(utils::normalize_parent_path(&temp_dir.join("linked.so")));
+        assert!(config.is_kept(&temp_dir.join("linked.so")));
+        assert!(!config.is_kept(&temp_dir.join("other.so")));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Bundles from plugin locations that were not part of a partial sync should not be forgotten,
+    /// and orphans that were not pruned during a full sync should still be found during the next
+    /// full sync.
+    #[test]
+    fn manifest_partial_sync_orphans() {
+        let temp_dir =
+            env::temp_dir().join(format!("yabridgectl-test-partial-{}", std::process::id()));
+        let vst3_home = temp_dir.join("vst3").join("yabridge");
+        let bundle = |name: &str| vst3_home.join(name);
+        let bundle_file =
+            |name: &str, arch: &str| bundle(name).join(format!("Contents/{}/plugin.so", arch));
+        for (name, arch) in &[
+            ("Alpha.vst3", "x86_64-linux"),
+            ("Alpha.vst3", "x86-linux"),
+            ("Beta.vst3", "x86_64-linux"),
+        ] {
+            fs::create_dir_all(bundle_file(name, arch).parent().unwrap()).unwrap();
+            fs::write(bundle_file(name, arch), "").unwrap();
+        }
+        // The manifest is stored on disk between syncs, so every step goes through a round trip
+        let round_trip = |manifest: &Manifest| -> Manifest {
+            toml::from_str(&toml::to_string_pretty(manifest).unwrap()).unwrap()
+        };
+
+        // `Alpha.vst3` comes from one plugin location and `Beta.vst3` from another
+        let mut known_vst3_files: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
+        known_vst3_files.insert(
+            bundle("Alpha.vst3"),
+            [
+                bundle_file("Alpha.vst3", "x86_64-linux"),
+                bundle_file("Alpha.vst3", "x86-linux"),
+            ]
+            .iter()
+            .cloned()
+            .collect(),
+        );
+        known_vst3_files.insert(
+            bundle("Beta.vst3"),
+            [bundle_file("Beta.vst3", "x86_64-linux")]
+                .iter()
+                .cloned()
+                .collect(),
+        );
+        let mut manifest = Manifest::default();
+        manifest.set_vst3_bundles(&vst3_home, &known_vst3_files);
+        let mut manifest = round_trip(&manifest);
+
+        // The 32-bit version of the plugin in `Alpha.vst3` has been removed, and only the first
+        // plugin location gets synced
+        known_vst3_files.clear();
+        known_vst3_files.insert(
+            bundle("Alpha.vst3"),
+            [bundle_file("Alpha.vst3", "x86_64-linux")]
+                .iter()
+                .cloned()
+                .collect(),
+        );
+        manifest.merge_vst3_bundles(&vst3_home, &known_vst3_files);
+        let mut manifest = round_trip(&manifest);
+        assert!(manifest.describes_vst3_home(&vst3_home));
+        assert!(manifest.vst3_bundles.contains_key(&bundle("Beta.vst3")));
+
+        // During the next full sync the plugin in `Beta.vst3` has been uninstalled. Without
+        // `--prune` the orphans are kept in the manifest.
+        let expected_orphans = vec![
+            (
+                bundle("Alpha.vst3"),
+                NativeFile::Regular(bundle_file("Alpha.vst3", "x86-linux")),
+            ),
+            (
+                bundle("Beta.vst3"),
+                NativeFile::Directory(bundle("Beta.vst3")),
+            ),
+        ];
+        let orphans = manifest.vst3_orphans(&known_vst3_files);
+        assert_eq!(orphans, expected_orphans);
+        manifest.set_vst3_bundles(&vst3_home, &known_vst3_files);
+        manifest.add_orphans(&orphans);
+        let mut manifest = round_trip(&manifest);
+
+        // So the sync after that should still find them, until they have actually been pruned
+        let orphans = manifest.vst3_orphans(&known_vst3_files);
+        assert_eq!(orphans, expected_orphans);
+        for (_, file) in &orphans {
+            match file {
+                NativeFile::Directory(path) => fs::remove_dir_all(path).unwrap(),
+                file => fs::remove_file(file.path()).unwrap(),
+            }
+        }
+        manifest.set_vst3_bundles(&vst3_home, &known_vst3_files);
+        let manifest = round_trip(&manifest);
+        assert_eq!(manifest.vst3_orphans(&known_vst3_files), Vec::new());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
 }
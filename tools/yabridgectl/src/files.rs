@@ -17,17 +17,36 @@
 //! Functions to index plugins and to set up yabridge for those plugins.
 
 use aho_corasick::AhoCorasick;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use lazy_static::lazy_static;
 use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
 use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use walkdir::WalkDir;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use walkdir::{DirEntry, WalkDir};
 
-use crate::config::{yabridge_vst3_home, YabridgeFiles};
-use crate::utils::get_file_type;
+use crate::config::{Pe32Cache, Pe32Parser, YabridgeFiles};
+use crate::utils::{self, get_file_type, Progress};
+
+/// The name of the file containing gitignore-style patterns for files and directories that should
+/// be skipped when indexing a plugin location. This file is only read from the root of a plugin
+/// location, and the patterns are relative to that location. This complements the blacklist stored
+/// in yabridgectl's configuration file.
+pub const IGNORE_FILE_NAME: &str = ".yabridge-ignore";
+
+/// Parts of the file names or product names of known VST2 shell plugins. These are matched case
+/// insensitively. See `is_vst2_shell()`.
+const VST2_SHELL_NAMES: &[&str] = &["WaveShell"];
 
 /// Stores the results from searching through a directory. We'll search for Windows VST2 plugin
 /// `.dll` files, Windows VST3 plugin modules, and native Linux `.so` files inside of a directory.
@@ -41,15 +60,32 @@ pub struct SearchResults {
     /// `.dll` files skipped over during the search. Used for printing statistics and shown when
     /// running `yabridgectl sync --verbose`.
     pub skipped_files: Vec<PathBuf>,
+    /// `.dll` and `.vst3` files that turned out to be managed .NET assemblies. These can never be
+    /// plugins, so we skip them before trying to parse them. These are listed separately from
+    /// `skipped_files` when running `yabridgectl sync --verbose`.
+    pub skipped_managed_files: Vec<PathBuf>,
+    /// Files from `skipped_files` that the built-in parser could not parse, along with the error.
+    /// This is only used with `Pe32Parser::Builtin`, since otherwise `winedump` parses these files.
+    pub parse_errors: Vec<(PathBuf, String)>,
 
     /// Absolute paths to any `.so` files inside of the directory, and whether they're a symlink or
     /// a regular file.
     pub so_files: Vec<NativeFile>,
+    /// Directories and files that were skipped during indexing because we did not have permission
+    /// to read them. Plugins in these directories won't have been found.
+    pub unreadable_paths: Vec<PathBuf>,
+    /// Symlinks to `.dll` and `.vst3` files that are either broken or that point to another file
+    /// system. These are listed separately during `yabridgectl sync` since they're easy to miss.
+    pub plugin_symlinks: Vec<PluginSymlink>,
+    /// VST3 bundles that contain a native Linux version of the plugin, as well as `.vst3` files
+    /// that are native Linux modules themselves. These don't need to be bridged, so any Windows
+    /// VST3 modules in these bundles are not included in `plugins`.
+    pub native_vst3_bundles: Vec<PathBuf>,
 }
 
 /// The results of the first step of the search process. We'll first index all possibly relevant
 /// files in a directory before filtering them down to a `SearchResults` object.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct SearchIndex {
     /// Any `.dll` file.
     pub dll_files: Vec<PathBuf>,
@@ -59,6 +95,44 @@ pub struct SearchIndex {
     /// Absolute paths to any `.so` files inside of the directory, and whether they're a symlink or
     /// a regular file.
     pub so_files: Vec<NativeFile>,
+    /// Directories and files that were skipped because we did not have permission to read them.
+    pub unreadable_paths: Vec<PathBuf>,
+    /// Broken symlinks and symlinks to other file systems with a `.dll` or `.vst3` extension.
+    pub plugin_symlinks: Vec<PluginSymlink>,
+    /// VST3 bundles containing a native Linux module in `Contents/<arch>-linux`. The `.so` files in
+    /// those directories are not included in `so_files`, since they're not yabridge's files. This
+    /// may contain duplicates if a bundle contains modules for multiple architectures.
+    pub native_vst3_bundles: Vec<PathBuf>,
+}
+
+/// A symlink to a `.dll` or `.vst3` file or directory found during indexing that may cause
+/// problems. Both variants contain the path to the symlink and the symlink's target as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginSymlink {
+    /// The symlink's target does not exist, for instance because it's on a drive that's currently
+    /// not mounted. These files will not have been indexed.
+    Broken { path: PathBuf, target: PathBuf },
+    /// The symlink points to a file on another file system than the one the plugin location is
+    /// on. This works fine, but these plugins will disappear when that file system is not mounted.
+    CrossDevice { path: PathBuf, target: PathBuf },
+}
+
+/// The information we need from a `.dll` or `.vst3` file to determine whether it's a plugin. This
+/// is parsed from the file's headers (or from `winedump`'s output) and the file's version resource,
+/// and it's stored in the `Pe32Cache` so unchanged files don't have to be parsed again during the
+/// next search.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Pe32Info {
+    /// Whether the file is a valid PE32(+) binary. Used for `max_parse_errors`.
+    pub is_pe32: bool,
+    /// Whether the file is a 32-bit binary.
+    pub is_32_bit: bool,
+    /// Whether the file exports one of the VST2 entry points.
+    pub exports_vst2_entry_point: bool,
+    /// Whether the file exports the VST3 entry point.
+    pub exports_vst3_entry_point: bool,
+    /// The product name from the file's version resource, if it has one.
+    pub product_name: Option<String>,
 }
 
 /// Native `.so` files and VST3 bundle directories we found during a search.
@@ -78,6 +152,34 @@ impl NativeFile {
             }
         }
     }
+
+    /// Whether this is a regular file with more than one link. Files set up using the hard link
+    /// installation method will show up as regular files, so we need to check this separately.
+    pub fn is_hard_link(&self) -> bool {
+        match &self {
+            NativeFile::Regular(path) => fs::symlink_metadata(path)
+                .map(|metadata| metadata.nlink() > 1)
+                .unwrap_or(false),
+            NativeFile::Symlink(_) | NativeFile::Directory(_) => false,
+        }
+    }
+
+    /// The time this file was last modified. Symlinks are not followed. For directories this is
+    /// the modification time of the most recently modified file or directory within that
+    /// directory, so a bundle counts as modified when any of its files has been modified.
+    pub fn modified(&self) -> Option<SystemTime> {
+        match &self {
+            NativeFile::Symlink(path) | NativeFile::Regular(path) => fs::symlink_metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+            NativeFile::Directory(path) => WalkDir::new(path)
+                .follow_links(false)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+                .max(),
+        }
+    }
 }
 
 /// A plugin as found during the search. This can be either a VST2 plugin or a VST3 module.
@@ -87,6 +189,37 @@ pub enum Plugin {
     Vst3(Vst3Module),
 }
 
+impl Plugin {
+    /// The architecture of the plugin.
+    pub fn architecture(&self) -> LibArchitecture {
+        match self {
+            Plugin::Vst2(Vst2Plugin { architecture, .. })
+            | Plugin::Vst3(Vst3Module { architecture, .. }) => *architecture,
+        }
+    }
+
+    /// The plugin's product name as stored in the plugin file, if it has one.
+    pub fn display_name(&self) -> Option<&str> {
+        match self {
+            Plugin::Vst2(Vst2Plugin { display_name, .. })
+            | Plugin::Vst3(Vst3Module { display_name, .. }) => display_name.as_deref(),
+        }
+    }
+}
+
+/// The plugin formats yabridgectl can set up. This is used to limit `yabridgectl sync` and
+/// `yabridgectl status` to only some plugin formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginFormat {
+    Vst2,
+    Vst3,
+}
+
+impl PluginFormat {
+    /// All supported plugin formats.
+    pub const ALL: &'static [PluginFormat] = &[PluginFormat::Vst2, PluginFormat::Vst3];
+}
+
 /// VST2 plugins we found during a search along with their architecture.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Vst2Plugin {
@@ -94,6 +227,18 @@ pub struct Vst2Plugin {
     pub path: PathBuf,
     /// The architecture of the VST2 plugin.
     pub architecture: LibArchitecture,
+    /// The plugin's product name as stored in the `.dll` file, if it has one. Used in `yabridgectl
+    /// status`.
+    pub display_name: Option<String>,
+    /// Whether this is likely a VST2 shell plugin, like Waves' WaveShell. These expose multiple
+    /// plugins from a single `.dll` file, which some hosts handle differently. See
+    /// `is_vst2_shell()` for how this is detected.
+    pub is_shell: bool,
+    /// Whether the `.dll` file also exports the VST3 entry point. Some installers ship a single
+    /// library that implements both formats with a `.dll` extension. We'll only ever set these up
+    /// as VST2 plugins, so in `yabridgectl sync --verbose` we'll mention that the VST3 version of
+    /// the plugin may be installed somewhere else.
+    pub exports_vst3_entry_point: bool,
 }
 
 /// VST3 modules we found during a search.
@@ -107,6 +252,12 @@ pub struct Vst3Module {
     /// plugisn by the same manufacturer together. We detect this by looking for a parent `VST3`
     /// directory. If we can't find that, this will be `None`.
     pub subdirectory: Option<PathBuf>,
+    /// The directory the merged VST3 bundle for this module should be set up in. This is
+    /// `~/.vst3/yabridge`, or the same directory inside of a Flatpak application's home directory.
+    pub vst3_home: PathBuf,
+    /// The plugin's product name as stored in the module, if it has one. Used in `yabridgectl
+    /// status`.
+    pub display_name: Option<String>,
 }
 
 /// The type of the VST3 module. VST 3.6.10 style bundles require slightly different handling
@@ -157,7 +308,10 @@ impl Vst3Module {
     }
 
     /// If this was a VST 3.6.10 style bundle, then return the path to the `Resources` directory if
-    /// it has one.
+    /// it has one. Some legacy `.vst3` modules instead come with a directory containing their
+    /// resources next to the module with the same name, so `Foo/` for `Foo.vst3`. That directory
+    /// will then be used as the merged bundle's `Resources` directory, unless it or one of its
+    /// immediate subdirectories contains `.vst3` files.
     pub fn original_resources_dir(&self) -> Option<PathBuf> {
         match &self.module {
             Vst3ModuleType::Bundle(bundle_home) => {
@@ -169,6 +323,52 @@ impl Vst3Module {
                     None
                 }
             }
+            Vst3ModuleType::Legacy(module_path) => {
+                // This could also be a manufacturer's subdirectory containing other plugins that
+                // happens to have the same name, and those should not become resources. Those
+                // plugins are sometimes grouped in another level of subdirectories.
+                let path = module_path.with_extension("");
+                if !path.is_dir() {
+                    return None;
+                }
+
+                let contains_vst3_modules = WalkDir::new(&path)
+                    .min_depth(1)
+                    .max_depth(2)
+                    .into_iter()
+                    .any(|entry| match entry {
+                        Ok(entry) => {
+                            entry
+                                .path()
+                                .extension()
+                                .and_then(|extension| extension.to_str())
+                                == Some("vst3")
+                        }
+                        // Better safe than sorry
+                        Err(_) => true,
+                    });
+                if !contains_vst3_modules {
+                    Some(path)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// If this was a VST 3.6.10 style bundle, then return the path to the `Presets` directory if it
+    /// has one. Some plugins ship their factory presets here instead of in `Resources`.
+    pub fn original_presets_dir(&self) -> Option<PathBuf> {
+        match &self.module {
+            Vst3ModuleType::Bundle(bundle_home) => {
+                let mut path = bundle_home.join("Contents");
+                path.push("Presets");
+                if path.exists() {
+                    Some(path)
+                } else {
+                    None
+                }
+            }
             Vst3ModuleType::Legacy(_) => None,
         }
     }
@@ -181,10 +381,11 @@ impl Vst3Module {
     ///        Wine prefixes?
     pub fn target_bundle_home(&self) -> PathBuf {
         match &self.subdirectory {
-            Some(directory) => yabridge_vst3_home()
+            Some(directory) => self
+                .vst3_home
                 .join(directory)
                 .join(self.original_module_name()),
-            None => yabridge_vst3_home().join(self.original_module_name()),
+            None => self.vst3_home.join(self.original_module_name()),
         }
     }
 
@@ -192,6 +393,11 @@ impl Vst3Module {
     /// of this module. The path here depends on whether we're using a 32-bit or 64-bit version of
     /// yabridge. If the configuration is not given (for instance, becuase yabridge is not set up
     /// properly) we'll assume the module should be 64-bit.
+    ///
+    /// NOTE: This intentionally does not depend on the Windows module's architecture. The native
+    ///       module gets loaded by the Linux host, so its directory has to match
+    ///       `libyabridge-vst3.so`'s architecture. A 64-bit `libyabridge-vst3.so` will find a
+    ///       32-bit module in `x86-win` on its own and bridge it using the 32-bit plugin host.
     pub fn target_native_module_path(&self, config: Option<&YabridgeFiles>) -> PathBuf {
         let native_module_name = match &self.module {
             Vst3ModuleType::Legacy(path) | Vst3ModuleType::Bundle(path) => path
@@ -239,8 +445,16 @@ impl Vst3Module {
         path
     }
 
+    /// The same as `target_resources_dir()`, but for the bundle's `Presets` directory.
+    pub fn target_presets_dir(&self) -> PathBuf {
+        let mut path = self.target_bundle_home();
+        path.push("Contents");
+        path.push("Presets");
+        path
+    }
+
     /// Get a textual representation of the module type. Used in `yabridgectl status`.
-    pub fn type_str(&self) -> &str {
+    pub fn type_str(&self) -> &'static str {
         match &self.module {
             Vst3ModuleType::Legacy(_) => "legacy",
             Vst3ModuleType::Bundle(_) => "bundle",
@@ -250,7 +464,7 @@ impl Vst3Module {
 
 /// The architecture of a library file (either `.dll` or `.so` depending on the context). Needed so
 /// we can create a merged bundle for VST3 plugins.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy)]
 pub enum LibArchitecture {
     Lib32,
     Lib64,
@@ -335,99 +549,814 @@ impl SearchResults {
     }
 }
 
-/// Find all `.dll`, `.vst3` and `.so` files under a directory. These results can be filtered down
-/// to actual VST2 plugins and VST3 modules using `search()`. Any path found in the blacklist will
-/// be pruned immediately, so this can be used to both not index individual files and to skip an
-/// entire directory.
-pub fn index(directory: &Path, blacklist: &HashSet<&Path>) -> SearchIndex {
-    let mut dll_files: Vec<PathBuf> = Vec::new();
-    let mut vst3_files: Vec<PathBuf> = Vec::new();
-    let mut so_files: Vec<NativeFile> = Vec::new();
-    // XXX: We're silently skipping directories and files we don't have permission to read. This
-    //      sounds like the expected behavior, but I"m not entirely sure.
-    for (file_idx, entry) in WalkDir::new(directory)
-        .follow_links(true)
-        .into_iter()
-        .filter_entry(|e| !blacklist.contains(e.path()))
-        .filter_map(|e| e.ok())
-        .filter(|e| !e.file_type().is_dir())
-        .enumerate()
+/// Check whether there are any VST2 preset (`.fxp`) or preset bank (`.fxb`) files in the same
+/// directory as `path`. This is a strong hint that a `.dll` file is a VST2 plugin, even if we
+/// couldn't detect it as one. This is only used to explain why files were skipped in
+/// `yabridgectl sync --verbose`, since it's not a replacement for checking the exported functions.
+pub fn has_vst2_preset_siblings(path: &Path) -> bool {
+    let directory = match path.parent().map(fs::read_dir) {
+        Some(Ok(directory)) => directory,
+        _ => return false,
+    };
+
+    directory.filter_map(|entry| entry.ok()).any(|entry| {
+        matches!(
+            entry
+                .path()
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map(|extension| extension.to_lowercase())
+                .as_deref(),
+            Some("fxp") | Some("fxb")
+        )
+    })
+}
+
+/// Find all VST3 modules that would be set up in the same merged bundle in `~/.vst3/yabridge` with
+/// the same architecture. Only the first of those modules will be set up during a sync. The
+/// results are grouped by the target bundle and the architecture, and the module paths are in the
+/// order `yabridgectl sync` would encounter them in.
+pub fn find_vst3_clashes<'a>(
+    results: impl IntoIterator<Item = &'a SearchResults>,
+) -> BTreeMap<(PathBuf, LibArchitecture), Vec<PathBuf>> {
+    let mut targets: BTreeMap<(PathBuf, LibArchitecture), Vec<PathBuf>> = BTreeMap::new();
+    for search_results in results {
+        for plugin in &search_results.plugins {
+            if let Plugin::Vst3(module) = plugin {
+                targets
+                    .entry((module.target_bundle_home(), module.architecture))
+                    .or_default()
+                    .push(module.original_path().to_owned());
+            }
+        }
+    }
+
+    targets.retain(|_, sources| sources.len() > 1);
+    targets
+}
+
+/// Check whether a VST2 plugin is a shell plugin that exposes multiple plugins from a single `.dll`
+/// file. Shell plugins export the same entry points as regular VST2 plugins and they only identify
+/// themselves as a shell when the host queries the plugin's category, so this can't be detected
+/// from the exported functions. Instead we'll compare the file name and the product name to the
+/// names of known shell plugins.
+fn is_vst2_shell(path: &Path, product_name: Option<&str>) -> bool {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase());
+    let product_name = product_name.map(|name| name.to_lowercase());
+
+    VST2_SHELL_NAMES.iter().any(|shell_name| {
+        let shell_name = shell_name.to_lowercase();
+        file_name
+            .iter()
+            .chain(product_name.iter())
+            .any(|name| name.contains(&shell_name))
+    })
+}
+
+/// Parse the information we need from a `.dll` or `.vst3` file using the selected parser, without
+/// going through the `Pe32Cache`. With `Pe32Parser::Auto` this returns an error if `winedump` could
+/// not be run, and with `Pe32Parser::Builtin` this returns an error if the file could not be
+/// parsed.
+pub fn parse_pe32_info(path: &Path, parser: Pe32Parser) -> Result<Pe32Info> {
+    match parser {
+        Pe32Parser::Auto => parse_pe32_info_winedump(path),
+        Pe32Parser::Builtin => parse_pe32_info_builtin(path),
+    }
+}
+
+/// Parse the information we need from a `.dll` or `.vst3` file by reading its headers and export
+/// table ourselves. This returns an error if the file's headers are malformed.
+fn parse_pe32_info_builtin(path: &Path) -> Result<Pe32Info> {
+    const IMAGE_FILE_MACHINE_I386: u16 = 0x14C;
+
+    let (machine, exported_functions) = match utils::get_exported_functions(path)
+        .with_context(|| format!("Could not parse '{}'", path.display()))?
     {
+        Some(exports) => exports,
+        None => {
+            return Ok(Pe32Info {
+                is_pe32: false,
+                is_32_bit: false,
+                exports_vst2_entry_point: false,
+                exports_vst3_entry_point: false,
+                product_name: None,
+            })
+        }
+    };
+    let exports = |names: &[&str]| {
+        exported_functions
+            .iter()
+            .any(|function| names.contains(&function.as_str()))
+    };
+
+    Ok(Pe32Info {
+        is_pe32: true,
+        is_32_bit: machine == IMAGE_FILE_MACHINE_I386,
+        exports_vst2_entry_point: exports(&["VSTPluginMain", "main"]),
+        exports_vst3_entry_point: exports(&["GetPluginFactory"]),
+        product_name: utils::get_product_name(path).ok().flatten(),
+    })
+}
+
+/// Parse the information we need from a `.dll` or `.vst3` file using `winedump`. This returns an
+/// error if `winedump` could not be run.
+fn parse_pe32_info_winedump(path: &Path) -> Result<Pe32Info> {
+    lazy_static! {
+        static ref VST2_AUTOMATON: AhoCorasick =
+            AhoCorasick::new_auto_configured(&["VSTPluginMain", "main"]);
+        static ref VST3_AUTOMATON: AhoCorasick =
+            AhoCorasick::new_auto_configured(&["GetPluginFactory"]);
+        static ref DLL32_AUTOMATON: AhoCorasick =
+            AhoCorasick::new_auto_configured(&["Machine:                      014C"]);
+        static ref PE32_AUTOMATON: AhoCorasick = AhoCorasick::new_auto_configured(&["Machine:"]);
+    }
+
+    let winedump = |args: &[&str]| {
+        Command::new("winedump")
+            .args(args)
+            .arg(path)
+            .output()
+            .context(
+                "Could not find 'winedump'. In some distributions this is part of a seperate Wine \
+                 tools package.",
+            )
+            .map(|output| output.stdout)
+    };
+
+    let headers = winedump(&[])?;
+    let exported_functions = winedump(&["-j", "export"])?;
+    Ok(Pe32Info {
+        is_pe32: PE32_AUTOMATON.is_match(&headers),
+        is_32_bit: DLL32_AUTOMATON.is_match(&headers),
+        exports_vst2_entry_point: VST2_AUTOMATON.is_match(&exported_functions),
+        exports_vst3_entry_point: VST3_AUTOMATON.is_match(&exported_functions),
+        product_name: utils::get_product_name(path).ok().flatten(),
+    })
+}
+
+/// Find all `.dll`, `.vst3` and `.so` files under a directory. These results can be filtered down
+/// to actual VST2 plugins and VST3 modules using `search()`. Any path found in the blacklist or
+/// matching one of the blacklisted glob patterns will be pruned immediately, so this can be used to
+/// both not index individual files and to skip an entire directory. The patterns from a
+/// `.yabridge-ignore` file at the root of `directory` are applied the same way. Subdirectories are
+/// walked in parallel, so the order of the files in the resulting index is not stable. If
+/// `progress` is set, then the indexed files will be counted there.
+pub fn index(
+    directory: &Path,
+    blacklist: &HashSet<&Path>,
+    blacklist_patterns: &[glob::Pattern],
+    progress: Option<&Progress>,
+) -> SearchIndex {
+    // Warnings are printed through the progress bar so they don't get mangled by it
+    let hidden_progress = Progress::hidden();
+    let progress = progress.unwrap_or(&hidden_progress);
+    // The progress bar is shared between plugin locations, so the 100.000 files warning in
+    // `index_directory()` needs its own count
+    let indexed_files = AtomicUsize::new(0);
+
+    let ignore_patterns = read_ignore_file(directory, progress);
+    let is_blacklisted = |path: &Path| {
+        blacklist.contains(path)
+            || blacklist_patterns
+                .iter()
+                .chain(ignore_patterns.iter())
+                .any(|pattern| pattern.matches_path(path))
+    };
+    if is_blacklisted(directory) {
+        return SearchIndex::default();
+    }
+
+    let device = fs::metadata(directory)
+        .map(|metadata| metadata.dev())
+        .unwrap_or(0);
+    index_directory(
+        directory,
+        device,
+        &[],
+        &is_blacklisted,
+        &indexed_files,
+        progress,
+    )
+}
+
+/// Whether a file or directory contains metadata left behind by macOS, like the `__MACOSX`
+/// directories created when extracting zip files made on a Mac and `._Foo.dll` AppleDouble files.
+/// These are never plugins even though they may have a `.dll` or `.vst3` extension, so we skip them
+/// while indexing instead of trying to parse them.
+fn is_macos_metadata(file_name: &OsStr) -> bool {
+    let file_name = file_name.as_bytes();
+    file_name == b"__MACOSX" || file_name.starts_with(b"._")
+}
+
+/// Read the patterns from the `.yabridge-ignore` file in `directory`, if it exists. These follow a
+/// subset of the gitignore syntax: empty lines and lines starting with `#` are ignored, patterns
+/// containing a slash (other than a trailing one) are relative to `directory`, and all other
+/// patterns match files and directories with that name at any depth. The patterns are converted to
+/// absolute glob patterns so they can be matched the same way as the blacklisted patterns.
+/// Negated patterns are not supported. Invalid patterns are skipped with a warning.
+fn read_ignore_file(directory: &Path, progress: &Progress) -> Vec<glob::Pattern> {
+    let ignore_file_path = directory.join(IGNORE_FILE_NAME);
+    let contents = match fs::read_to_string(&ignore_file_path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+
+    let escaped_directory = glob::Pattern::escape(&directory.to_string_lossy());
+    let mut patterns = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let pattern = line.trim_end_matches('/');
+        let absolute_pattern = if pattern.contains('/') {
+            format!("{}/{}", escaped_directory, pattern.trim_start_matches('/'))
+        } else {
+            format!("{}/**/{}", escaped_directory, pattern)
+        };
+        match glob::Pattern::new(&absolute_pattern) {
+            Ok(pattern) => patterns.push(pattern),
+            Err(err) => progress.println(&format!(
+                "\n{}",
+                utils::wrap(&format!(
+                    "Warning: Skipping invalid pattern '{}' in '{}': {}",
+                    line,
+                    utils::display_path(&ignore_file_path),
+                    err
+                ))
+            )),
+        }
+    }
+
+    patterns
+}
+
+/// The recursive part of `index()`. We'll index all files in `directory` on the current thread,
+/// and then recurse into all subdirectories in parallel. Symlinks are followed, so `ancestors`
+/// contains the device and inode numbers of all parent directories to be able to detect loops.
+/// `device` is the device number of the plugin location, used to detect symlinks to plugins on
+/// other file systems. `indexed_files` counts the files indexed in this plugin location, while
+/// `progress` may be shared with other plugin locations. Both are shared between all threads.
+fn index_directory(
+    directory: &Path,
+    device: u64,
+    ancestors: &[(u64, u64)],
+    is_blacklisted: &(dyn Fn(&Path) -> bool + Sync),
+    indexed_files: &AtomicUsize,
+    progress: &Progress,
+) -> SearchIndex {
+    let mut index = SearchIndex::default();
+    // We'll skip over directories and files we don't have permission to read, but we'll keep track
+    // of them so we can tell the user that the scan was incomplete
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            if err.kind() == io::ErrorKind::PermissionDenied {
+                index.unreadable_paths.push(directory.to_owned());
+            }
+
+            return index;
+        }
+    };
+    let ancestors: Vec<(u64, u64)> = match fs::metadata(directory) {
+        Ok(metadata) => ancestors
+            .iter()
+            .copied()
+            .chain(std::iter::once((metadata.dev(), metadata.ino())))
+            .collect(),
+        Err(_) => ancestors.to_vec(),
+    };
+
+    let mut subdirectories: Vec<PathBuf> = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if is_blacklisted(&path) || is_macos_metadata(&entry.file_name()) {
+            continue;
+        }
+
+        let is_symlink = entry
+            .file_type()
+            .map(|file_type| file_type.is_symlink())
+            .unwrap_or(false);
+        let is_plugin_symlink = is_symlink
+            && matches!(
+                path.extension().and_then(|os| os.to_str()),
+                Some("dll") | Some("vst3")
+            );
+        // Like `WalkDir::follow_links(true)`, we'll look at the symlink's target here. Broken
+        // symlinks are skipped, but we'll keep track of the ones that look like plugins.
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                match err.kind() {
+                    io::ErrorKind::PermissionDenied => index.unreadable_paths.push(path),
+                    io::ErrorKind::NotFound if is_plugin_symlink => {
+                        if let Ok(target) = fs::read_link(&path) {
+                            index
+                                .plugin_symlinks
+                                .push(PluginSymlink::Broken { path, target });
+                        }
+                    }
+                    _ => (),
+                }
+
+                continue;
+            }
+        };
+        if is_plugin_symlink && metadata.dev() != device {
+            if let Ok(target) = fs::read_link(&path) {
+                index.plugin_symlinks.push(PluginSymlink::CrossDevice {
+                    path: path.clone(),
+                    target,
+                });
+            }
+        }
+
+        if metadata.is_dir() {
+            // Symlinks pointing to one of its parent directories would otherwise cause an infinite
+            // loop
+            if !ancestors.contains(&(metadata.dev(), metadata.ino())) {
+                subdirectories.push(path);
+            }
+
+            continue;
+        }
+
         // This is a bit of an odd warning, but I can see it happening that someone adds their
         // entire home directory by accident. Removing the home directory would cause yabridgectl to
         // scan for leftover `.so` files, which would of course take an enternity. This warning will
         // at least tell the user what's happening and that they can safely cancel the scan.
-        if file_idx == 100_000 {
-            eprintln!(
+        progress.inc_indexed();
+        if indexed_files.fetch_add(1, Ordering::Relaxed) == 100_000 {
+            progress.println(
                 "Indexed over 100.000 files, press Ctrl+C to cancel this operation if this was not \
-                 intentional."
+                 intentional.",
             )
         }
 
-        match entry.path().extension().and_then(|os| os.to_str()) {
-            Some("dll") => dll_files.push(entry.into_path()),
-            Some("vst3") => vst3_files.push(entry.into_path()),
+        match path.extension().and_then(|os| os.to_str()) {
+            Some("dll") => index.dll_files.push(path),
+            Some("vst3") => index.vst3_files.push(path),
             Some("so") => {
-                if entry.path_is_symlink() {
-                    so_files.push(NativeFile::Symlink(entry.into_path()));
+                if let Some(bundle) = native_vst3_bundle(&path) {
+                    index.native_vst3_bundles.push(bundle.to_owned());
+                } else if is_symlink {
+                    index.so_files.push(NativeFile::Symlink(path));
                 } else {
-                    so_files.push(NativeFile::Regular(entry.into_path()));
+                    index.so_files.push(NativeFile::Regular(path));
                 }
             }
             _ => (),
         }
     }
 
+    let subdirectory_indices: Vec<SearchIndex> = subdirectories
+        .par_iter()
+        .map(|subdirectory| {
+            index_directory(
+                subdirectory,
+                device,
+                &ancestors,
+                is_blacklisted,
+                indexed_files,
+                progress,
+            )
+        })
+        .collect();
+    for subdirectory_index in subdirectory_indices {
+        index.dll_files.extend(subdirectory_index.dll_files);
+        index.vst3_files.extend(subdirectory_index.vst3_files);
+        index.so_files.extend(subdirectory_index.so_files);
+        index
+            .unreadable_paths
+            .extend(subdirectory_index.unreadable_paths);
+        index
+            .plugin_symlinks
+            .extend(subdirectory_index.plugin_symlinks);
+        index
+            .native_vst3_bundles
+            .extend(subdirectory_index.native_vst3_bundles);
+    }
+
+    index
+}
+
+/// If `path` is a native Linux module inside of a VST3 bundle, so
+/// `<plugin_name>.vst3/Contents/<architecture>-linux/<plugin_name>.so`, then return the path to that
+/// bundle. Universal bundles can contain both these native modules and Windows modules.
+fn native_vst3_bundle(path: &Path) -> Option<&Path> {
+    let arch_dir = path.parent()?;
+    let contents_dir = arch_dir.parent()?;
+    let bundle = contents_dir.parent()?;
+    let is_native_module = arch_dir.file_name()?.to_str()?.ends_with("-linux")
+        && contents_dir.file_name()? == "Contents"
+        && bundle.extension()? == "vst3";
+
+    if is_native_module {
+        Some(bundle)
+    } else {
+        None
+    }
+}
+
+/// Find the user preset directories for VST3 plugins in a Wine prefix. Windows VST3 hosts store
+/// these in `Documents/VST3 Presets/<vendor>/<plugin>` in the user's home directory. Returns
+/// `(vendor, plugin, path)` tuples, where `path` is the plugin's preset directory.
+pub fn wine_vst3_preset_dirs(prefix: &Path) -> Vec<(String, String, PathBuf)> {
+    let subdirectories = |directory: &Path| -> Vec<fs::DirEntry> {
+        match fs::read_dir(directory) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    };
+
+    let mut preset_dirs = Vec::new();
+    for user_home in subdirectories(&prefix.join("drive_c").join("users")) {
+        // Older versions of Wine used `My Documents` instead
+        for documents_dir in &["Documents", "My Documents"] {
+            let presets_dir = user_home.path().join(documents_dir).join("VST3 Presets");
+            for vendor in subdirectories(&presets_dir) {
+                for plugin in subdirectories(&vendor.path()) {
+                    if let (Ok(vendor_name), Ok(plugin_name)) = (
+                        vendor.file_name().into_string(),
+                        plugin.file_name().into_string(),
+                    ) {
+                        preset_dirs.push((vendor_name, plugin_name, plugin.path()));
+                    }
+                }
+            }
+        }
+    }
+
+    preset_dirs
+}
+
+/// Find all merged VST3 bundles in `vst3_home` (usually `~/.vst3/yabridge`). Symlinks are not
+/// followed since the bundles contain symlinks to directories in the plugin locations, and those
+/// could also contain symlink cycles. `vst3_home` itself may still be a symlink.
+fn find_vst3_bundles(vst3_home: &Path) -> impl Iterator<Item = DirEntry> {
+    WalkDir::new(vst3_home)
+        .follow_links(false)
+        .same_file_system(true)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || entry.file_type().is_dir())
+        .filter_map(|e| e.ok())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .and_then(|extension| extension.to_str())
+                == Some("vst3")
+        })
+}
+
+/// Find the files in `vst3_home` (usually `~/.vst3/yabridge`) that were set up for plugins in one
+/// of `locations`. This is used to clean up after removing a plugin location. The merged bundles
+/// contain symlinks to the original Windows VST3 modules, so we can tell which bundles belonged to
+/// those locations by looking at where those symlinks point to. If all Windows modules in a bundle
+/// came from `locations` then only the bundle's directory will be returned. Otherwise we'll only
+/// return the symlinks pointing to one of `locations`. The paths in `locations` should be
+/// canonicalized.
+pub fn index_vst3_location_orphans(vst3_home: &Path, locations: &[&Path]) -> Vec<NativeFile> {
+    let mut orphans = Vec::new();
+    let installed_vst3_bundles = find_vst3_bundles(vst3_home);
+    for bundle in installed_vst3_bundles {
+        let mut bundle_orphans = Vec::new();
+        let mut has_other_modules = false;
+        for entry in WalkDir::new(bundle.path())
+            .follow_links(false)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|entry| entry.path_is_symlink())
+        {
+            let target = match entry.path().read_link() {
+                // Relative symlinks created with `--relative-symlinks` are relative to the
+                // directory containing the symlink. The target itself may also be a symlink.
+                Ok(target) => {
+                    utils::normalize_parent_path(&entry.path().parent().unwrap().join(target))
+                }
+                Err(_) => continue,
+            };
+
+            if locations
+                .iter()
+                .any(|location| target.starts_with(location))
+            {
+                bundle_orphans.push(NativeFile::Symlink(entry.into_path()));
+            } else if entry
+                .path()
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .and_then(|name| name.to_str())
+                .map(|name| name.ends_with("-win"))
+                .unwrap_or(false)
+            {
+                // This bundle also contains a Windows module from another location, so we can't
+                // remove the entire bundle
+                has_other_modules = true;
+            }
+        }
+
+        if has_other_modules {
+            orphans.extend(bundle_orphans);
+        } else if !bundle_orphans.is_empty() {
+            orphans.push(NativeFile::Directory(bundle.into_path()));
+        }
+    }
+
+    orphans
+}
+
+/// Find all files in `vst3_home` (usually `~/.vst3/yabridge`) that were not set up during the
+/// current sync by walking the entire directory. `known_vst3_files` contains the files set up
+/// during the sync, grouped by merged bundle. This returns `(bundle, file)` pairs. If an entire
+/// bundle is unknown then only the bundle's directory will be returned. This is only used when
+/// there's no manifest from a previous sync, since walking all of `~/.vst3/yabridge` can be slow.
+pub fn index_vst3_orphans(
+    vst3_home: &Path,
+    known_vst3_files: &HashMap<PathBuf, HashSet<PathBuf>>,
+) -> Vec<(PathBuf, NativeFile)> {
+    let mut orphans = Vec::new();
+    let installed_vst3_bundles = find_vst3_bundles(vst3_home);
+    for bundle in installed_vst3_bundles {
+        match known_vst3_files.get(bundle.path()) {
+            None => orphans.push((
+                bundle.path().to_owned(),
+                NativeFile::Directory(bundle.path().to_owned()),
+            )),
+            Some(managed_vst3_bundle_files) => {
+                // Find orphan files and symlinks within this bundle. We need this to be able to
+                // switch between 32-bit and 64-bit versions of both yabridge and the Windows plugin
+                orphans.extend(
+                    WalkDir::new(bundle.path())
+                        .follow_links(false)
+                        .into_iter()
+                        .filter_map(|e| e.ok())
+                        .filter_map(|entry| {
+                            let managed_file = managed_vst3_bundle_files.contains(entry.path());
+                            match get_file_type(entry.path().to_owned()).unwrap() {
+                                // Don't remove directories, since we're not tracking the
+                                // directories within the bundle
+                                NativeFile::Directory(_) => None,
+                                unknown_file if !managed_file => {
+                                    Some((bundle.path().to_owned(), unknown_file))
+                                }
+                                _ => None,
+                            }
+                        }),
+                );
+            }
+        }
+    }
+
+    orphans
+}
+
+/// The number of files yabridgectl set up somewhere and their combined size, split up by the kind of
+/// file. Used for `yabridgectl du`. Hard links and symlinks don't take up any additional space, so
+/// only the size of the copies is tracked.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DiskUsage {
+    pub copies: usize,
+    pub copies_size: u64,
+    pub hard_links: usize,
+    pub symlinks: usize,
+}
+
+impl DiskUsage {
+    /// Count the file at `path`. Directories and files that don't exist are ignored. Regular files
+    /// with more than one link are counted as hard links.
+    pub fn add(&mut self, path: &Path) {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => self.symlinks += 1,
+            Ok(metadata) if metadata.is_file() && metadata.nlink() > 1 => self.hard_links += 1,
+            Ok(metadata) if metadata.is_file() => {
+                self.copies += 1;
+                self.copies_size += metadata.len();
+            }
+            _ => (),
+        }
+    }
+
+    /// Add the counts from another `DiskUsage` to this one.
+    pub fn extend(&mut self, other: &DiskUsage) {
+        self.copies += other.copies;
+        self.copies_size += other.copies_size;
+        self.hard_links += other.hard_links;
+        self.symlinks += other.symlinks;
+    }
+}
+
+/// Count all files in `vst3_home` (usually `~/.vst3/yabridge`) for `yabridgectl du`. Everything in
+/// there has been set up by yabridgectl, so we don't need to know which plugins these files belong
+/// to.
+pub fn vst3_home_disk_usage(vst3_home: &Path) -> DiskUsage {
+    let mut usage = DiskUsage::default();
+    for entry in WalkDir::new(vst3_home)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        usage.add(entry.path());
+    }
+
+    usage
+}
+
+/// Find all broken symlinks inside of the merged bundles in `vst3_home` (usually
+/// `~/.vst3/yabridge`). These are the symlinks to Windows VST3 modules and to their `Resources` and
+/// `Presets` directories. They can break when the original plugin has been moved or uninstalled.
+/// This returns `(bundle, symlink)` pairs.
+pub fn index_broken_vst3_symlinks(vst3_home: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let installed_vst3_bundles = find_vst3_bundles(vst3_home);
+
+    let mut broken_symlinks = Vec::new();
+    for bundle in installed_vst3_bundles {
+        broken_symlinks.extend(
+            WalkDir::new(bundle.path())
+                .follow_links(false)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|entry| entry.path_is_symlink() && fs::metadata(entry.path()).is_err())
+                .map(|entry| (bundle.path().to_owned(), entry.into_path())),
+        );
+    }
+
+    broken_symlinks
+}
+
+/// Index a single plugin file added with `yabridgectl add <file>`. Unlike `index()` this only
+/// looks at the file itself and the `.so` file next to it, if the file is a `.dll` file. Any other
+/// kinds of files are ignored.
+pub fn index_file(file: &Path) -> SearchIndex {
+    let mut dll_files: Vec<PathBuf> = Vec::new();
+    let mut vst3_files: Vec<PathBuf> = Vec::new();
+    let mut so_files: Vec<NativeFile> = Vec::new();
+    match file.extension().and_then(|os| os.to_str()) {
+        Some("dll") => {
+            dll_files.push(file.to_owned());
+            so_files.extend(get_file_type(file.with_extension("so")));
+        }
+        Some("vst3") => vst3_files.push(file.to_owned()),
+        _ => (),
+    }
+
     SearchIndex {
         dll_files,
         vst3_files,
         so_files,
+        unreadable_paths: Vec::new(),
+        plugin_symlinks: Vec::new(),
+        native_vst3_bundles: Vec::new(),
     }
 }
 
 impl SearchIndex {
+    /// Drop all indexed files that are only relevant for plugin formats not in `formats`, so they
+    /// won't have to be parsed during the search. The `.so` files are only used for VST2 plugins,
+    /// so those are dropped together with the `.dll` files to prevent them from being reported as
+    /// orphans.
+    pub fn retain_formats(mut self, formats: &[PluginFormat]) -> Self {
+        if !formats.contains(&PluginFormat::Vst2) {
+            self.dll_files.clear();
+            self.so_files.clear();
+        }
+        if !formats.contains(&PluginFormat::Vst3) {
+            self.vst3_files.clear();
+            self.native_vst3_bundles.clear();
+        }
+
+        self
+    }
+
     /// Filter these indexing results down to actual VST2 plugins and VST3 modules. This will skip
-    /// all invalid files, such as regular `.dll` libraries. Will return an error if `winedump`
-    /// could not be found.
-    pub fn search(self) -> Result<SearchResults> {
-        lazy_static! {
-            static ref VST2_AUTOMATON: AhoCorasick =
-                AhoCorasick::new_auto_configured(&["VSTPluginMain", "main"]);
-            static ref VST3_AUTOMATON: AhoCorasick =
-                AhoCorasick::new_auto_configured(&["GetPluginFactory"]);
-            static ref DLL32_AUTOMATON: AhoCorasick =
-                AhoCorasick::new_auto_configured(&["Machine:                      014C"]);
-        }
-
-        let winedump = |args: &[&str], path: &Path| {
-            Command::new("winedump")
-                .args(args)
-                .arg(path)
-                .output()
-                .context(
-                    "Could not find 'winedump'. In some distributions this is part of a seperate \
-                     Wine tools package.",
-                )
-                .map(|output| output.stdout)
+    /// all invalid files, such as regular `.dll` libraries. Files are parsed using `parser`, and
+    /// this will return an error if `winedump` is used but could not be found. With
+    /// `Pe32Parser::Builtin` files that can't be parsed are skipped and listed in the results'
+    /// `parse_errors` instead. If `max_parse_errors` is set, then this will also return an error
+    /// once more than that many files turned out not to be valid PE32(+) binaries. That usually means
+    /// that the wrong directory was added. Merged bundles for the found VST3 modules will be set up
+    /// in `vst3_home`. If `cache` is set, then files that haven't changed since they were last
+    /// parsed won't have to be parsed again, and newly parsed files will be added to the cache. If
+    /// `progress` is set, then the parsed files will be counted there.
+    pub fn search(
+        self,
+        max_parse_errors: Option<usize>,
+        parser: Pe32Parser,
+        vst3_home: &Path,
+        cache: Option<&Mutex<Pe32Cache>>,
+        progress: Option<&Progress>,
+    ) -> Result<SearchResults> {
+        let num_parse_errors = AtomicUsize::new(0);
+        let parse_errors: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+        let pe32_info = |path: &Path| -> Result<Pe32Info> {
+            // Hashing a file is a lot cheaper than parsing it twice
+            let hash = cache.and_then(|_| utils::hash_file(path).ok());
+            let cached_info = cache
+                .zip(hash)
+                .and_then(|(cache, hash)| cache.lock().unwrap().get(path, hash));
+            let info = match cached_info {
+                Some(info) => info,
+                None => match parse_pe32_info(path, parser) {
+                    Ok(info) => {
+                        if let Some((cache, hash)) = cache.zip(hash) {
+                            cache
+                                .lock()
+                                .unwrap()
+                                .insert(path.to_owned(), hash, info.clone());
+                        }
+
+                        info
+                    }
+                    // With the built-in parser these files are simply skipped. They're not
+                    // cached, so they will be parsed again during the next search.
+                    Err(err) if parser == Pe32Parser::Builtin => {
+                        parse_errors
+                            .lock()
+                            .unwrap()
+                            .push((path.to_owned(), format!("{:#}", err)));
+
+                        Pe32Info {
+                            is_pe32: false,
+                            is_32_bit: false,
+                            exports_vst2_entry_point: false,
+                            exports_vst3_entry_point: false,
+                            product_name: None,
+                        }
+                    }
+                    Err(err) => return Err(err),
+                },
+            };
+
+            // We'll count the files that are not PE32(+) binaries to abort the scan when there are
+            // too many of them
+            if let Some(max_parse_errors) = max_parse_errors {
+                if !info.is_pe32
+                    && num_parse_errors.fetch_add(1, Ordering::Relaxed) >= max_parse_errors
+                {
+                    return Err(anyhow!(
+                        "Aborting the scan after more than {} files could not be parsed. This \
+                         location may contain something other than plugins. You can use \
+                         'yabridgectl blacklist' to skip parts of this location, or you can \
+                         change this limit with 'yabridgectl set --max-parse-errors=<n>'.",
+                        max_parse_errors
+                    ));
+                }
+            }
+
+            if let Some(progress) = progress {
+                progress.inc_parsed();
+            }
+
+            Ok(info)
         };
-        let pe32_info = |path: &Path| winedump(&[], path);
-        let exported_functions = |path: &Path| winedump(&["-j", "export"], path);
+
+        // Some plugins ship with managed .NET assemblies. These don't export any native functions
+        // so they can never be plugins, and we can skip them without having to parse them. Files we
+        // can't parse are left for the regular parser to deal with.
+        let is_managed = |path: &PathBuf| utils::is_managed_assembly(path).unwrap_or(false);
+        let (mut skipped_managed_files, dll_files): (Vec<PathBuf>, Vec<PathBuf>) =
+            self.dll_files.into_par_iter().partition(is_managed);
+        // `.vst3` files can also be native Linux VST3 modules. Those don't need to be bridged, so
+        // they're listed along with the native bundles instead of failing to parse as PE32 files.
+        let (native_vst3_files, vst3_files): (Vec<PathBuf>, Vec<PathBuf>) = self
+            .vst3_files
+            .into_par_iter()
+            .partition(|path| utils::is_elf_file(path));
+        let (skipped_managed_vst3_files, vst3_files): (Vec<PathBuf>, Vec<PathBuf>) =
+            vst3_files.into_par_iter().partition(is_managed);
+        skipped_managed_files.extend(skipped_managed_vst3_files);
+        if let Some(progress) = progress {
+            progress.add_to_parse(dll_files.len() + vst3_files.len());
+        }
 
         // We'll have to figure out which `.dll` files are VST2 plugins and which should be skipped
         // by checking whether the file contains one of the VST2 entry point functions. This vector
         // will contain an `Err(path)` if `path` was not a valid VST2 plugin.
-        let is_vst2_plugin: Vec<Result<Vst2Plugin, PathBuf>> = self
-            .dll_files
+        let is_vst2_plugin: Vec<Result<Vst2Plugin, PathBuf>> = dll_files
             .into_par_iter()
             .map(|path| {
-                let architecture = if DLL32_AUTOMATON.is_match(pe32_info(&path)?) {
+                let info = pe32_info(&path)?;
+                let architecture = if info.is_32_bit {
                     LibArchitecture::Lib32
                 } else {
                     LibArchitecture::Lib64
                 };
 
-                if VST2_AUTOMATON.is_match(exported_functions(&path)?) {
-                    Ok(Ok(Vst2Plugin { path, architecture }))
+                if info.exports_vst2_entry_point {
+                    Ok(Ok(Vst2Plugin {
+                        is_shell: is_vst2_shell(&path, info.product_name.as_deref()),
+                        exports_vst3_entry_point: info.exports_vst3_entry_point,
+                        path,
+                        architecture,
+                        display_name: info.product_name,
+                    }))
                 } else {
                     Ok(Err(path))
                 }
@@ -438,17 +1367,17 @@ impl SearchIndex {
         // to figure out of the `.vst3` file is a legacy standalone VST3 module, or part of a VST
         // 3.6.10 bundle. We also need to know the plugin's architecture because we're going to
         // create a univeral VST3 bundle.
-        let is_vst3_module: Vec<Result<Vst3Module, PathBuf>> = self
-            .vst3_files
+        let is_vst3_module: Vec<Result<Vst3Module, PathBuf>> = vst3_files
             .into_par_iter()
             .map(|module_path| {
-                let architecture = if DLL32_AUTOMATON.is_match(pe32_info(&module_path)?) {
+                let info = pe32_info(&module_path)?;
+                let architecture = if info.is_32_bit {
                     LibArchitecture::Lib32
                 } else {
                     LibArchitecture::Lib64
                 };
 
-                if VST3_AUTOMATON.is_match(exported_functions(&module_path)?) {
+                if info.exports_vst3_entry_point {
                     // Now we'll have to figure out if the plugin is part of a VST 3.6.10 style
                     // bundle or a legacy `.vst3` DLL file. A WIndows VST3 bundle contains at least
                     // `<plugin_name>.vst3/Contents/<architecture_string>/<plugin_name>.vst3`, so
@@ -507,6 +1436,8 @@ impl SearchIndex {
                         module,
                         architecture,
                         subdirectory,
+                        vst3_home: vst3_home.to_owned(),
+                        display_name: info.product_name,
                     }))
                 } else {
                     Ok(Err(module_path))
@@ -524,8 +1455,17 @@ impl SearchIndex {
             }
         }
 
+        let mut native_vst3_bundles = self.native_vst3_bundles;
+        native_vst3_bundles.extend(native_vst3_files);
+        native_vst3_bundles.sort();
+        native_vst3_bundles.dedup();
         for candidate in is_vst3_module {
             match candidate {
+                // There's no need to bridge plugins that also come with a native Linux version
+                Ok(Vst3Module {
+                    module: Vst3ModuleType::Bundle(bundle),
+                    ..
+                }) if native_vst3_bundles.binary_search(&bundle).is_ok() => (),
                 Ok(module) => plugins.push(Plugin::Vst3(module)),
                 Err(path) => skipped_files.push(path),
             }
@@ -534,7 +1474,12 @@ impl SearchIndex {
         Ok(SearchResults {
             plugins,
             skipped_files,
+            skipped_managed_files,
+            parse_errors: parse_errors.into_inner().unwrap(),
             so_files: self.so_files,
+            unreadable_paths: self.unreadable_paths,
+            plugin_symlinks: self.plugin_symlinks,
+            native_vst3_bundles,
         })
     }
 }
@@ -14,13 +14,15 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use anyhow::Result;
-use clap::{app_from_crate, App, AppSettings, Arg};
+use anyhow::{anyhow, Context, Result};
+use clap::{app_from_crate, App, AppSettings, Arg, ArgMatches};
 use colored::Colorize;
 use std::env;
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 
 use crate::config::Config;
+use crate::files::PluginFormat;
 
 mod actions;
 mod config;
@@ -28,6 +30,18 @@ mod files;
 mod utils;
 
 fn main() -> Result<()> {
+    // Some actions need yabridgectl to exit with a specific exit code. Those are returned as errors
+    // so everything has been cleaned up by the time we exit.
+    match run() {
+        Err(err) => match err.downcast_ref::<actions::ExitCodeError>() {
+            Some(exit_code_error) => std::process::exit(exit_code_error.exit_code()),
+            None => Err(err),
+        },
+        Ok(()) => Ok(()),
+    }
+}
+
+fn run() -> Result<()> {
     // We'll modify our `PATH` environment variable so it matches up with
     // `get_augmented_search_path()` from `src/plugin/utils.h` for easier setup
     let yabridge_home = config::yabridge_directories()?.get_data_home();
@@ -41,28 +55,127 @@ fn main() -> Result<()> {
 
     let mut config = Config::read()?;
 
-    // Used for validation in `yabridgectl rm <path>`
-    let plugin_directories: Vec<&str> = config
-        .plugin_dirs
-        .iter()
-        .map(|path| path.to_str().expect("Path contains invalid unicode"))
-        .collect();
     // Used for validation in `yabridgectl blacklist rm <path>`
     let blacklist_entries: Vec<&str> = config
         .blacklist
         .iter()
         .map(|path| path.to_str().expect("Path contains invalid unicode"))
+        .chain(
+            config
+                .blacklist_patterns
+                .iter()
+                .map(|pattern| pattern.as_str()),
+        )
+        .collect();
+    // Used for validation in `yabridgectl keep rm <path>`
+    let keep_entries: Vec<&str> = config
+        .keep
+        .iter()
+        .map(|path| path.to_str().expect("Path contains invalid unicode"))
         .collect();
 
     let matches = app_from_crate!()
         .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .about("Only print warnings and errors")
+                .long_about(
+                    "Only print warnings and errors. This affects 'yabridgectl sync', \
+                     'yabridgectl status', and 'yabridgectl rm'. The summary at the end of \
+                     'yabridgectl sync' is only printed if something went wrong.",
+                )
+                .global(true),
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .about("The number of threads to use when searching for plugins")
+                .long_about(
+                    "The number of threads to use when searching for plugins. By default \
+                     yabridgectl uses one thread per CPU core. Lowering this can speed up \
+                     indexing plugin locations on network mounts, and '--jobs 1' indexes \
+                     everything serially.",
+                )
+                .validator(|jobs| match jobs.parse::<usize>() {
+                    Ok(jobs) if jobs > 0 => Ok(()),
+                    _ => Err(String::from("The number of jobs must be a positive integer")),
+                })
+                .takes_value(true)
+                .value_name("N")
+                .global(true),
+        )
+        .arg(
+            Arg::new("no-color")
+                .long("no-color")
+                .about("Don't use colors in the output")
+                .long_about(
+                    "Don't use colors in the output. Colors are also disabled when STDOUT is not \
+                     a terminal, or when the 'NO_COLOR' environment variable is set.",
+                )
+                .global(true),
+        )
         .subcommand(
             App::new("add")
-                .about("Add a plugin install location")
+                .about("Add one or more plugin install locations")
                 .display_order(1)
                 .arg(
                     Arg::new("path")
-                        .about("Path to a directory containing Windows VST plugins")
+                        .about("Paths to directories containing Windows VST plugins")
+                        .long_about(
+                            "Paths to directories containing Windows VST plugins, or to \
+                             individual Windows VST2 plugin '.dll' files or VST3 '.vst3' modules. \
+                             If one of the paths can't be added, then the other paths will still \
+                             be added.",
+                        )
+                        .takes_value(true)
+                        .multiple(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("prefix")
+                        .long("prefix")
+                        .about("The Wine prefix the plugins in this location should be run in")
+                        .long_about(
+                            "The Wine prefix the plugins in this location should be run in. By \
+                             default yabridge uses the Wine prefix the plugin is installed in, \
+                             which is not possible to detect for plugins installed outside of a \
+                             Wine prefix. Rerun 'yabridgectl sync' after adding the location.",
+                        )
+                        .validator(validate_path)
+                        .takes_value(true)
+                        .value_name("WINEPREFIX"),
+                )
+                .arg(
+                    Arg::new("interactive")
+                        .short('i')
+                        .long("interactive")
+                        .about("Choose which subdirectories to add after scanning")
+                        .long_about(
+                            "Search the directories for plugins first, and then choose which of \
+                             their immediate subdirectories should be added. Every selected \
+                             subdirectory is added as its own plugin location, and plugins \
+                             stored directly in the directory are added as individual files. \
+                             This is useful for large directories that also contain things you \
+                             don't want yabridgectl to index. Individual plugin files are added \
+                             as usual.",
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("add-prefix")
+                .about("Add the common plugin install locations in a Wine prefix")
+                .long_about(
+                    "Add the common plugin install locations in a Wine prefix. This adds the \
+                     VST3 directories and the most common VST2 directories in the prefix's \
+                     'Program Files' directories, if they exist.",
+                )
+                .display_order(2)
+                .arg(
+                    Arg::new("prefix")
+                        .about("Path to the Wine prefix")
                         .validator(validate_path)
                         .takes_value(true)
                         .required(true),
@@ -70,25 +183,247 @@ fn main() -> Result<()> {
         )
         .subcommand(
             App::new("rm")
-                .about("Remove a plugin install location")
-                .display_order(2)
+                .about("Remove one or more plugin install locations")
+                .display_order(3)
                 .arg(
                     Arg::new("path")
-                        .about("Path to a previously added directory")
-                        .possible_values(&plugin_directories)
+                        .about("Paths to previously added directories or files")
+                        .long_about(
+                            "Paths to previously added directories or files. The numbers shown by \
+                             'yabridgectl list --index' can be used in place of the paths. When \
+                             removing multiple locations, you will only be asked once whether you \
+                             want to remove the leftover files from all of those locations.",
+                        )
                         .takes_value(true)
-                        .required(true),
+                        .multiple(true)
+                        .required_unless_present("glob"),
+                )
+                .arg(
+                    Arg::new("glob")
+                        .long("glob")
+                        .about("Remove all plugin locations matching a glob pattern")
+                        .long_about(
+                            "Remove all plugin locations matching a glob pattern, like \
+                             '/mnt/old-drive/*'. You will only be asked once whether you want to \
+                             remove the leftover files from all of those locations.",
+                        )
+                        .validator(|pattern| {
+                            glob::Pattern::new(pattern)
+                                .map(|_| ())
+                                .map_err(|err| format!("Invalid glob pattern: {}", err))
+                        })
+                        .takes_value(true)
+                        .value_name("PATTERN")
+                        .conflicts_with("path"),
                 ),
         )
         .subcommand(
             App::new("list")
                 .about("List the plugin install locations")
-                .display_order(3),
+                .display_order(4)
+                .arg(
+                    Arg::new("null")
+                        .short('0')
+                        .long("null")
+                        .about("Separate paths with null bytes instead of newlines"),
+                )
+                .arg(
+                    Arg::new("index")
+                        .short('n')
+                        .long("index")
+                        .about("Print a number before every location for use with 'yabridgectl rm'")
+                        .conflicts_with("null"),
+                ),
         )
         .subcommand(
             App::new("status")
                 .about("Show the installation status for all plugins")
-                .display_order(4),
+                .display_order(5)
+                .arg(
+                    Arg::new("watch")
+                        .short('w')
+                        .long("watch")
+                        .about("Keep refreshing the status until interrupted with Ctrl+C"),
+                )
+                .arg(
+                    Arg::new("fail_if_no_yabridge")
+                        .long("fail-if-no-yabridge")
+                        .about("Exit with status code 2 if yabridge's files could not be found")
+                        .long_about(
+                            "Exit with status code 2 if yabridge's files could not be found. This \
+                             can be used in scripts to verify that yabridge has been installed \
+                             before running 'yabridgectl sync'.",
+                        )
+                        .conflicts_with("watch"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .about("Only show plugins using this format")
+                        .long_about(
+                            "Only show plugins using this format. This option can be used \
+                             multiple times to select multiple formats. All plugin formats are \
+                             included by default.",
+                        )
+                        .possible_values(&["vst2", "vst3"])
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("no-cache")
+                        .long("no-cache")
+                        .about("Parse all plugin files again instead of using the cache")
+                        .long_about(
+                            "Parse all plugin files again instead of using the information cached \
+                             during previous runs for files that have not changed since.",
+                        ),
+                )
+                .arg(
+                    Arg::new("show-conflicts")
+                        .long("show-conflicts")
+                        .about("Also list plugins that are provided by multiple plugin locations")
+                        .long_about(
+                            "Also list plugins that are provided by multiple plugin locations. \
+                             This shows the same information as 'yabridgectl clashes' below the \
+                             installation status.",
+                        )
+                        .conflicts_with("json"),
+                )
+                .arg(
+                    Arg::new("outdated")
+                        .long("outdated")
+                        .about("Only list plugins that need to be synced")
+                        .long_about(
+                            "Only list plugins that need to be synced. These are plugins that \
+                             have not been set up yet, plugins with invalid files, and plugins \
+                             using copies of an older version of yabridge. Plugin locations \
+                             where everything is up to date are left out. This is useful after \
+                             updating yabridge.",
+                        )
+                        .conflicts_with("json"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .about("Print the status as a JSON document")
+                        .long_about(
+                            "Print the status as a JSON document. The document contains a \
+                             'schema_version' field that will be incremented whenever existing \
+                             fields are changed or removed.",
+                        )
+                        .conflicts_with("watch"),
+                ),
+        )
+        .subcommand(
+            App::new("clashes")
+                .about("Show plugins that are provided by multiple plugin locations")
+                .long_about(
+                    "Show plugins that are provided by multiple plugin locations. If the same \
+                     VST3 plugin is installed in multiple Wine prefixes, then only one of those \
+                     versions can be set up in '~/.vst3/yabridge'. This lists every source for \
+                     those plugins so you can decide which ones to remove or blacklist.",
+                )
+                .display_order(6),
+        )
+        .subcommand(
+            App::new("info")
+                .about("Show everything yabridgectl knows about a single plugin")
+                .long_about(
+                    "Show everything yabridgectl knows about a single plugin. This includes the \
+                     plugin's format, architecture, exported entry points, the files yabridgectl \
+                     would set up for it, and its current installation status. The plugin does \
+                     not have to be inside of one of the plugin locations.",
+                )
+                .display_order(7)
+                .arg(
+                    Arg::new("path")
+                        .about("Path to a .dll or .vst3 file, or a VST3 bundle")
+                        .validator(validate_path)
+                        .takes_value(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            App::new("list-formats")
+                .about("Show which plugin formats your version of yabridge supports")
+                .display_order(8),
+        )
+        .subcommand(
+            App::new("self-test")
+                .about("Verify that yabridge has been set up correctly")
+                .long_about(
+                    "Verify that yabridge has been set up correctly. This sets up \
+                     'libyabridge-vst2.so' for a test plugin in a temporary directory using your \
+                     installation method, and it checks whether the Wine plugin host can run in \
+                     the default Wine prefix. This does not require any plugins to be installed, \
+                     but it also doesn't load a plugin since yabridge doesn't come with a Windows \
+                     plugin that could be loaded. Use 'yabridgectl sync --check-wine-prefix' to \
+                     verify that your plugins can be loaded.",
+                )
+                .display_order(9),
+        )
+        .subcommand(
+            App::new("doctor")
+                .about("Check your yabridge setup and show how to fix any problems")
+                .long_about(
+                    "Check your yabridge setup and show how to fix any problems. This runs the \
+                     same checks 'yabridgectl sync' performs after setting up your plugins, \
+                     without changing anything. Exits with a non-zero status code if any of the \
+                     critical checks failed.",
+                )
+                .display_order(10)
+                .arg(
+                    Arg::new("wine")
+                        .long("wine")
+                        .about("The Wine binary to check yabridge against")
+                        .long_about(
+                            "The Wine binary to check yabridge against, instead of '$WINELOADER' \
+                             or the 'wine' binary in your search path. This is useful when you \
+                             have multiple versions of Wine installed.",
+                        )
+                        .value_name("PATH")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            App::new("repair")
+                .about("Fix merged VST3 bundles with broken symlinks")
+                .long_about(
+                    "Fix merged VST3 bundles with broken symlinks. This happens when a VST3 \
+                     plugin has been moved or uninstalled since the last sync. Broken symlinks \
+                     are pointed to the plugin's new location if it can still be found in one of \
+                     the plugin locations, and they are removed otherwise. Bundles that no longer \
+                     contain any plugins are removed entirely. Bundles without broken symlinks \
+                     are left alone.",
+                )
+                .display_order(11)
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .about("Only show what would be fixed, without changing anything"),
+                ),
+        )
+        .subcommand(
+            App::new("du")
+                .about("Show how much disk space yabridge's files take up")
+                .long_about(
+                    "Show how much disk space yabridge's files take up. This counts the copies, \
+                     hard links, and symlinks set up for VST2 plugins and in '~/.vst3/yabridge', \
+                     and it shows the combined size of the copies. Hard links and symlinks don't \
+                     take up any additional space.",
+                )
+                .display_order(12),
+        )
+        .subcommand(
+            App::new("prefixes")
+                .about("Show which Wine prefixes your plugins will run in")
+                .long_about(
+                    "Show which Wine prefixes your plugins will run in. This uses the same rules \
+                     as yabridge itself: '$WINEPREFIX' if it is set, the Wine prefix passed to \
+                     'yabridgectl add --prefix' for the plugin's location, the Wine prefix the \
+                     plugin is installed in, or the default Wine prefix in '~/.wine' otherwise.",
+                )
+                .display_order(13),
         )
         .subcommand(
             App::new("sync")
@@ -98,7 +433,28 @@ fn main() -> Result<()> {
                     Arg::new("force")
                         .short('f')
                         .long("force")
-                        .about("Always update files, even not necessary"),
+                        .about("Always update files, even not necessary")
+                        .long_about(
+                            "Always update files, even not necessary. A regular sync already \
+                             compares the contents of every copy to yabridge's current files and \
+                             checks where every symlink and hard link points to, so damaged or \
+                             partially written files are repaired without this option. Recreating \
+                             every file may cause your DAW to rescan all of your plugins.",
+                        ),
+                )
+                .arg(
+                    Arg::new("wine")
+                        .long("wine")
+                        .about("The Wine binary to use for the post-installation setup checks")
+                        .long_about(
+                            "The Wine binary to use for the post-installation setup checks, \
+                             instead of '$WINELOADER' or the 'wine' binary in your search path. \
+                             Switching to a different Wine version will cause the checks to be \
+                             run again.",
+                        )
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .conflicts_with("no-verify"),
                 )
                 .arg(
                     Arg::new("no-verify")
@@ -106,17 +462,239 @@ fn main() -> Result<()> {
                         .long("no-verify")
                         .about("Skip post-installation setup checks"),
                 )
+                .arg(
+                    Arg::new("check-wine-prefix")
+                        .long("check-wine-prefix")
+                        .about("Verify that plugins can be loaded in their Wine prefixes")
+                        .long_about(
+                            "Verify that plugins can be loaded in their Wine prefixes. This will \
+                             try to load one plugin from every plugin location using the Wine \
+                             plugin host and it will show you Wine's error output when that fails. \
+                             This catches problems such as missing runtime libraries in a prefix \
+                             that the regular post-installation setup checks can't detect.",
+                        )
+                        .conflicts_with("no-verify"),
+                )
+                .arg(
+                    Arg::new("strict")
+                        .long("strict")
+                        .about("Exit with a non-zero exit code when a setup check fails")
+                        .long_about(
+                            "Exit with exit code 3 when one of the post-installation setup checks \
+                             prints a warning, for instance because Wine could not run \
+                             'yabridge-host.exe' or because it cannot be found in your search \
+                             path. The warnings are printed either way, but by default they don't \
+                             affect yabridgectl's exit code. This is useful when running \
+                             'yabridgectl sync' from scripts.",
+                        )
+                        .conflicts_with_all(&["no-verify", "watch"]),
+                )
+                .arg(
+                    Arg::new("no-centralized-warning")
+                        .long("no-centralized-warning")
+                        .about("Only warn about duplicate VST3 plugins in verbose mode")
+                        .long_about(
+                            "Only warn about VST3 plugins provided by multiple Wine prefixes when \
+                             the '--verbose' option is set. Only one of those versions can be set \
+                             up in '~/.vst3/yabridge', so this should only be used if you're aware \
+                             of the other versions being skipped.",
+                        ),
+                )
+                .arg(
+                    Arg::new("quiet-skipped")
+                        .long("quiet-skipped")
+                        .about("Don't report skipped non-plugin .dll files")
+                        .long_about(
+                            "Don't report skipped non-plugin .dll files. This hides the number of \
+                             skipped files in the summary. The skipped files are still listed \
+                             when the '--verbose' option is used.",
+                        ),
+                )
+                .arg(
+                    Arg::new("print-plan")
+                        .long("print-plan")
+                        .about("Print the actions that will be taken for every plugin first"),
+                )
+                .arg(
+                    Arg::new("only")
+                        .long("only")
+                        .about("Only sync this plugin location")
+                        .long_about(
+                            "Only sync this plugin location. This option can be used multiple \
+                             times to sync multiple plugin locations, and the paths must already \
+                             have been added using 'yabridgectl add'. Leftover files in \
+                             '~/.vst3/yabridge' are not detected or pruned when this option is \
+                             used since they may belong to one of the other plugin locations.",
+                        )
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .validator(validate_path),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .about("Only set up plugins using this format")
+                        .long_about(
+                            "Only set up plugins using this format. This option can be used \
+                             multiple times to select multiple formats. All plugin formats are \
+                             included by default.",
+                        )
+                        .possible_values(&["vst2", "vst3"])
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("no-cache")
+                        .long("no-cache")
+                        .about("Parse all plugin files again instead of using the cache")
+                        .long_about(
+                            "Parse all plugin files again instead of using the information cached \
+                             during previous runs for files that have not changed since.",
+                        ),
+                )
+                .arg(
+                    Arg::new("preserve-timestamps")
+                        .long("preserve-timestamps")
+                        .about("Keep the modification times of updated copies")
+                        .long_about(
+                            "Keep the modification times of updated copies. Some DAWs rescan \
+                             every plugin whose file has changed, so without this option updating \
+                             yabridge may cause all of your plugins to be rescanned.",
+                        ),
+                )
+                .arg(
+                    Arg::new("watch")
+                        .long("watch")
+                        .about("Keep syncing whenever plugins are added, updated, or removed")
+                        .long_about(
+                            "Keep syncing whenever plugins are added, updated, or removed. After \
+                             the initial sync, the plugin locations are watched for new, changed, \
+                             and removed '.dll' and '.vst3' files until you press Ctrl+C. \
+                             Pressing Ctrl+C during a sync lets that sync finish first, and \
+                             pressing it again exits immediately.",
+                        )
+                        .conflicts_with_all(&["dry-run", "print-plan"]),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .about("Show what would be set up or removed without changing anything")
+                        .long_about(
+                            "Show what would be set up or removed without changing anything. \
+                             Every file that would be created or updated is printed, and when \
+                             combined with '--prune' the leftover files that would be removed \
+                             are listed as well.",
+                        ),
+                )
+                .arg(
+                    Arg::new("no-summary")
+                        .long("no-summary")
+                        .about("Only print warnings and errors")
+                        .long_about(
+                            "Only print warnings and errors. Unlike the global '--quiet' option, \
+                             this also suppresses the summary when the checks after syncing fail.",
+                        )
+                        .conflicts_with_all(&["dry-run", "print-plan", "verbose"]),
+                )
+                .arg(
+                    Arg::new("relative-symlinks")
+                        .long("relative-symlinks")
+                        .about("Use relative paths when symlinking Windows VST3 modules")
+                        .long_about(
+                            "Use relative paths when symlinking Windows VST3 modules into \
+                             '~/.vst3/yabridge'. This keeps the merged VST3 bundles working when \
+                             both '~/.vst3/yabridge' and your plugin directories are moved or \
+                             backed up together, as long as their relative layout stays the same.",
+                        ),
+                )
                 .arg(
                     Arg::new("prune")
                         .short('p')
                         .long("prune")
                         .about("Remove unrelated or leftover .so files"),
                 )
+                .arg(
+                    Arg::new("prune-older-than")
+                        .long("prune-older-than")
+                        .about("Only prune leftover files that are older than this")
+                        .long_about(
+                            "Only prune leftover files that haven't been modified for at least \
+                             this long, like '30m', '12h', or '7d'. VST3 bundles count as modified \
+                             when any of the files inside of them has been modified. Newer \
+                             leftover files are listed but kept until a later sync, which gives \
+                             you some time to undo an accidental reorganization.",
+                        )
+                        .value_name("DURATION")
+                        .validator(|duration| utils::parse_duration(duration).map(|_| ()))
+                        .takes_value(true)
+                        .requires("prune"),
+                )
+                .arg(
+                    Arg::new("link-presets")
+                        .long("link-presets")
+                        .about("Also link the user presets for VST3 plugins from their Wine prefixes")
+                        .long_about(
+                            "Also link the user presets for VST3 plugins from their Wine prefixes. \
+                             Windows VST3 hosts store these presets in 'Documents/VST3 \
+                             Presets/<vendor>/<plugin>'. These preset directories will be \
+                             symlinked to '~/.vst3/presets/<vendor>/<plugin>' for all bridged VST3 \
+                             plugins so Linux VST3 hosts can also find them. Existing preset \
+                             directories in '~/.vst3/presets' are never touched.",
+                        ),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .about("Don't ask for confirmation before pruning")
+                        .long_about(
+                            "Don't ask for confirmation before removing leftover files with \
+                             '--prune'. Leftover files are never removed without confirmation \
+                             when yabridgectl is not run from a terminal, so this is needed when \
+                             pruning from a script.",
+                        )
+                        .requires("prune"),
+                )
+                .arg(
+                    Arg::new("report")
+                        .long("report")
+                        .about("Write a JSON report of everything the sync did to a file")
+                        .long_about(
+                            "Write a JSON report of everything the sync did to a file. This lists \
+                             the plugins that were newly set up, the plugins that were already up \
+                             to date, the plugins that were skipped and why, and the leftover \
+                             files that were found or pruned. Unlike 'yabridgectl status --json', \
+                             this describes the changes made by the sync and not the resulting \
+                             state.",
+                        )
+                        .value_name("FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("output-dir")
+                        .long("output-dir")
+                        .about("Set up the VST3 plugins in another directory for a one-off sync")
+                        .long_about(
+                            "Set up the merged VST3 bundles in this directory instead of in \
+                             '~/.vst3/yabridge', for instance to create a portable collection of \
+                             bridged plugins. Your regular installation is not touched. VST2 \
+                             plugins are skipped since their '.so' files need to be placed next \
+                             to the plugin's '.dll' file, and the directory is assumed to be \
+                             empty so leftover files are not searched for. Combine this with \
+                             '--relative-symlinks' if the plugin directories are moved along with \
+                             the output directory.",
+                        )
+                        .value_name("DIR")
+                        .takes_value(true)
+                        .conflicts_with_all(&["prune", "watch", "link-presets"]),
+                )
                 .arg(
                     Arg::new("verbose")
                         .short('v')
                         .long("verbose")
-                        .about("Print information about plugins being set up or skipped"),
+                        .about("Print information about plugins being set up or skipped")
+                        .conflicts_with("quiet"),
                 ),
         )
         .subcommand(
@@ -136,12 +714,17 @@ fn main() -> Result<()> {
                              rerun 'yabridgectl sync' whenever you update yabridge. \
                              '{}' only works for hosts that support individually sandboxed plugins \
                              such as Bitwig Studio, but it does not require setting environment \
-                             variables or to manual updates.",
+                             variables or to manual updates. \
+                             '{}' works like '{}', but it creates hard links instead of copies to \
+                             save disk space. This only works when your plugins are on the same \
+                             file system as yabridge's files.",
                             "copy".bright_white(),
-                            "symlink".bright_white()
+                            "symlink".bright_white(),
+                            "hardlink".bright_white(),
+                            "copy".bright_white()
                         ))
                         .setting(clap::ArgSettings::NextLineHelp)
-                        .possible_values(&["copy", "symlink"])
+                        .possible_values(&["copy", "symlink", "hardlink"])
                         .takes_value(true),
                 )
                 .arg(
@@ -151,10 +734,17 @@ fn main() -> Result<()> {
                         .long_about(
                             "Path to the directory containing 'libyabridge-{vst2,vst3}.so'. If this \
                              is not set, then yabridgectl will look in both '/usr/lib' and \
-                             '~/.local/share/yabridge' by default.",
+                             '~/.local/share/yabridge' by default, followed by the 'yabridge' \
+                             directories in '$XDG_DATA_DIRS'. This option can be used multiple \
+                             times to search multiple directories in order, and it also accepts \
+                             absolute glob patterns like '/opt/yabridge-*' for installations in \
+                             versioned directories. When a pattern matches multiple directories, \
+                             the most recently updated version of yabridge is used.",
                         )
-                        .validator(validate_path)
-                        .takes_value(true).conflicts_with("path_auto"),
+                        .validator(validate_path_or_pattern)
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .conflicts_with("path_auto"),
                 )
                 .arg(
                     Arg::new("path_auto")
@@ -165,7 +755,50 @@ fn main() -> Result<()> {
                              setting a path with the '--path' option to revert back to the default \
                              auto detection behaviour.",
                         ),
-                ).arg(
+                )
+                .arg(
+                    Arg::new("host_exe")
+                        .long("host-exe")
+                        .about("Path to 'yabridge-host.exe' (advanced)")
+                        .long_about(
+                            "Path to 'yabridge-host.exe'. By default yabridgectl searches for the \
+                             plugin host in the search path and in '~/.local/share/yabridge'. This \
+                             path is only used for yabridgectl's setup checks. yabridge itself \
+                             still looks for the plugin host next to 'libyabridge-{vst2,vst3}.so' \
+                             and in the search path, so make sure it can find this file there \
+                             as well. 'yabridgectl status' and 'yabridgectl sync' will warn you \
+                             when it can't.",
+                        )
+                        .validator(validate_path)
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .conflicts_with("host_auto"),
+                )
+                .arg(
+                    Arg::new("host_32_exe")
+                        .long("host-32-exe")
+                        .about("Path to 'yabridge-host-32.exe' (advanced)")
+                        .long_about(
+                            "Path to 'yabridge-host-32.exe'. This works the same way as the \
+                             '--host-exe' option, and it's useful when the 32-bit plugin host is \
+                             installed to a different directory than the 64-bit one.",
+                        )
+                        .validator(validate_path)
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .conflicts_with("host_auto"),
+                )
+                .arg(
+                    Arg::new("host_auto")
+                        .long("host-auto")
+                        .about("Search for the plugin hosts in the search path again")
+                        .long_about(
+                            "Search for 'yabridge-host.exe' and 'yabridge-host-32.exe' in the \
+                             search path again after setting their paths with the '--host-exe' \
+                             and '--host-32-exe' options. This is the default behaviour.",
+                        ),
+                )
+                .arg(
                     Arg::new("no_verify")
                         .long("no-verify")
                         .about("Always skip post-installation setup checks")
@@ -175,6 +808,179 @@ fn main() -> Result<()> {
                         )
                         .possible_values(&["true", "false"])
                         .takes_value(true),
+                )
+                .arg(
+                    Arg::new("max_parse_errors")
+                        .long("max-parse-errors")
+                        .about("Stop searching a location after this many unparsable files")
+                        .long_about(
+                            "Stop searching a plugin location after this many files in that \
+                             location could not be parsed. This prevents a directory that \
+                             was added by accident from causing a very slow scan. Set this to 0 \
+                             to remove the limit, which is the default.",
+                        )
+                        .validator(|value| {
+                            value
+                                .parse::<usize>()
+                                .map(|_| ())
+                                .map_err(|_| String::from("Expected a number"))
+                        })
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("parser")
+                        .long("parser")
+                        .about("How to parse plugin files (advanced)")
+                        .long_about(
+                            "How to parse '.dll' and '.vst3' files to determine whether they're \
+                             plugins. With 'auto' yabridgectl uses Wine's 'winedump' tool. With \
+                             'builtin' yabridgectl parses these files itself instead, so Wine's \
+                             tools don't need to be installed. This parser is still experimental. \
+                             Files it can't parse are skipped, and they're listed along with the \
+                             reason when running 'yabridgectl sync --verbose'. The default is \
+                             'auto'.",
+                        )
+                        .possible_values(&["auto", "builtin"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("flatpak")
+                        .long("flatpak")
+                        .about("Set up VST3 plugins for a Flatpak application")
+                        .long_about(
+                            "Set up VST3 plugins for the Flatpak application with this ID, like \
+                             'com.bitwig.BitwigStudio'. Sandboxed applications that keep their \
+                             own '~/.vst3' directory can't see the plugins in '~/.vst3/yabridge', \
+                             so yabridgectl will set up VST3 plugins in \
+                             '~/.var/app/<app-id>/.vst3/yabridge' instead. The copy-based \
+                             installation method should be used together with this option, and \
+                             your plugin directories need to be accessible from within the \
+                             sandbox. VST2 plugins are not affected by this option.",
+                        )
+                        .takes_value(true)
+                        .value_name("APP_ID")
+                        .conflicts_with("no_flatpak"),
+                )
+                .arg(
+                    Arg::new("no_flatpak")
+                        .long("no-flatpak")
+                        .about("Set up VST3 plugins in '~/.vst3/yabridge' again")
+                        .long_about(
+                            "Set up VST3 plugins in '~/.vst3/yabridge' again after setting a \
+                             Flatpak application with the '--flatpak' option. This is the \
+                             default behaviour.",
+                        ),
+                )
+                .arg(
+                    Arg::new("vst3_home")
+                        .long("vst3-home")
+                        .about("Set up VST3 plugins in a subdirectory of this directory instead")
+                        .long_about(
+                            "Set up merged VST3 bundles in a 'yabridge' subdirectory of this \
+                             directory instead of in '~/.vst3/yabridge'. This way other VST3 \
+                             plugins in this directory are never touched by yabridgectl. This \
+                             takes precedence over the '--flatpak' option. Your VST3 hosts need \
+                             to be configured to search this directory for plugins. Bundles \
+                             that were set up in the old directory are not removed \
+                             automatically, and 'yabridgectl status' will warn about plugins \
+                             that are set up in both directories.",
+                        )
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .conflicts_with("vst3_home_auto"),
+                )
+                .arg(
+                    Arg::new("vst3_home_auto")
+                        .long("vst3-home-auto")
+                        .about("Set up VST3 plugins in the default directory again")
+                        .long_about(
+                            "Set up VST3 plugins in '~/.vst3/yabridge' again, or in the Flatpak \
+                             application's VST3 directory if the '--flatpak' option was used, \
+                             after setting a directory with the '--vst3-home' option. This is the \
+                             default behaviour.",
+                        ),
+                )
+                .arg(
+                    Arg::new("lib_arch")
+                        .long("lib-arch")
+                        .about("Set the architecture of yabridge's libraries (advanced)")
+                        .long_about(
+                            "Set the architecture of yabridge's libraries instead of detecting it \
+                             from their ELF headers. This detection only works for x86 and x86_64 \
+                             builds of yabridge, so this can be used on other platforms, for \
+                             instance when running x86 plugin hosts using box64 or FEX on ARM. \
+                             This determines where 'libyabridge-vst3.so' is placed within the \
+                             merged VST3 bundles.",
+                        )
+                        .possible_values(&["x86", "x86_64"])
+                        .takes_value(true)
+                        .conflicts_with("lib_arch_auto"),
+                )
+                .arg(
+                    Arg::new("lib_arch_auto")
+                        .long("lib-arch-auto")
+                        .about("Detect the architecture of yabridge's libraries again")
+                        .long_about(
+                            "Detect the architecture of yabridge's libraries from their ELF \
+                             headers again after setting it with the '--lib-arch' option. This \
+                             is the default behaviour.",
+                        ),
+                )
+                .arg(
+                    Arg::new("no_centralized_warning")
+                        .long("no-centralized-warning")
+                        .about("Always hide warnings about duplicate VST3 plugins")
+                        .long_about(
+                            "Always hide warnings about VST3 plugins provided by multiple Wine \
+                             prefixes unless the '--verbose' option is passed to 'yabridgectl \
+                             sync'. This can be set temporarily by passing the \
+                             '--no-centralized-warning' option to 'yabridgectl sync'.",
+                        )
+                        .possible_values(&["true", "false"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("quiet_skipped")
+                        .long("quiet-skipped")
+                        .about("Always hide the number of skipped non-plugin .dll files")
+                        .long_about(
+                            "Always hide the number of skipped non-plugin .dll files during \
+                             'yabridgectl sync'. This can be set temporarily by passing the \
+                             '--quiet-skipped' option to 'yabridgectl sync'.",
+                        )
+                        .possible_values(&["true", "false"])
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("show")
+                        .long("show")
+                        .about("Print the current settings without changing anything")
+                        .long_about(
+                            "Print the current settings without changing anything. Every setting \
+                             is printed on its own line as 'key=value' using the same names as in \
+                             the config file, so the output can easily be used in shell scripts. \
+                             Settings that have not been set have an empty value, and every \
+                             yabridge path candidate is printed on a separate line.",
+                        )
+                        .conflicts_with_all(&[
+                            "method",
+                            "path",
+                            "path_auto",
+                            "host_exe",
+                            "host_32_exe",
+                            "host_auto",
+                            "no_verify",
+                            "max_parse_errors",
+                            "parser",
+                            "flatpak",
+                            "no_flatpak",
+                            "vst3_home",
+                            "vst3_home_auto",
+                            "lib_arch",
+                            "lib_arch_auto",
+                            "no_centralized_warning",
+                            "quiet_skipped",
+                        ]),
                 ),
         )
         .subcommand(
@@ -186,11 +992,98 @@ fn main() -> Result<()> {
                     "Manage the indexing blacklist (advanced)\n\
                      \n\
                      This lets you skip over individual files and entire directories in the \
-                     indexing process. You most likely won't have to use this feature.",
+                     indexing process. Entries can either be literal paths or glob patterns. You \
+                     most likely won't have to use this feature.",
+                )
+                .subcommand(
+                    App::new("add")
+                        .about("Add paths or a glob pattern to the blacklist")
+                        .display_order(1)
+                        .arg(
+                            Arg::new("path")
+                                .about("Paths to files or directories")
+                                .takes_value(true)
+                                .multiple(true)
+                                .required_unless_present("glob"),
+                        )
+                        .arg(
+                            Arg::new("glob")
+                                .long("glob")
+                                .about("Skip all files and directories matching a glob pattern")
+                                .long_about(
+                                    "Skip all files and directories matching a glob pattern, like \
+                                     '/home/user/.wine/**/*_demo.dll'. Patterns are matched \
+                                     against absolute paths, and '*' also matches path \
+                                     separators.",
+                                )
+                                .validator(|pattern| {
+                                    glob::Pattern::new(pattern)
+                                        .map(|_| ())
+                                        .map_err(|err| format!("Invalid glob pattern: {}", err))
+                                })
+                                .takes_value(true)
+                                .value_name("PATTERN")
+                                .conflicts_with("path"),
+                        )
+                        .arg(
+                            Arg::new("recursive-clean")
+                                .long("recursive-clean")
+                                .about("Also remove the files already set up for these paths")
+                                .long_about(
+                                    "Also remove the files yabridgectl has already set up for the \
+                                     plugins in these paths. This includes the '.so' files next \
+                                     to the plugins and the merged VST3 bundles in \
+                                     '~/.vst3/yabridge'. You will be asked for confirmation \
+                                     before anything is removed. Without this option these files \
+                                     are only removed during the next 'yabridgectl sync --prune'.",
+                                )
+                                .conflicts_with("glob"),
+                        ),
+                )
+                .subcommand(
+                    App::new("rm")
+                        .about("Remove a path or a glob pattern from the blacklist")
+                        .display_order(2)
+                        .arg(
+                            Arg::new("path")
+                                .about("A previously added path or glob pattern")
+                                .possible_values(&blacklist_entries)
+                                .takes_value(true)
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    App::new("list")
+                        .about("List the blacklisted paths and glob patterns")
+                        .display_order(3)
+                        .arg(
+                            Arg::new("null")
+                                .short('0')
+                                .long("null")
+                                .about("Separate paths with null bytes instead of newlines"),
+                        ),
+                )
+                .subcommand(
+                    App::new("clear")
+                        .about("Clear the entire blacklist")
+                        .display_order(4),
+                ),
+        )
+        .subcommand(
+            App::new("keep")
+                .about("Manage the files that should never be pruned (advanced)")
+                .display_order(202)
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .long_about(
+                    "Manage the files that should never be pruned (advanced)\n\
+                     \n\
+                     Files and directories in this list are never removed as leftover files by \
+                     'yabridgectl sync --prune' or when removing a plugin location. Unlike with \
+                     the blacklist, these paths are still searched for plugins.",
                 )
                 .subcommand(
                     App::new("add")
-                        .about("Add a path to the blacklist")
+                        .about("Add a path to the keep list")
                         .display_order(1)
                         .arg(
                             Arg::new("path")
@@ -202,88 +1095,481 @@ fn main() -> Result<()> {
                 )
                 .subcommand(
                     App::new("rm")
-                        .about("Remove a path from the blacklist")
+                        .about("Remove a path from the keep list")
                         .display_order(2)
                         .arg(
                             Arg::new("path")
                                 .about("Path to a previously added file or directory")
-                                .possible_values(&blacklist_entries)
+                                .possible_values(&keep_entries)
                                 .takes_value(true)
                                 .required(true),
                         ),
                 )
                 .subcommand(
                     App::new("list")
-                        .about("List the blacklisted paths")
-                        .display_order(3),
+                        .about("List the kept paths")
+                        .display_order(3)
+                        .arg(
+                            Arg::new("null")
+                                .short('0')
+                                .long("null")
+                                .about("Separate paths with null bytes instead of newlines"),
+                        ),
                 )
                 .subcommand(
                     App::new("clear")
-                        .about("Clear the entire blacklist")
+                        .about("Clear the entire keep list")
                         .display_order(4),
                 ),
         )
+        .subcommand(
+            App::new("export-config")
+                .about("Print the configuration so it can be imported elsewhere")
+                .long_about(
+                    "Print the configuration as TOML so it can be imported on another machine \
+                     using 'yabridgectl import-config'. This includes the plugin locations, the \
+                     blacklist, the keep list, and all settings.",
+                )
+                .display_order(203),
+        )
+        .subcommand(
+            App::new("import-config")
+                .about("Import a configuration printed by 'yabridgectl export-config'")
+                .long_about(
+                    "Import a configuration printed by 'yabridgectl export-config'. The plugin \
+                     locations, blacklist, keep list, and Wine prefixes are added to the current \
+                     configuration, and the other settings are replaced. All paths must be \
+                     absolute, and you'll get a warning for paths that don't exist on this \
+                     machine.",
+                )
+                .display_order(204)
+                .arg(
+                    Arg::new("path")
+                        .about("The file to import, reads from STDIN if omitted")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("replace")
+                        .long("replace")
+                        .about("Replace the current configuration instead of merging"),
+                ),
+        )
         .get_matches();
 
+    // `colored` already disables colors when STDOUT is not a terminal and it mostly respects
+    // `NO_COLOR`, but `CLICOLOR_FORCE` would still take precedence over `NO_COLOR`
+    if matches.is_present("no-color") || env::var_os("NO_COLOR").is_some() {
+        colored::control::set_override(false);
+    }
+
+    // Both `wine --version` and the winelib scripts for yabridge's Wine plugin hosts respect
+    // `$WINELOADER`, so overriding it here makes every check use the selected Wine binary
+    if let Some(("sync", options)) | Some(("doctor", options)) = matches.subcommand() {
+        if let Some(wine_binary) = options.value_of_os("wine") {
+            env::set_var("WINELOADER", wine_binary);
+        }
+    }
+
+    // This has to be done before anything uses rayon's global thread pool
+    if let Ok(jobs) = matches.value_of_t::<usize>("jobs") {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .context("Could not configure the thread pool")?;
+    }
+
     // We're calling canonicalize when adding and setting paths since relative paths would cause
     // some weird behaviour. There's no built-in way to make relative paths absoltue without
     // resolving symlinks, but I don't think this will cause any issues.
     //
     // https://github.com/rust-lang/rust/issues/59117
     match matches.subcommand() {
-        Some(("add", options)) => actions::add_directory(
+        Some(("add", options)) => {
+            let wine_prefix = match options.value_of_t::<PathBuf>("prefix") {
+                Ok(prefix) => Some(prefix.canonicalize()?),
+                Err(_) => None,
+            };
+            for_each_path(options, "Added", |path| {
+                let path = canonicalize_path(&path)?;
+                if path.is_file() {
+                    actions::add_file(&mut config, path, wine_prefix.clone())
+                } else if options.is_present("interactive") {
+                    actions::add_directory_interactive(&mut config, &path, wine_prefix.clone())
+                } else {
+                    actions::add_directory(&mut config, path, wine_prefix.clone())
+                }
+            })
+        }
+        Some(("add-prefix", options)) => actions::add_prefix(
             &mut config,
-            options
-                .value_of_t_or_exit::<PathBuf>("path")
+            &options
+                .value_of_t_or_exit::<PathBuf>("prefix")
                 .canonicalize()?,
         ),
+        Some(("rm", options)) if options.is_present("glob") => {
+            // The pattern has already been validated
+            actions::remove_matching(
+                &mut config,
+                &glob::Pattern::new(options.value_of("glob").unwrap()).unwrap(),
+                options.is_present("quiet"),
+            )
+        }
         Some(("rm", options)) => {
-            actions::remove_directory(&mut config, &options.value_of_t_or_exit::<PathBuf>("path"))
+            // Location numbers refer to the list from before any of the locations were removed.
+            // The user is only asked once whether the leftover files from all removed locations
+            // should be removed, even if some of the paths could not be removed.
+            let original_config = config.clone();
+            let mut removed_locations: Vec<PathBuf> = Vec::new();
+            let result = for_each_path(options, "Removed", |path| {
+                let path = resolve_location_index(&original_config, path.as_os_str())?;
+                actions::remove_location(&mut config, &path)?;
+                removed_locations.push(path);
+
+                Ok(())
+            });
+
+            if !removed_locations.is_empty() {
+                actions::finish_removing_locations(
+                    &config,
+                    &removed_locations,
+                    options.is_present("quiet"),
+                )?;
+            }
+
+            result
         }
-        Some(("list", _)) => actions::list_directories(&config),
-        Some(("status", _)) => actions::show_status(&config),
-        Some(("sync", options)) => actions::do_sync(
+        Some(("list", options)) => actions::list_directories(
+            &config,
+            options.is_present("null"),
+            options.is_present("index"),
+        ),
+        Some(("status", options)) => {
+            let formats = parse_formats(options);
+            let status_options = actions::StatusOptions {
+                fail_if_no_yabridge: options.is_present("fail_if_no_yabridge"),
+                formats: &formats,
+                no_cache: options.is_present("no-cache"),
+                show_conflicts: options.is_present("show-conflicts"),
+                outdated: options.is_present("outdated"),
+                quiet: options.is_present("quiet"),
+            };
+
+            if options.is_present("watch") {
+                actions::watch_status(&config, &status_options)
+            } else if options.is_present("json") {
+                actions::show_status_json(&config, &status_options)
+            } else {
+                actions::show_status(&config, &status_options)
+            }
+        }
+        Some(("clashes", _)) => actions::show_clashes(&config),
+        Some(("prefixes", _)) => actions::show_prefixes(&config),
+        Some(("info", options)) => actions::show_plugin_info(
+            &config,
+            &options
+                .value_of_t_or_exit::<PathBuf>("path")
+                .canonicalize()?,
+        ),
+        Some(("list-formats", _)) => actions::list_formats(&config),
+        Some(("self-test", _)) => actions::self_test(&config),
+        Some(("doctor", _)) => actions::doctor(&config),
+        Some(("export-config", _)) => actions::export_config(&config),
+        Some(("import-config", options)) => actions::import_config(
             &mut config,
-            &actions::SyncOptions {
+            options
+                .value_of_os("path")
+                .filter(|path| *path != "-")
+                .map(Path::new),
+            options.is_present("replace"),
+        ),
+        Some(("du", _)) => actions::show_disk_usage(&config),
+        Some(("repair", options)) => actions::repair(&config, options.is_present("dry-run")),
+        Some(("sync", options)) => {
+            let only = options
+                .values_of_os("only")
+                .into_iter()
+                .flatten()
+                .map(|path| canonicalize_path(Path::new(path)))
+                .collect::<Result<Vec<PathBuf>>>()?;
+            let sync_options = actions::SyncOptions {
                 force: options.is_present("force"),
                 no_verify: options.is_present("no-verify"),
+                check_wine_prefix: options.is_present("check-wine-prefix"),
+                no_centralized_warning: options.is_present("no-centralized-warning"),
+                quiet_skipped: options.is_present("quiet-skipped"),
+                print_plan: options.is_present("print-plan"),
+                no_summary: options.is_present("no-summary"),
+                relative_symlinks: options.is_present("relative-symlinks"),
+                formats: parse_formats(options),
+                no_cache: options.is_present("no-cache"),
                 prune: options.is_present("prune"),
+                prune_older_than: options
+                    .value_of("prune-older-than")
+                    .map(utils::parse_duration)
+                    .transpose()?,
                 verbose: options.is_present("verbose"),
-            },
-        ),
-        Some(("set", options)) => actions::set_settings(
-            &mut config,
-            &actions::SetOptions {
-                method: options.value_of("method"),
-                // We've already verified that the path is valid, so we should only be getting
-                // errors for missing arguments
-                path: options
-                    .value_of_t::<PathBuf>("path")
-                    .ok()
-                    .and_then(|path| path.canonicalize().ok()),
-                path_auto: options.is_present("path_auto"),
-                no_verify: options.value_of("no_verify").map(|value| value == "true"),
-            },
-        ),
-        Some(("blacklist", blacklist)) => match blacklist.subcommand() {
-            Some(("add", options)) => actions::blacklist::add_path(
+                dry_run: options.is_present("dry-run"),
+                preserve_timestamps: options.is_present("preserve-timestamps"),
+                quiet: options.is_present("quiet"),
+                strict: options.is_present("strict"),
+                only,
+                report: options.value_of_os("report").map(PathBuf::from),
+                yes: options.is_present("yes"),
+                link_presets: options.is_present("link-presets"),
+                output_dir: options
+                    .value_of_os("output-dir")
+                    .map(|path| env::current_dir().map(|cwd| cwd.join(path)))
+                    .transpose()?,
+                watch: options.is_present("watch"),
+            };
+
+            if options.is_present("watch") {
+                actions::watch_sync(&mut config, &sync_options)
+            } else {
+                actions::do_sync(&mut config, &sync_options)
+            }
+        }
+        Some(("set", options)) if options.is_present("show") => actions::show_settings(&config),
+        Some(("set", options)) => {
+            // A single existing path is stored as is. Multiple paths or glob patterns are stored
+            // as an ordered list of candidates, with literal paths escaped so they're matched
+            // exactly. We've already verified that the paths and patterns are valid.
+            let paths: Vec<&str> = options.values_of("path").into_iter().flatten().collect();
+            let (path, path_candidates) = match paths.as_slice() {
+                [] => (None, Vec::new()),
+                [path] if !is_glob_pattern(path) || Path::new(path).exists() => {
+                    (Some(canonicalize_path(Path::new(path))?), Vec::new())
+                }
+                paths => (
+                    None,
+                    paths
+                        .iter()
+                        .map(|path| match Path::new(path).canonicalize() {
+                            Ok(path) => glob::Pattern::escape(&path.to_string_lossy()),
+                            Err(_) => path.to_string(),
+                        })
+                        .collect(),
+                ),
+            };
+
+            actions::set_settings(
                 &mut config,
-                options
-                    .value_of_t_or_exit::<PathBuf>("path")
-                    .canonicalize()?,
-            ),
-            Some(("rm", options)) => actions::blacklist::remove_path(
+                &actions::SetOptions {
+                    method: options.value_of("method"),
+                    path,
+                    path_candidates,
+                    path_auto: options.is_present("path_auto"),
+                    host_exe: options
+                        .value_of_os("host_exe")
+                        .map(|path| canonicalize_path(Path::new(path)))
+                        .transpose()?,
+                    host_32_exe: options
+                        .value_of_os("host_32_exe")
+                        .map(|path| canonicalize_path(Path::new(path)))
+                        .transpose()?,
+                    host_auto: options.is_present("host_auto"),
+                    no_verify: options.value_of("no_verify").map(|value| value == "true"),
+                    no_centralized_warning: options
+                        .value_of("no_centralized_warning")
+                        .map(|value| value == "true"),
+                    quiet_skipped: options
+                        .value_of("quiet_skipped")
+                        .map(|value| value == "true"),
+                    max_parse_errors: options.value_of_t::<usize>("max_parse_errors").ok(),
+                    parser: options.value_of("parser"),
+                    flatpak: options.value_of("flatpak"),
+                    no_flatpak: options.is_present("no_flatpak"),
+                    // This directory does not need to exist yet, it will be created during the sync
+                    vst3_home: match options.value_of_t::<PathBuf>("vst3_home") {
+                        Ok(path) => Some(utils::normalize_path(&env::current_dir()?.join(path))),
+                        Err(_) => None,
+                    },
+                    vst3_home_auto: options.is_present("vst3_home_auto"),
+                    lib_arch: options.value_of("lib_arch"),
+                    lib_arch_auto: options.is_present("lib_arch_auto"),
+                },
+            )
+        }
+        Some(("blacklist", blacklist)) => match blacklist.subcommand() {
+            Some(("add", options)) if options.is_present("glob") => {
+                actions::blacklist::add_pattern(&mut config, options.value_of("glob").unwrap())
+            }
+            Some(("add", options)) => {
+                let mut blacklisted_paths: Vec<PathBuf> = Vec::new();
+                let result = for_each_path(options, "Blacklisted", |path| {
+                    let path = canonicalize_path(&path)?;
+                    actions::blacklist::add_path(&mut config, path.clone())?;
+                    blacklisted_paths.push(path);
+
+                    Ok(())
+                });
+
+                // Clean up after the paths that could be blacklisted, even if some of them failed
+                if options.is_present("recursive-clean") {
+                    actions::blacklist::remove_managed_files(
+                        &config,
+                        &blacklisted_paths,
+                        options.is_present("quiet"),
+                    )?;
+                }
+
+                result
+            }
+            Some(("rm", options)) => {
+                actions::blacklist::remove_entry(&mut config, options.value_of("path").unwrap())
+            }
+            Some(("list", options)) => {
+                actions::blacklist::list_paths(&config, options.is_present("null"))
+            }
+            Some(("clear", _)) => actions::blacklist::clear(&mut config),
+            _ => unreachable!(),
+        },
+        Some(("keep", keep)) => match keep.subcommand() {
+            Some(("add", options)) => {
+                // Leftover files are often symlinks, so we can't resolve the last component for
+                // those. Symlinked directories are resolved so the files in them are kept as well.
+                let path = env::current_dir()?.join(options.value_of_t_or_exit::<PathBuf>("path"));
+                let path = if path.is_dir() {
+                    utils::normalize_path(&path)
+                } else {
+                    utils::normalize_parent_path(&path)
+                };
+
+                actions::keep::add_path(&mut config, path)
+            }
+            Some(("rm", options)) => actions::keep::remove_path(
                 &mut config,
                 &options.value_of_t_or_exit::<PathBuf>("path"),
             ),
-            Some(("list", _)) => actions::blacklist::list_paths(&config),
-            Some(("clear", _)) => actions::blacklist::clear(&mut config),
+            Some(("list", options)) => {
+                actions::keep::list_paths(&config, options.is_present("null"))
+            }
+            Some(("clear", _)) => actions::keep::clear(&mut config),
             _ => unreachable!(),
         },
         _ => unreachable!(),
     }
 }
 
+/// Parse the plugin formats passed to the `--format` option. If the option was not used, then this
+/// will contain all plugin formats.
+fn parse_formats(options: &ArgMatches) -> Vec<PluginFormat> {
+    match options.values_of("format") {
+        Some(formats) => formats
+            .map(|format| match format {
+                "vst2" => PluginFormat::Vst2,
+                "vst3" => PluginFormat::Vst3,
+                s => unimplemented!("Unexpected plugin format '{}'", s),
+            })
+            .collect(),
+        None => PluginFormat::ALL.to_vec(),
+    }
+}
+
+/// Run `action` for every path passed to the `path` argument. A failure for one path is printed
+/// without aborting the other paths. When multiple paths were passed, the number of paths that
+/// were processed successfully is printed afterwards, and an error is returned if any of them
+/// failed. `past_verb` is used in that summary, e.g. `Added`. The errors returned by `action`
+/// should mention the path they belong to.
+fn for_each_path(
+    options: &ArgMatches,
+    past_verb: &str,
+    mut action: impl FnMut(PathBuf) -> Result<()>,
+) -> Result<()> {
+    let paths: Vec<PathBuf> = options
+        .values_of_os("path")
+        .unwrap()
+        .map(PathBuf::from)
+        .collect();
+    // A single path behaves exactly like it did before multiple paths were supported
+    if let [path] = paths.as_slice() {
+        return action(path.clone());
+    }
+
+    let mut num_failed = 0;
+    for path in &paths {
+        if let Err(err) = action(path.clone()) {
+            eprintln!("Error: {:#}", err);
+            num_failed += 1;
+        }
+    }
+
+    if num_failed > 0 {
+        Err(anyhow!(
+            "{} {} of {} paths, {} failed",
+            past_verb,
+            paths.len() - num_failed,
+            paths.len(),
+            num_failed
+        ))
+    } else {
+        if !options.is_present("quiet") {
+            println!("{} {} paths", past_verb, paths.len());
+        }
+
+        Ok(())
+    }
+}
+
+/// Canonicalize a path passed on the command line, with a clearer error message if the path does
+/// not exist.
+fn canonicalize_path(path: &Path) -> Result<PathBuf> {
+    path.canonicalize()
+        .with_context(|| format!("File or directory '{}' could not be found", path.display()))
+}
+
+/// Resolve a number printed by `yabridgectl list --index` to the plugin location it refers to.
+/// Anything else is returned as is. Plugin locations are always stored as absolute paths, so a
+/// plain number can never refer to an existing location on its own.
+fn resolve_location_index(config: &Config, path: &OsStr) -> Result<PathBuf> {
+    let index: usize = match path.to_str().and_then(|path| path.parse().ok()) {
+        Some(index) => index,
+        None => return Ok(PathBuf::from(path)),
+    };
+
+    index
+        .checked_sub(1)
+        .and_then(|index| {
+            config
+                .plugin_dirs
+                .iter()
+                .chain(config.plugin_files.iter())
+                .nth(index)
+        })
+        .cloned()
+        .with_context(|| {
+            format!(
+                "There is no plugin location with number {}, use 'yabridgectl list --index' to \
+                 list the plugin locations",
+                index
+            )
+        })
+}
+
+/// Verify that a path exists or that it's an absolute glob pattern, used for validating the yabridge
+/// path.
+fn validate_path_or_pattern(path: &str) -> Result<(), String> {
+    if Path::new(path).exists() || !is_glob_pattern(path) {
+        return validate_path(path);
+    }
+
+    if Path::new(path).is_relative() {
+        return Err(format!(
+            "Glob pattern '{}' should be an absolute path",
+            path
+        ));
+    }
+
+    glob::Pattern::new(path)
+        .map(|_| ())
+        .map_err(|err| format!("Invalid glob pattern: {}", err))
+}
+
+/// Whether `path` contains any of the characters that have a special meaning in glob patterns.
+fn is_glob_pattern(path: &str) -> bool {
+    path.contains(&['*', '?', '['][..])
+}
+
 /// Verify that a path exists, used for validating arguments.
 fn validate_path(path: &str) -> Result<(), String> {
     let path = Path::new(path);
@@ -18,26 +18,56 @@
 
 use anyhow::{anyhow, Context, Result};
 use colored::Colorize;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use is_executable::IsExecutable;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
 use std::env;
 use std::fs;
 use std::hash::Hasher;
-use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::os::unix::ffi::OsStrExt;
 use std::os::unix::fs as unix_fs;
+use std::os::unix::fs::MetadataExt;
 use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
 use textwrap::Wrapper;
 
-use crate::config::{self, Config, KnownConfig, YABRIDGE_HOST_32_EXE_NAME, YABRIDGE_HOST_EXE_NAME};
-use crate::files::{LibArchitecture, NativeFile};
+use crate::config::{
+    self, Config, KnownConfig, YabridgeFiles, YABRIDGE_HOST_32_EXE_NAME, YABRIDGE_HOST_EXE_NAME,
+};
+use crate::files::{LibArchitecture, NativeFile, Plugin};
 
 /// (Part of) the expected output when running `yabridge-host.exe`. Used to verify that everything's
 /// working correctly. We'll only match this prefix so we can modify the exact output at a later
 /// moment without causing issues.
 const YABRIDGE_HOST_EXPECTED_OUTPUT_PREFIX: &str = "Usage: yabridge-";
 
+/// The name of the registry file in the root of a Wine prefix. This is used to detect whether a
+/// directory is a Wine prefix, and to determine the prefix's architecture.
+const WINE_SYSTEM_REG_NAME: &str = "system.reg";
+
+/// Set by our SIGINT handler after `catch_interrupts()` has been called.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Microsoft Visual C++ runtime libraries commonly imported by plugins, along with the `winetricks`
+/// verb that installs them. Wine ships its own implementations of these, but plugins often won't
+/// load with those.
+const VC_RUNTIME_DLLS: &[(&str, &str)] = &[
+    ("msvcp140.dll", "vcrun2019"),
+    ("vcruntime140.dll", "vcrun2019"),
+    ("vcruntime140_1.dll", "vcrun2019"),
+    ("msvcp120.dll", "vcrun2013"),
+    ("msvcr120.dll", "vcrun2013"),
+    ("msvcp110.dll", "vcrun2012"),
+    ("msvcr110.dll", "vcrun2012"),
+    ("msvcp100.dll", "vcrun2010"),
+    ("msvcr100.dll", "vcrun2010"),
+];
+
 /// Wrapper around [`reflink::reflink_or_copy()`](reflink::reflink_or_copy) with a human readable
 /// error message.
 pub fn copy_or_reflink<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<Option<u64>> {
@@ -87,6 +117,61 @@ pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<()> {
     })
 }
 
+/// Wrapper around [`std::fs::hard_link()`](std::fs::hard_link) with a human readable error
+/// message.
+pub fn hard_link<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> Result<()> {
+    fs::hard_link(&src, &dst).with_context(|| {
+        format!(
+            "Error hard linking '{}' to '{}'. Hard links only work when both files are on the \
+             same file system.",
+            src.as_ref().display(),
+            dst.as_ref().display()
+        )
+    })
+}
+
+/// Check whether two paths refer to the same file by comparing their device and inode numbers.
+/// Symlinks are followed.
+pub fn is_same_file<P: AsRef<Path>, Q: AsRef<Path>>(a: P, b: Q) -> Result<bool> {
+    let metadata = |path: &Path| {
+        fs::metadata(path).with_context(|| format!("Could not read '{}'", path.display()))
+    };
+    let (a, b) = (metadata(a.as_ref())?, metadata(b.as_ref())?);
+
+    Ok(a.dev() == b.dev() && a.ino() == b.ino())
+}
+
+/// Compute a relative path that points to `path` from within the `base` directory. This is done
+/// purely lexically, so symlinks in either path are not resolved. If one of the two paths is not
+/// absolute, then `path` is returned as is. If the two paths are the same, then this returns `.`.
+pub fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    if !path.is_absolute() || !base.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let path_components: Vec<_> = path.components().collect();
+    let base_components: Vec<_> = base.components().collect();
+    let common_prefix_len = path_components
+        .iter()
+        .zip(&base_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut result = PathBuf::new();
+    for _ in common_prefix_len..base_components.len() {
+        result.push("..");
+    }
+    for component in &path_components[common_prefix_len..] {
+        result.push(component);
+    }
+    // An empty path would not be a valid symlink target
+    if result.as_os_str().is_empty() {
+        result.push(".");
+    }
+
+    result
+}
+
 /// Get the architecture of the ELF file at `path`. This detection is a bit naive, but we'd rather
 /// not depend on `libmagic` or `libreadelf` just for this, since encountering a 32-bit yabridge
 /// library is going to be incredibly rare.
@@ -118,8 +203,456 @@ pub fn get_elf_architecture(path: &Path) -> Result<LibArchitecture> {
     match machine_arch {
         0x03 => Ok(LibArchitecture::Lib32), // x86
         0x3E => Ok(LibArchitecture::Lib64), // AMD x86-64
-        _ => Err(anyhow!("'{}' is not a recognized ELF machine ISA")),
+        // Other architectures can't be mapped to a `LibArchitecture` automatically, since hosts on
+        // other platforms look for the library in a different `Contents/*-linux` directory. When
+        // running x86 plugin hosts through an emulator the user can still set the architecture.
+        _ => {
+            let machine_name = match machine_arch {
+                0x28 => String::from("32-bit ARM"),
+                0xB7 => String::from("AArch64"),
+                _ => format!("an unknown ELF machine type ({:#06x})", machine_arch),
+            };
+
+            Err(anyhow!(
+                "'{}' has been compiled for {}, only x86 and x86_64 builds of yabridge are \
+                 detected automatically. Use 'yabridgectl set --lib-arch=<x86|x86_64>' to set \
+                 the architecture yabridgectl should use instead.",
+                path.display(),
+                machine_name
+            ))
+        }
+    }
+}
+
+/// Check whether `path` is an ELF file by looking at the magic number at the start of its header.
+/// Native Linux VST3 modules sometimes end up in plugin locations with a `.vst3` extension, and
+/// those should not be parsed as Windows PE32(+) binaries. Returns `false` if the file can't be
+/// read.
+pub fn is_elf_file(path: &Path) -> bool {
+    let mut magic_bytes = [0u8; 4];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic_bytes))
+        .map(|_| magic_bytes == *b"\x7fELF")
+        .unwrap_or(false)
+}
+
+/// Check whether a PE32(+) binary is a managed .NET assembly. These assemblies have a CLR runtime
+/// header and they don't export any native functions, so they can never be plugins. Like with
+/// `get_elf_architecture()` we'll parse just enough of the headers to find this out.
+///
+/// This is based on the PE format specification:
+/// https://docs.microsoft.com/en-us/windows/win32/debug/pe-format
+pub fn is_managed_assembly(path: &Path) -> Result<bool> {
+    let mut file = fs::File::open(path)?;
+    let mut u16_bytes = [0u8; 2];
+    let mut u32_bytes = [0u8; 4];
+
+    file.seek(SeekFrom::Start(0x3C))?; // e_lfanew, 4 bytes
+    file.read_exact(&mut u32_bytes)?;
+    let pe_header_offset = u32::from_le_bytes(u32_bytes) as u64;
+
+    file.seek(SeekFrom::Start(pe_header_offset))?;
+    file.read_exact(&mut u32_bytes)?;
+    if &u32_bytes != b"PE\0\0" {
+        return Ok(false);
+    }
+
+    // The optional header directly follows the 4 byte signature and the 20 byte COFF header, and
+    // the offset of the data directories depends on whether this is a PE32 or a PE32+ binary
+    let optional_header_offset = pe_header_offset + 4 + 20;
+    file.seek(SeekFrom::Start(optional_header_offset))?;
+    file.read_exact(&mut u16_bytes)?;
+    let data_directories_offset = match u16::from_le_bytes(u16_bytes) {
+        0x10B => optional_header_offset + 96,  // PE32
+        0x20B => optional_header_offset + 112, // PE32+
+        _ => return Ok(false),
+    };
+
+    // NumberOfRvaAndSizes directly precedes the data directories. The CLR runtime header is the
+    // fifteenth data directory, and every data directory entry is 8 bytes.
+    file.seek(SeekFrom::Start(data_directories_offset - 4))?;
+    file.read_exact(&mut u32_bytes)?;
+    if u32::from_le_bytes(u32_bytes) < 15 {
+        return Ok(false);
+    }
+
+    file.seek(SeekFrom::Start(data_directories_offset + 14 * 8))?;
+    file.read_exact(&mut u32_bytes)?;
+    Ok(u32::from_le_bytes(u32_bytes) != 0)
+}
+
+/// The parts of a PE32(+) binary's headers needed to find its data directories and to translate
+/// the RVAs stored in there to file offsets. This is shared by the functions below that parse the
+/// import, export, and resource tables.
+///
+/// This is based on the same specification as `is_managed_assembly()`:
+/// https://docs.microsoft.com/en-us/windows/win32/debug/pe-format
+struct PeHeaders {
+    /// The machine type from the COFF header.
+    machine: u16,
+    /// The file offset of the first data directory.
+    data_directories_offset: u64,
+    /// The number of data directories in the optional header.
+    num_data_directories: u32,
+    /// The virtual address, the size, and the file offset of every section.
+    sections: Vec<(u32, u32, u32)>,
+}
+
+impl PeHeaders {
+    /// Read the headers from a PE32(+) binary. Returns `None` if the file doesn't have a DOS and a
+    /// PE signature, and an error if the headers are malformed.
+    fn read(file: &mut fs::File) -> Result<Option<PeHeaders>> {
+        if file.metadata()?.len() < 0x40 || read_u16_at(file, 0)?.to_le_bytes() != *b"MZ" {
+            return Ok(None);
+        }
+
+        let pe_header_offset = read_u32_at(file, 0x3C)? as u64; // e_lfanew
+        match read_u32_at(file, pe_header_offset) {
+            Ok(signature) if signature.to_le_bytes() == *b"PE\0\0" => (),
+            _ => return Ok(None),
+        }
+
+        let machine = read_u16_at(file, pe_header_offset + 4)?;
+        let num_sections = read_u16_at(file, pe_header_offset + 4 + 2)?;
+        let optional_header_size = read_u16_at(file, pe_header_offset + 4 + 16)?;
+        let optional_header_offset = pe_header_offset + 4 + 20;
+        let data_directories_offset = match read_u16_at(file, optional_header_offset)? {
+            0x10B => optional_header_offset + 96,  // PE32
+            0x20B => optional_header_offset + 112, // PE32+
+            magic => return Err(anyhow!("Unknown optional header magic 0x{:X}", magic)),
+        };
+        // NumberOfRvaAndSizes directly precedes the data directories
+        let num_data_directories = read_u32_at(file, data_directories_offset - 4)?;
+
+        let mut sections = Vec::with_capacity(num_sections as usize);
+        let section_table_offset = optional_header_offset + optional_header_size as u64;
+        for i in 0..num_sections as u64 {
+            let section_offset = section_table_offset + i * 40;
+            let virtual_size = read_u32_at(file, section_offset + 8)?;
+            let virtual_address = read_u32_at(file, section_offset + 12)?;
+            let raw_data_size = read_u32_at(file, section_offset + 16)?;
+            let raw_data_offset = read_u32_at(file, section_offset + 20)?;
+            sections.push((
+                virtual_address,
+                virtual_size.max(raw_data_size),
+                raw_data_offset,
+            ));
+        }
+
+        Ok(Some(PeHeaders {
+            machine,
+            data_directories_offset,
+            num_data_directories,
+            sections,
+        }))
+    }
+
+    /// Get the RVA stored in the data directory with index `index`. Returns `None` if the binary
+    /// doesn't have that data directory, or if it's empty.
+    fn data_directory_rva(&self, file: &mut fs::File, index: u32) -> Result<Option<u32>> {
+        if index >= self.num_data_directories {
+            return Ok(None);
+        }
+
+        // Every data directory entry is 8 bytes, starting with the RVA
+        let rva = read_u32_at(file, self.data_directories_offset + index as u64 * 8)?;
+        Ok(if rva == 0 { None } else { Some(rva) })
+    }
+
+    /// Translate an RVA to a file offset using the section table. Returns `None` if the RVA is not
+    /// part of any section, or if the resulting offset doesn't fit in the file's 32-bit offsets.
+    fn rva_to_offset(&self, rva: u32) -> Option<u64> {
+        self.sections
+            .iter()
+            .find(|(address, size, _)| rva >= *address && rva - address < *size)
+            .and_then(|(address, _, offset)| offset.checked_add(rva - address))
+            .map(u64::from)
+    }
+}
+
+/// Read a little endian `u16` from `file` at `offset`.
+fn read_u16_at(file: &mut fs::File, offset: u64) -> Result<u16> {
+    let mut bytes = [0u8; 2];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+/// Read a little endian `u32` from `file` at `offset`.
+fn read_u32_at(file: &mut fs::File, offset: u64) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Read a null terminated string from `file` at `offset`, like the DLL and function names in the
+/// import and export tables. Names are cut off after 256 bytes.
+fn read_c_string_at(file: &mut fs::File, offset: u64) -> Result<String> {
+    let mut name = Vec::new();
+    file.seek(SeekFrom::Start(offset))?;
+    file.take(256).read_to_end(&mut name)?;
+    let name_len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+
+    Ok(String::from_utf8_lossy(&name[..name_len]).into_owned())
+}
+
+/// Get the names of all DLLs imported by the PE32(+) binary at `path`. This parses the import
+/// directory ourselves for the same reason as in `is_managed_assembly()`. Returns an empty list if
+/// the file is not a PE32(+) binary.
+///
+/// This is based on the same specification as `is_managed_assembly()`:
+/// https://docs.microsoft.com/en-us/windows/win32/debug/pe-format#import-directory-table
+pub fn get_imported_dlls(path: &Path) -> Result<Vec<String>> {
+    let mut file = fs::File::open(path)?;
+    let headers = match PeHeaders::read(&mut file)? {
+        Some(headers) => headers,
+        None => return Ok(Vec::new()),
+    };
+
+    // The import table is the second data directory
+    let import_table_rva = match headers.data_directory_rva(&mut file, 1)? {
+        Some(rva) => rva,
+        None => return Ok(Vec::new()),
+    };
+
+    // The import directory table is a list of 20 byte entries terminated by an empty entry
+    let mut dll_names = Vec::new();
+    let mut descriptor_offset = match headers.rva_to_offset(import_table_rva) {
+        Some(offset) => offset,
+        None => return Ok(Vec::new()),
+    };
+    loop {
+        let name_rva = read_u32_at(&mut file, descriptor_offset + 12)?;
+        if name_rva == 0 {
+            break;
+        }
+
+        if let Some(name_offset) = headers.rva_to_offset(name_rva) {
+            dll_names.push(read_c_string_at(&mut file, name_offset)?);
+        }
+
+        descriptor_offset += 20;
+    }
+
+    Ok(dll_names)
+}
+
+/// Get the machine type and the names of all functions exported by the PE32(+) binary at `path`.
+/// This is what yabridgectl's built-in parser uses instead of `winedump` to determine whether a file
+/// is a plugin. Returns `None` if the file is not a PE32(+) binary, and an error if the file could
+/// not be read or if its headers are malformed.
+///
+/// This is based on the same specification as `is_managed_assembly()`:
+/// https://docs.microsoft.com/en-us/windows/win32/debug/pe-format#export-directory-table
+pub fn get_exported_functions(path: &Path) -> Result<Option<(u16, Vec<String>)>> {
+    // Nothing should be exporting this many functions, but we don't want to read garbage forever
+    const MAX_EXPORTED_NAMES: u32 = 1 << 16;
+
+    let mut file = fs::File::open(path)?;
+    let headers = match PeHeaders::read(&mut file)? {
+        Some(headers) => headers,
+        None => return Ok(None),
+    };
+
+    // The export table is the first data directory
+    let export_table_rva = match headers.data_directory_rva(&mut file, 0)? {
+        Some(rva) => rva,
+        None => return Ok(Some((headers.machine, Vec::new()))),
+    };
+    let rva_to_offset = |rva: u32| {
+        headers
+            .rva_to_offset(rva)
+            .ok_or_else(|| anyhow!("RVA 0x{:X} is not part of any section", rva))
+    };
+
+    // The export directory table contains the number of named exports and an RVA pointing to an
+    // array of RVAs for those names
+    let export_directory_offset = rva_to_offset(export_table_rva)?;
+    let num_names = read_u32_at(&mut file, export_directory_offset + 24)?;
+    if num_names > MAX_EXPORTED_NAMES {
+        return Err(anyhow!("The export table contains {} names", num_names));
+    }
+    let name_pointers_offset =
+        rva_to_offset(read_u32_at(&mut file, export_directory_offset + 32)?)?;
+
+    let mut function_names = Vec::with_capacity(num_names as usize);
+    for i in 0..num_names as u64 {
+        let name_offset = rva_to_offset(read_u32_at(&mut file, name_pointers_offset + i * 4)?)?;
+        function_names.push(read_c_string_at(&mut file, name_offset)?);
+    }
+
+    Ok(Some((headers.machine, function_names)))
+}
+
+/// Get the product name stored in the version resource of the PE32(+) binary at `path`. Plugin
+/// files are often named differently from the plugin itself, so this is shown in `yabridgectl
+/// status`. Returns `None` if the file is not a PE32(+) binary or if it doesn't have a version
+/// resource containing a product name.
+///
+/// This is based on the same specification as `is_managed_assembly()`, and the version resource's
+/// layout is described here:
+/// https://docs.microsoft.com/en-us/windows/win32/menurc/vs-versioninfo
+pub fn get_product_name(path: &Path) -> Result<Option<String>> {
+    // Version resources can contain a lot of strings, but we don't need to read absurdly large ones
+    const MAX_VERSION_RESOURCE_SIZE: u32 = 64 * 1024;
+    const RT_VERSION: u32 = 16;
+
+    let mut file = fs::File::open(path)?;
+    let headers = match PeHeaders::read(&mut file)? {
+        Some(headers) => headers,
+        None => return Ok(None),
+    };
+
+    // The resource table is the third data directory
+    let resource_table_offset = match headers
+        .data_directory_rva(&mut file, 2)?
+        .and_then(|rva| headers.rva_to_offset(rva))
+    {
+        Some(offset) => offset,
+        None => return Ok(None),
+    };
+
+    // The resource table is a tree of directories sorted by type, name, and language. Every
+    // directory has a 16 byte header followed by 8 byte entries, with the named entries coming
+    // before the entries with numeric IDs. The high bit of an entry's offset is set if it points to
+    // another directory. We want the first version resource, regardless of its name and language.
+    let find_entry = |file: &mut fs::File, directory_offset: u64, id: Option<u32>| {
+        let num_named_entries = read_u16_at(file, directory_offset + 12)? as u64;
+        let num_id_entries = read_u16_at(file, directory_offset + 14)? as u64;
+        for i in 0..(num_named_entries + num_id_entries) {
+            let entry_offset = directory_offset + 16 + i * 8;
+            let matches = match id {
+                Some(id) => i >= num_named_entries && read_u32_at(file, entry_offset)? == id,
+                None => true,
+            };
+            if matches {
+                return read_u32_at(file, entry_offset + 4).map(Some);
+            }
+        }
+
+        Ok(None)
+    };
+    let mut entry_offset = match find_entry(&mut file, resource_table_offset, Some(RT_VERSION))? {
+        Some(offset) => offset,
+        None => return Ok(None),
+    };
+    // After the type directory there should only be the name and language directories. A
+    // malformed file could contain a cycle here, so we won't follow any more directories than that.
+    for _ in 0..2 {
+        if entry_offset & 0x8000_0000 == 0 {
+            break;
+        }
+
+        let directory_offset = resource_table_offset + (entry_offset & 0x7FFF_FFFF) as u64;
+        entry_offset = match find_entry(&mut file, directory_offset, None)? {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+    }
+    if entry_offset & 0x8000_0000 != 0 {
+        return Ok(None);
+    }
+
+    let data_entry_offset = resource_table_offset + entry_offset as u64;
+    let data_rva = read_u32_at(&mut file, data_entry_offset)?;
+    let data_size = read_u32_at(&mut file, data_entry_offset + 4)?.min(MAX_VERSION_RESOURCE_SIZE);
+    let data_offset = match headers.rva_to_offset(data_rva) {
+        Some(offset) => offset,
+        None => return Ok(None),
+    };
+    let mut data = vec![0u8; data_size as usize];
+    file.seek(SeekFrom::Start(data_offset))?;
+    file.read_exact(&mut data)?;
+
+    // Instead of walking the entire `VS_VERSIONINFO` structure we'll look for the `String`
+    // structure with the `ProductName` key. The key is a null terminated UTF-16 string that's
+    // preceded by three 16-bit fields, and the value follows after padding to a 32-bit boundary.
+    let key: Vec<u8> = "ProductName\0"
+        .encode_utf16()
+        .flat_map(|c| c.to_le_bytes())
+        .collect();
+    let key_offset = match data
+        .windows(key.len())
+        .enumerate()
+        .find(|(offset, window)| offset % 2 == 0 && *offset >= 6 && *window == key.as_slice())
+    {
+        Some((offset, _)) => offset,
+        None => return Ok(None),
+    };
+    let value_length = u16::from_le_bytes([data[key_offset - 4], data[key_offset - 3]]) as usize;
+    let value_offset = (key_offset + key.len() + 3) & !3;
+    let value: Vec<u16> = data
+        .get(value_offset..)
+        .unwrap_or_default()
+        .chunks_exact(2)
+        .take(value_length)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&c| c != 0)
+        .collect();
+    let product_name = String::from_utf16_lossy(&value).trim().to_owned();
+
+    Ok(if product_name.is_empty() {
+        None
+    } else {
+        Some(product_name)
+    })
+}
+
+/// Check which Microsoft Visual C++ runtime libraries imported by the plugin at `plugin_path` are
+/// missing from the Wine prefix yabridge will load the plugin in. Libraries shipped alongside the
+/// plugin are fine, and the placeholder DLLs Wine puts in `system32` for its own builtin
+/// implementations are treated as missing. Returns the names of the missing libraries along with
+/// the `winetricks` verb that installs them.
+pub fn find_missing_vc_runtimes(
+    config: &Config,
+    plugin_path: &Path,
+    architecture: LibArchitecture,
+) -> Result<Vec<(String, &'static str)>> {
+    let wine_prefix =
+        get_plugin_wine_prefix(config, plugin_path).unwrap_or_else(default_wine_prefix);
+    let system_dir = wine_prefix.join("drive_c/windows").join(
+        match (architecture, get_wine_prefix_arch(&wine_prefix)) {
+            (LibArchitecture::Lib32, LibArchitecture::Lib64) => "syswow64",
+            _ => "system32",
+        },
+    );
+
+    let mut missing_runtimes = Vec::new();
+    for dll_name in get_imported_dlls(plugin_path)? {
+        let dll_name = dll_name.to_lowercase();
+        let winetricks_verb = match VC_RUNTIME_DLLS
+            .iter()
+            .find(|(name, _)| *name == dll_name.as_str())
+        {
+            Some((_, verb)) => *verb,
+            None => continue,
+        };
+
+        let bundled_dll = plugin_path.with_file_name(&dll_name);
+        let installed_dll = system_dir.join(&dll_name);
+        if !bundled_dll.exists() && !is_real_dll(&installed_dll) {
+            missing_runtimes.push((dll_name, winetricks_verb));
+        }
     }
+
+    Ok(missing_runtimes)
+}
+
+/// Check whether `path` exists and is not one of the placeholder DLLs Wine creates for its builtin
+/// libraries. Those contain a signature directly after the DOS header.
+fn is_real_dll(path: &Path) -> bool {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+
+    let mut signature = [0u8; 20];
+    let is_placeholder = file.seek(SeekFrom::Start(0x40)).is_ok()
+        && file.read_exact(&mut signature).is_ok()
+        && (signature.starts_with(b"Wine builtin DLL") || &signature == b"Wine placeholder DLL");
+
+    !is_placeholder
 }
 
 /// Get the type of a file, if it exists.
@@ -132,12 +665,58 @@ pub fn get_file_type(path: PathBuf) -> Option<NativeFile> {
     }
 }
 
+/// Find the Wine prefix a plugin is installed in. This uses the same method as yabridge itself, by
+/// looking for a parent directory containing a `dosdevices` directory. Returns `None` if the plugin
+/// is not installed inside of a Wine prefix, in which case yabridge will use the default prefix.
+pub fn find_wine_prefix(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|directory| directory.join("dosdevices").is_dir())
+        .map(|directory| directory.to_owned())
+}
+
+/// Find the IDs of all Flatpak applications that have their own `~/.vst3` directory. These
+/// applications won't be able to see the plugins set up in the regular `~/.vst3/yabridge`
+/// directory.
+pub fn find_flatpak_vst3_apps() -> Vec<String> {
+    let flatpak_apps_home =
+        PathBuf::from(env::var("HOME").expect("$HOME is not set")).join(config::FLATPAK_APPS_HOME);
+    let entries = match fs::read_dir(flatpak_apps_home) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut app_ids: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join(".vst3").is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    app_ids.sort();
+
+    app_ids
+}
+
 /// Get the architecture (either 64-bit or 32-bit) of the default Wine prefix in `~/.wine`. Defaults
 /// to 64-bit if `~/.wine` doesn't exist or if the prefix is invalid.
 pub fn get_default_wine_prefix_arch() -> LibArchitecture {
-    let wine_system_reg_path = PathBuf::from(env::var("HOME").expect("$HOME is not set"))
-        .join(".wine")
-        .join("system.reg");
+    get_wine_prefix_arch(&default_wine_prefix())
+}
+
+/// The path to the default Wine prefix in `~/.wine`, used when `$WINEPREFIX` is not set.
+pub fn default_wine_prefix() -> PathBuf {
+    PathBuf::from(env::var("HOME").expect("$HOME is not set")).join(".wine")
+}
+
+/// Check whether `path` looks like a Wine prefix. Like `get_wine_prefix_arch()`, this looks for the
+/// prefix's `system.reg` file.
+pub fn is_wine_prefix(path: &Path) -> bool {
+    path.join(WINE_SYSTEM_REG_NAME).is_file()
+}
+
+/// Get the architecture (either 64-bit or 32-bit) of the Wine prefix at `prefix`. Defaults to
+/// 64-bit if the prefix doesn't exist or if the prefix is invalid.
+pub fn get_wine_prefix_arch(prefix: &Path) -> LibArchitecture {
+    let wine_system_reg_path = prefix.join(WINE_SYSTEM_REG_NAME);
 
     // Fall back to 64-bit if the prefix doesn't exist
     let wine_system_reg = match fs::File::open(wine_system_reg_path) {
@@ -159,6 +738,20 @@ pub fn get_default_wine_prefix_arch() -> LibArchitecture {
     LibArchitecture::Lib64
 }
 
+/// Get the Wine prefix yabridge will use for the plugin at `plugin_path`. Just like in yabridge
+/// itself, this is `$WINEPREFIX` if that's set, the Wine prefix associated with the plugin's
+/// location if there is one, or the prefix the plugin is installed in otherwise. Returns `None` if
+/// yabridge will use the default Wine prefix.
+pub fn get_plugin_wine_prefix(config: &Config, plugin_path: &Path) -> Option<PathBuf> {
+    match env::var("WINEPREFIX") {
+        Ok(prefix) => Some(PathBuf::from(prefix)),
+        Err(_) => config
+            .associated_wine_prefix(plugin_path)
+            .map(|prefix| prefix.to_owned())
+            .or_else(|| find_wine_prefix(plugin_path)),
+    }
+}
+
 /// Hash the conetnts of a file as an `i64` using Rust's built in hasher. Collisions are not a big
 /// issue in our situation so we can get away with this.
 ///
@@ -176,20 +769,282 @@ pub fn hash_file(file: &Path) -> Result<i64> {
     Ok(hasher.finish() as i64)
 }
 
+/// Format a path for use in yabridgectl's output. Paths on Linux can contain newlines and other
+/// control characters, which would break up the output. Those characters are escaped the same way
+/// they would be in a Rust string literal.
+pub fn display_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Parse a duration like `30s`, `15m`, `12h`, `7d`, or `2w`. Used for the `--prune-older-than`
+/// option.
+pub fn parse_duration(duration: &str) -> Result<Duration> {
+    let unit_start = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Missing a unit, expected one of 's', 'm', 'h', 'd', or 'w'"))?;
+    let (amount, unit) = duration.split_at(unit_start);
+    let amount: u64 = amount
+        .parse()
+        .with_context(|| format!("Invalid duration '{}'", duration))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 60 * 60 * 24,
+        "w" => 60 * 60 * 24 * 7,
+        _ => {
+            return Err(anyhow!(
+                "Unknown unit '{}', expected one of 's', 'm', 'h', 'd', or 'w'",
+                unit
+            ))
+        }
+    };
+
+    amount
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("Duration '{}' is too long", duration))
+}
+
+/// Format how long ago something happened, like `5 minutes ago`. Only the largest unit is shown
+/// since this is only meant to give a rough idea.
+pub fn format_time_ago(elapsed: Duration) -> String {
+    const UNITS: &[(u64, &str)] = &[
+        (60 * 60 * 24 * 7, "week"),
+        (60 * 60 * 24, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+
+    let secs = elapsed.as_secs();
+    for (unit_secs, unit) in UNITS {
+        let amount = secs / unit_secs;
+        if amount == 1 {
+            return format!("1 {} ago", unit);
+        } else if amount > 1 {
+            return format!("{} {}s ago", amount, unit);
+        }
+    }
+
+    String::from("just now")
+}
+
+/// Format a file size in bytes using binary units, like `12.3 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{} bytes", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+
+        size /= 1024.0;
+        unit = next_unit;
+    }
+
+    format!("{:.1} {}", size, unit)
+}
+
+/// The template used by `Progress` until we know how many plugin files need to be parsed. The
+/// message contains the number of indexed files.
+const PROGRESS_INDEXING_TEMPLATE: &str = "Indexing plugin locations... {msg}";
+
+/// The template used by `Progress` once plugin files are being parsed.
+const PROGRESS_PARSING_TEMPLATE: &str = "[{bar:30}] {pos}/{len} plugin files parsed, {msg}";
+
+/// `Progress` only updates the number of indexed files shown in its message every this many files,
+/// since formatting the progress bar for every file would slow down indexing large directories.
+/// This needs to be a power of two.
+const PROGRESS_INDEXED_FILES_INTERVAL: usize = 128;
+
+/// A progress bar for indexing and searching plugin locations, since scanning a directory with
+/// thousands of plugins can take a while. This shows the number of plugin files parsed so far
+/// along with the number of indexed files on STDERR, so it doesn't interfere with the regular
+/// output on STDOUT. `indicatif` hides the bar when STDERR is not a terminal. All counters can be
+/// updated from multiple threads at once. The bar is cleared again when this object is dropped.
+pub struct Progress {
+    bar: ProgressBar,
+    indexed_files: AtomicUsize,
+}
+
+impl Progress {
+    /// Create a progress bar that will be drawn if STDERR is a terminal.
+    pub fn on_stderr() -> Self {
+        Progress::with_draw_target(ProgressDrawTarget::stderr())
+    }
+
+    /// Create a progress bar that only keeps count and never draws anything.
+    pub fn hidden() -> Self {
+        Progress::with_draw_target(ProgressDrawTarget::hidden())
+    }
+
+    fn with_draw_target(target: ProgressDrawTarget) -> Self {
+        let bar = ProgressBar::with_draw_target(0, target)
+            .with_style(ProgressStyle::default_bar().template(PROGRESS_INDEXING_TEMPLATE));
+
+        Progress {
+            bar,
+            indexed_files: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count a file found while indexing.
+    pub fn inc_indexed(&self) {
+        let indexed_files = self.indexed_files.fetch_add(1, Ordering::Relaxed) + 1;
+        if indexed_files & (PROGRESS_INDEXED_FILES_INTERVAL - 1) == 0 {
+            self.bar
+                .set_message(format!("{} files indexed", indexed_files));
+        }
+    }
+
+    /// Add `num_files` files to the number of files that are going to be parsed. This also switches
+    /// to a progress bar for the parsed files.
+    pub fn add_to_parse(&self, num_files: usize) {
+        self.bar.set_style(
+            ProgressStyle::default_bar()
+                .template(PROGRESS_PARSING_TEMPLATE)
+                .progress_chars("#>-"),
+        );
+        self.bar.set_message(format!(
+            "{} files indexed",
+            self.indexed_files.load(Ordering::Relaxed)
+        ));
+        self.bar.inc_length(num_files as u64);
+    }
+
+    /// Count a file that has been parsed.
+    pub fn inc_parsed(&self) {
+        self.bar.inc(1);
+    }
+
+    /// Print a message to STDERR above the progress bar. Unlike `ProgressBar::println()`, this also
+    /// prints the message when the progress bar is hidden.
+    pub fn println(&self, message: &str) {
+        if self.bar.is_hidden() {
+            eprintln!("{}", message);
+        } else {
+            self.bar.println(message);
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// Install a SIGINT handler so pressing Ctrl+C no longer immediately terminates yabridgectl. Long
+/// running operations can instead poll `interrupted()` and stop at a point where they won't leave
+/// any half written files behind. Pressing Ctrl+C a second time still exits immediately, in case
+/// the user doesn't want to wait for that.
+pub fn catch_interrupts() {
+    extern "C" fn handle_sigint(_signal: libc::c_int) {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            // SAFETY: `_exit()` is async-signal-safe, unlike `std::process::exit()`
+            unsafe { libc::_exit(128 + libc::SIGINT) };
+        }
+    }
+
+    // SAFETY: The handler only uses an atomic and `_exit()`, which are both async-signal-safe
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            handle_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+}
+
+/// Whether Ctrl+C has been pressed since `catch_interrupts()` was called.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Write paths to STDOUT separated by null bytes instead of newlines. The paths are written as is,
+/// so this is the only unambiguous way to output paths containing newlines. This is used for the
+/// `--null` options.
+pub fn print_null_separated<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for path in paths {
+        stdout.write_all(path.as_os_str().as_bytes())?;
+        stdout.write_all(b"\0")?;
+    }
+
+    stdout.flush().context("Could not write to STDOUT")
+}
+
 /// Resolve symlinks in a path, like the `realpath` coreutil, but don't throw any errors of `path`
 /// does not exist, unlike the `realpath` libc function.
 ///
 /// This is used to resolve symlinked directories in the syncing process so the plugin counts are
 /// correct even when one plugin directory contains a symlink to another plugin directory.
 pub fn normalize_path(path: &Path) -> PathBuf {
-    for prefix in path.ancestors() {
-        // If part of `path`s prefix exists, then we'll try to resolve symlinks there
-        if let Ok(normalized_prefix) = fs::canonicalize(&prefix) {
-            return normalized_prefix.join(path.strip_prefix(prefix).unwrap());
+    normalize_path_cached(path, &mut HashMap::new())
+}
+
+/// The same as [normalize_path()], but directories that have already been normalized are looked up
+/// in `cache` instead of being resolved again. `yabridgectl sync` normalizes a lot of paths that
+/// share the same parent directories, and every call to `fs::canonicalize()` is a round trip on
+/// network file systems. Only directories are cached since syncing creates and replaces symlinks
+/// to files, so the last component is resolved separately if it's a symlink. A cache should not be
+/// reused across multiple syncs.
+pub fn normalize_path_cached(path: &Path, cache: &mut HashMap<PathBuf, PathBuf>) -> PathBuf {
+    let normalized_path = normalize_parent_path_cached(path, cache);
+    match fs::symlink_metadata(&normalized_path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::canonicalize(&normalized_path).unwrap_or(normalized_path)
         }
+        _ => normalized_path,
     }
+}
 
-    path.to_owned()
+/// The same as [normalize_path()], but the last component is not resolved, so a symlink to a file
+/// stays a path to that symlink. This is needed for leftover files, since those are often symlinks.
+pub fn normalize_parent_path(path: &Path) -> PathBuf {
+    normalize_parent_path_cached(path, &mut HashMap::new())
+}
+
+/// The implementation for [normalize_parent_path()] and [normalize_path_cached()].
+fn normalize_parent_path_cached(path: &Path, cache: &mut HashMap<PathBuf, PathBuf>) -> PathBuf {
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => {
+            normalize_directory_cached(parent, cache).join(file_name)
+        }
+        // This is either the root directory or a path ending in `..`
+        _ => fs::canonicalize(path).unwrap_or_else(|_| path.to_owned()),
+    }
+}
+
+/// Resolve all symlinks in `directory` for `normalize_parent_path_cached()`. If the directory does
+/// not exist, then its parent directory is normalized instead. The results are stored in `cache`.
+fn normalize_directory_cached(directory: &Path, cache: &mut HashMap<PathBuf, PathBuf>) -> PathBuf {
+    if let Some(normalized_directory) = cache.get(directory) {
+        return normalized_directory.clone();
+    }
+
+    let normalized_directory = match fs::canonicalize(directory) {
+        Ok(normalized_directory) => normalized_directory,
+        Err(_) => normalize_parent_path_cached(directory, cache),
+    };
+    cache.insert(directory.to_owned(), normalized_directory.clone());
+
+    normalized_directory
 }
 
 /// Verify that `yabridge-host.exe` can be found when yabridge is run in a host launched from the
@@ -198,7 +1053,9 @@ pub fn normalize_path(path: &Path) -> PathBuf {
 /// found. Returns `true` if it can be found, or if we the login shell is set to an unknown shell.
 /// In the last case we'll just print a warning since we don't know how to invoke the shell as a
 /// login shell. This is needed when using copies to ensure that yabridge can find the host binaries
-/// when the VST host is launched from the desktop enviornment.
+/// when the VST host is launched from the desktop enviornment. If the login shell can find
+/// `yabridge-host.exe`, then we'll also check the systemd user environment since desktop
+/// environments started through systemd won't use the login shell's environment.
 ///
 /// This is a bit messy, and with yabridge 2.1 automatically searching in `~/.local/share/yabridge`
 /// it's probably not really needed anymore, but it could still be useful in some edge case
@@ -278,7 +1135,36 @@ pub fn verify_path_setup(config: &Config) -> Result<bool> {
                 .env("HOME", env::var("HOME").unwrap_or_default());
 
             match command.stdout(Stdio::null()).stderr(Stdio::null()).status() {
-                Ok(status) if status.success() => Ok(true),
+                // Desktop environments and Wayland compositors started through a systemd user
+                // session don't necessarily source the login shell's profile, so the DAW may
+                // still not be able to find `yabridge-host.exe` in that case
+                Ok(status) if status.success() => match systemd_user_path_contains_host() {
+                    Some(false) => {
+                        eprintln!(
+                            "\n{}",
+                            wrap(&format!(
+                                "Warning: 'yabridge-host.exe' is present in your login shell's \
+                                 search path, but not in your systemd user environment's search \
+                                 path. If your desktop environment or DAW is started through \
+                                 systemd, then yabridge won't be able to run using the copy-based \
+                                 installation method until this is fixed.\n\
+                                 Add '{}' to the {} environment variable in \
+                                 '~/.config/environment.d/', or run '{}' from your login shell's \
+                                 profile. Rerun this command to verify that the variable has been \
+                                 set correctly, and then reboot your system to complete the \
+                                 setup.\n\
+                                 \n\
+                                 https://github.com/robbert-vdh/yabridge#troubleshooting-common-issues",
+                                config.files()?.libyabridge_vst2.parent().unwrap().display(),
+                                "PATH".bright_white(),
+                                "systemctl --user import-environment PATH".bright_white()
+                            ))
+                        );
+
+                        Ok(false)
+                    }
+                    Some(true) | None => Ok(true),
+                },
                 Ok(_) => {
                     eprintln!(
                         "\n{}",
@@ -322,11 +1208,35 @@ pub fn verify_path_setup(config: &Config) -> Result<bool> {
     }
 }
 
-/// Verify that the installed versions of Wine and yabridge will work together properly. This check
-/// is only performed once per combination of Wine and yabridge, and we'll update the config with
-/// the versions we just tested if the check succeeds. Will return `Err` values if either Wine or
-/// `yabridge-host.exe` can't be run.
-pub fn verify_wine_setup(config: &mut Config) -> Result<()> {
+/// Check whether `yabridge-host.exe` can be found in the search path of the systemd user
+/// environment. Applications started through a systemd user session will inherit this environment
+/// instead of the login shell's environment. Returns `None` if `systemctl` is not available or if
+/// the environment doesn't contain a search path at all.
+fn systemd_user_path_contains_host() -> Option<bool> {
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .arg("show-environment")
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let environment = String::from_utf8(output.stdout).ok()?;
+    let search_path = environment
+        .lines()
+        .find_map(|line| line.strip_prefix("PATH="))?;
+
+    Some(
+        env::split_paths(search_path)
+            .any(|directory| directory.join(YABRIDGE_HOST_EXE_NAME).is_executable()),
+    )
+}
+
+/// Get the output of `wine --version`, minus the trailing newline. Will return an error if Wine
+/// could not be run.
+pub fn get_wine_version() -> Result<String> {
     // These winelib scripts respect `$WINELOADER`, so we'll do the same thing
     let wine_binary = env::var("WINELOADER").unwrap_or_else(|_| String::from("wine"));
     let wine_version_output = Command::new(&wine_binary)
@@ -348,6 +1258,22 @@ pub fn verify_wine_setup(config: &mut Config) -> Result<()> {
         )
     })?;
 
+    Ok(wine_version)
+}
+
+/// Verify that the installed versions of Wine and yabridge will work together properly. This check
+/// is only performed once per combination of Wine and yabridge, and we'll update the config with
+/// the versions we just tested if the check succeeds. `host_architectures` contains the
+/// architectures of the Wine plugin hosts needed for the plugins that have been set up, as returned
+/// by `verify_wine_prefix_architectures()`. If that's empty, then we'll check the plugin host for
+/// the default Wine prefix instead. Will return `Err` values if either Wine or `yabridge-host.exe`
+/// can't be run. Returns `false` if one of the plugin hosts is missing or failed to run, after
+/// printing a warning.
+pub fn verify_wine_setup(
+    config: &mut Config,
+    host_architectures: &BTreeSet<LibArchitecture>,
+) -> Result<bool> {
+    let wine_version = get_wine_version()?;
     let files = config
         .files()
         .context(format!("Could not find '{}'", YABRIDGE_HOST_EXE_NAME))?;
@@ -356,33 +1282,188 @@ pub fn verify_wine_setup(config: &mut Config) -> Result<()> {
     // generated shell script. If somehow only the 32-bit verison is installed, we'll just hash that
     // one.
     let yabridge_host_hash = hash_file(
-        &files
+        files
             .yabridge_host_exe_so
-            .or(files.yabridge_host_32_exe_so)
+            .as_ref()
+            .or(files.yabridge_host_32_exe_so.as_ref())
             .with_context(|| format!("Could not locate '{}.so'", YABRIDGE_HOST_EXE_NAME))?,
     )?;
 
+    // It could be that the default Wine prefix was created with `WINEARCH=win32` set. In that case
+    // we should run the 32-bit `yabridge-host.exe` since the 64-bit verison won't be able to run.
+    let check_default_prefix = host_architectures.is_empty();
+    let host_architectures: Vec<LibArchitecture> = if check_default_prefix {
+        vec![get_default_wine_prefix_arch()]
+    } else {
+        host_architectures.iter().copied().collect()
+    };
+
     // Since these checks can take over a second if wineserver isn't already running we'll only
     // perform them when something has changed
     let current_config = KnownConfig {
         wine_version: wine_version.clone(),
         yabridge_host_hash,
+        host_architectures: host_architectures.clone(),
     };
     if config.last_known_config.as_ref() == Some(&current_config) {
-        return Ok(());
+        return Ok(true);
     }
 
-    // It could be that the default Wine prefix was created with `WINEARCH=win32` set. In that case
-    // we should run the 32-bit `yabridge-host.exe` since the 64-bit verison won't be able to run.
-    let host_binary_path = match get_default_wine_prefix_arch() {
-        LibArchitecture::Lib32 => files
-            .yabridge_host_32_exe
-            .with_context(|| format!("Could not find '{}'", YABRIDGE_HOST_32_EXE_NAME)),
-        LibArchitecture::Lib64 => files
-            .yabridge_host_exe
-            .with_context(|| format!("Could not find '{}'", YABRIDGE_HOST_EXE_NAME)),
-    }?;
+    let mut success = true;
+    for architecture in host_architectures {
+        let host_binary_path = match yabridge_host_path(&files, architecture) {
+            Ok(path) => path,
+            Err(err) if check_default_prefix => return Err(err),
+            // `verify_wine_prefix_architectures()` will have already warned about this
+            Err(_) => {
+                success = false;
+                continue;
+            }
+        };
+
+        success &= verify_yabridge_host(host_binary_path, &wine_version)?;
+    }
+
+    if success {
+        config.last_known_config = Some(current_config);
+        config.write()?;
+    }
+
+    Ok(success)
+}
+
+/// The prefix of the version marker embedded in yabridge's binaries. Both `libyabridge-*.so` and
+/// the Wine plugin hosts' `.exe.so` files contain this marker followed by the output of `git
+/// describe` or the Meson project version from when yabridge was built.
+const YABRIDGE_VERSION_MARKER: &str = "yabridge version: ";
 
+/// Find the version embedded in one of yabridge's binaries using the marker described above, like
+/// `3.4.0` or `3.4.0-12-g1a2b3c4`. Returns `None` if the file doesn't contain the marker, which is
+/// the case for versions of yabridge built before the marker was added.
+pub fn get_embedded_version(path: &Path) -> Result<Option<String>> {
+    let bytes = fs::read(path).with_context(|| format!("Could not read '{}'", path.display()))?;
+
+    Ok(bytes
+        .split(|&byte| byte == 0)
+        .filter(|string| string.len() <= 128)
+        .filter_map(|string| std::str::from_utf8(string).ok())
+        .filter_map(|string| string.strip_prefix(YABRIDGE_VERSION_MARKER))
+        .find(|version| is_yabridge_version(version))
+        .map(String::from))
+}
+
+/// Whether `version` looks like a version string produced by `git describe`, with an optional
+/// `-<commits>-g<hash>` and `-dirty` suffix.
+fn is_yabridge_version(version: &str) -> bool {
+    let is_number = |part: &str| !part.is_empty() && part.bytes().all(|c| c.is_ascii_digit());
+
+    let version = version.strip_suffix("-dirty").unwrap_or(version);
+    let mut parts = version.splitn(2, '-');
+    let release = parts.next().unwrap_or_default();
+    let release_parts: Vec<&str> = release.split('.').collect();
+    if release_parts.len() != 3 || !release_parts.iter().all(|part| is_number(part)) {
+        return false;
+    }
+
+    match parts.next() {
+        None => true,
+        Some(suffix) => {
+            let mut suffix_parts = suffix.splitn(2, '-');
+            let commits = suffix_parts.next().unwrap_or_default();
+            let hash = suffix_parts.next().unwrap_or_default();
+
+            is_number(commits)
+                && hash.len() > 1
+                && hash.starts_with('g')
+                && hash[1..].bytes().all(|c| c.is_ascii_hexdigit())
+        }
+    }
+}
+
+/// Check whether `libyabridge-{vst2,vst3}.so` and the Wine plugin hosts come from the same version
+/// of yabridge by comparing the versions embedded in those files. A partial upgrade can leave some
+/// of these files behind, and plugins may then crash in subtle ways. Returns a description of the
+/// first mismatch found, if any. Files without a version marker are skipped, and files that can't
+/// be read are skipped with a warning.
+pub fn yabridge_version_mismatch(files: &YabridgeFiles) -> Option<String> {
+    let read_version = |path: &Path| match get_embedded_version(path) {
+        Ok(version) => version,
+        Err(err) => {
+            eprintln!(
+                "{}",
+                wrap(&format!(
+                    "Warning: Could not check the version of '{}'. {:#}",
+                    display_path(path),
+                    err
+                ))
+            );
+            None
+        }
+    };
+
+    let libraries: Vec<(&Path, String)> = std::iter::once(files.libyabridge_vst2.as_path())
+        .chain(
+            files
+                .libyabridge_vst3
+                .as_ref()
+                .map(|(path, _)| path.as_path()),
+        )
+        .filter_map(|path| Some((path, read_version(path)?)))
+        .collect();
+    let hosts: Vec<(&Path, String)> = files
+        .yabridge_host_exe_so
+        .iter()
+        .chain(files.yabridge_host_32_exe_so.iter())
+        .map(|path| path.as_path())
+        .filter(|path| path.exists())
+        .filter_map(|path| Some((path, read_version(path)?)))
+        .collect();
+
+    libraries.iter().find_map(|(library, library_version)| {
+        hosts
+            .iter()
+            .find(|(_, host_version)| host_version != library_version)
+            .map(|(host, host_version)| {
+                format!(
+                    "'{}' is from yabridge {}, but '{}' is from yabridge {}",
+                    display_path(library),
+                    library_version,
+                    display_path(host),
+                    host_version
+                )
+            })
+    })
+}
+
+/// Get the path to the Wine plugin host yabridge uses for plugins and Wine prefixes with
+/// `architecture`. Returns an error if that plugin host could not be found.
+pub fn yabridge_host_path(files: &YabridgeFiles, architecture: LibArchitecture) -> Result<&Path> {
+    let (host_binary_name, host_binary_path) = match architecture {
+        LibArchitecture::Lib32 => (YABRIDGE_HOST_32_EXE_NAME, &files.yabridge_host_32_exe),
+        LibArchitecture::Lib64 => (YABRIDGE_HOST_EXE_NAME, &files.yabridge_host_exe),
+    };
+
+    host_binary_path
+        .as_deref()
+        .with_context(|| format!("Could not find '{}'", host_binary_name))
+}
+
+/// Check whether the Wine plugin host at `host_binary_path` can run using the current version of
+/// Wine. If it can't, then a warning explaining the most common causes is printed and this returns
+/// `false`. Returns an error if the plugin host could not be started at all.
+pub fn verify_yabridge_host(host_binary_path: &Path, wine_version: &str) -> Result<bool> {
+    match yabridge_host_error(host_binary_path)? {
+        Some(error) => {
+            print_yabridge_host_error(&error, wine_version);
+            Ok(false)
+        }
+        None => Ok(true),
+    }
+}
+
+/// Run `yabridge-host.exe` or `yabridge-host-32.exe` without any arguments and check whether it
+/// prints its usage string. Returns the last error Wine printed if it did not.
+pub fn yabridge_host_error(host_binary_path: &Path) -> Result<Option<String>> {
     let output = Command::new(&host_binary_path)
         .output()
         .with_context(|| format!("Could not run '{}'", host_binary_path.display()))?;
@@ -396,12 +1477,10 @@ pub fn verify_wine_setup(config: &mut Config) -> Result<()> {
     //
     // I don't know if it's possible to differentiate between the second and the third case, so
     // we'll always assume it's Wine that's outdated.
-    let mut success = false;
     let mut last_error: Option<&str> = None;
     for line in stderr.lines() {
         if line.starts_with(YABRIDGE_HOST_EXPECTED_OUTPUT_PREFIX) {
-            success = true;
-            break;
+            return Ok(None);
         }
 
         // Ignore fixme messages here, since those can be produced by wineserver even after the
@@ -411,35 +1490,194 @@ pub fn verify_wine_setup(config: &mut Config) -> Result<()> {
         }
     }
 
-    if success {
-        config.last_known_config = Some(current_config);
-        config.write()?;
-    } else {
-        eprintln!(
-            "\n{}",
-            wrap(&format!(
-                "Warning: Could not run '{yabridge_host}'. Wine reported the following error:\n\
-                 \n\
-                 {error}\n\
-                 \n\
-                 Make sure that you have downloaded the correct version of yabridge for your distro.\n\
-                 This can also happen when using a version of Wine that's not compatible with this \
-                 version of yabridge, in which case you'll need to upgrade Wine. Your current Wine \
-                 version is '{wine_version}'. \
-                 See the link below for instructions on how to upgrade your installation of Wine.\n\
+    Ok(Some(last_error.unwrap_or("<no_output>").to_owned()))
+}
+
+/// Print a warning containing the error returned by `yabridge_host_error()`, along with
+/// instructions on how to fix the most common causes of `yabridge-host.exe` not being able to run.
+pub fn print_yabridge_host_error(error: &str, wine_version: &str) {
+    eprintln!(
+        "\n{}",
+        wrap(&format!(
+            "Warning: Could not run '{yabridge_host}'. Wine reported the following error:\n\
+             \n\
+             {error}\n\
                  \n\
-                 https://github.com/robbert-vdh/yabridge#troubleshooting-common-issues",
-                yabridge_host = "yabridge-host.exe".bright_white(),
-                error = last_error.unwrap_or("<no_output>").bright_white(),
-                wine_version = wine_version
-                    .strip_prefix("wine-")
-                    .unwrap_or(&wine_version)
-                    .bright_white(),
-            ))
-        )
+             Make sure that you have downloaded the correct version of yabridge for your distro.\n\
+             This can also happen when using a version of Wine that's not compatible with this \
+             version of yabridge, in which case you'll need to upgrade Wine. Your current Wine \
+             version is '{wine_version}'. \
+             See the link below for instructions on how to upgrade your installation of Wine.\n\
+             \n\
+             https://github.com/robbert-vdh/yabridge#troubleshooting-common-issues",
+            yabridge_host = "yabridge-host.exe".bright_white(),
+            error = error.bright_white(),
+            wine_version = wine_version
+                .strip_prefix("wine-")
+                .unwrap_or(wine_version)
+                .bright_white(),
+        ))
+    )
+}
+
+/// Verify that `plugin` can actually be loaded by the Wine plugin host from within the Wine prefix
+/// it's installed in. `verify_wine_setup()` only checks whether `yabridge-host.exe` can run at all,
+/// but a plugin may still fail to load because of problems specific to its prefix, like missing
+/// runtime libraries. We'll run the plugin host just like yabridge would, but with a socket
+/// endpoint that doesn't exist. The plugin host will load the plugin library before it tries to
+/// connect to those sockets, so if we get a socket error then we know that loading the plugin
+/// succeeded. Returns `false` and prints a warning containing Wine's output if the plugin could not
+/// be loaded.
+pub fn verify_wine_prefix(config: &Config, files: &YabridgeFiles, plugin: &Plugin) -> Result<bool> {
+    let (plugin_type, plugin_path, architecture) = match plugin {
+        Plugin::Vst2(plugin) => ("vst2", plugin.path.as_path(), plugin.architecture),
+        Plugin::Vst3(module) => ("vst3", module.original_path(), module.architecture),
+    };
+    // Plugins in a 32-bit Wine prefix can only be loaded through the 32-bit plugin host
+    let wine_prefix = get_plugin_wine_prefix(config, plugin_path);
+    let prefix_architecture = match &wine_prefix {
+        Some(prefix) => get_wine_prefix_arch(prefix),
+        None => get_default_wine_prefix_arch(),
+    };
+    let host_binary_path = match (architecture, prefix_architecture) {
+        (LibArchitecture::Lib32, _) | (_, LibArchitecture::Lib32) => {
+            yabridge_host_path(files, LibArchitecture::Lib32)
+        }
+        (LibArchitecture::Lib64, LibArchitecture::Lib64) => {
+            yabridge_host_path(files, LibArchitecture::Lib64)
+        }
+    }?;
+
+    let mut command = Command::new(host_binary_path);
+    if let Some(prefix) = &wine_prefix {
+        command.env("WINEPREFIX", prefix);
     }
 
-    Ok(())
+    let nonexistent_endpoint =
+        env::temp_dir().join(format!("yabridgectl-check-{}", std::process::id()));
+    let output = command
+        .arg(plugin_type)
+        .arg(plugin_path)
+        .arg(&nonexistent_endpoint)
+        .arg(std::process::id().to_string())
+        .output()
+        .with_context(|| format!("Could not run '{}'", host_binary_path.display()))?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    // The line after this one contains the actual error. If the plugin loaded successfully, then
+    // this will be an error about not being able to connect to the sockets.
+    let initialization_error = stderr
+        .lines()
+        .skip_while(|line| !line.starts_with("Error while initializing the Wine plugin host"))
+        .nth(1);
+    let load_error = match initialization_error {
+        Some(error)
+            if error.starts_with("Could not load")
+                || error.starts_with("Could not find a valid VST entry point") =>
+        {
+            Some(error)
+        }
+        Some(_) => None,
+        // If the plugin host didn't print an initialization error at all, then it didn't even get
+        // to the point where it could load the plugin
+        None => Some(
+            stderr
+                .lines()
+                .rev()
+                .find(|line| line.get(5..10) != Some("fixme"))
+                .unwrap_or("<no_output>"),
+        ),
+    };
+
+    let wine_prefix_str = wine_prefix
+        .map(|prefix| prefix.display().to_string())
+        .unwrap_or_else(|| String::from("~/.wine"));
+    match load_error {
+        None => Ok(true),
+        Some(error) => {
+            eprintln!(
+                "\n{}",
+                wrap(&format!(
+                    "Warning: Could not load '{plugin}' in the Wine prefix at '{prefix}'. The \
+                     Wine plugin host reported the following error:\n\
+                     \n\
+                     {error}\n\
+                     \n\
+                     Plugins in this prefix will likely also fail to load in your DAW. This is \
+                     often caused by missing runtime libraries or by a broken Wine prefix.",
+                    plugin = plugin_path.display(),
+                    prefix = wine_prefix_str.bright_white(),
+                    error = error.bright_white(),
+                ))
+            );
+
+            Ok(false)
+        }
+    }
+}
+
+/// Determine which Wine plugin hosts are needed to run the plugins at `plugins` in their Wine
+/// prefixes, and verify that those have been installed. 32-bit plugins and plugins installed in a
+/// Wine prefix created with `WINEARCH=win32` can only be run with `yabridge-host-32.exe`, and
+/// 64-bit plugins can't be run in 32-bit Wine prefixes at all. Prints a warning for every plugin
+/// that can't be run. Returns the architectures of the plugin hosts that are needed, so they can be
+/// checked with `verify_wine_setup()`.
+pub fn verify_wine_prefix_architectures<'a>(
+    config: &Config,
+    files: &YabridgeFiles,
+    plugins: impl IntoIterator<Item = (&'a Path, LibArchitecture)>,
+) -> BTreeSet<LibArchitecture> {
+    let default_prefix = default_wine_prefix();
+    let mut prefix_architectures: HashMap<PathBuf, LibArchitecture> = HashMap::new();
+
+    let mut host_architectures = BTreeSet::new();
+    for (plugin_path, plugin_architecture) in plugins {
+        let prefix =
+            get_plugin_wine_prefix(config, plugin_path).unwrap_or_else(|| default_prefix.clone());
+        let prefix_architecture = *prefix_architectures
+            .entry(prefix.clone())
+            .or_insert_with(|| get_wine_prefix_arch(&prefix));
+        let host_architecture = match (plugin_architecture, prefix_architecture) {
+            (LibArchitecture::Lib64, LibArchitecture::Lib64) => LibArchitecture::Lib64,
+            (LibArchitecture::Lib32, _) => LibArchitecture::Lib32,
+            (LibArchitecture::Lib64, LibArchitecture::Lib32) => {
+                eprintln!(
+                    "\n{}",
+                    wrap(&format!(
+                        "Warning: '{}' is a 64-bit plugin, but it's installed in a 32-bit Wine \
+                         prefix at '{}'. 64-bit plugins can't be run in 32-bit Wine prefixes.",
+                        display_path(plugin_path),
+                        display_path(&prefix),
+                    ))
+                );
+
+                continue;
+            }
+        };
+        let (host_binary_name, host_binary_path) = match host_architecture {
+            LibArchitecture::Lib32 => (YABRIDGE_HOST_32_EXE_NAME, &files.yabridge_host_32_exe),
+            LibArchitecture::Lib64 => (YABRIDGE_HOST_EXE_NAME, &files.yabridge_host_exe),
+        };
+
+        if host_binary_path.is_none() {
+            eprintln!(
+                "\n{}",
+                wrap(&format!(
+                    "Warning: '{}' is a {} plugin installed in a {} Wine prefix at '{}', but '{}' \
+                     could not be found. yabridge won't be able to load this plugin.",
+                    display_path(plugin_path),
+                    plugin_architecture,
+                    prefix_architecture,
+                    display_path(&prefix),
+                    host_binary_name.bright_white()
+                ))
+            );
+        }
+
+        host_architectures.insert(host_architecture);
+    }
+
+    host_architectures
 }
 
 /// Wrap a long paragraph of text to terminal width, or 80 characters if the width of the terminal
@@ -449,3 +1687,322 @@ pub fn wrap(text: &str) -> String {
 
     wrapper.fill(text)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            relative_path(Path::new("/a/b/c.so"), Path::new("/a/b")),
+            Path::new("c.so")
+        );
+        assert_eq!(
+            relative_path(Path::new("/a/b/c.so"), Path::new("/a/d/e")),
+            Path::new("../../b/c.so")
+        );
+
+        // Paths that only share the root directory still get a relative path
+        assert_eq!(
+            relative_path(Path::new("/home/user/plugin.dll"), Path::new("/opt/vst3")),
+            Path::new("../../home/user/plugin.dll")
+        );
+        assert_eq!(
+            relative_path(Path::new("/a/b"), Path::new("/a/b")),
+            Path::new(".")
+        );
+        assert_eq!(
+            relative_path(Path::new("/a"), Path::new("/a/b/c")),
+            Path::new("../..")
+        );
+
+        // Relative paths can't be compared, so `path` is returned as is
+        assert_eq!(
+            relative_path(Path::new("b/c.so"), Path::new("/a")),
+            Path::new("b/c.so")
+        );
+        assert_eq!(
+            relative_path(Path::new("/a/b/c.so"), Path::new("a")),
+            Path::new("/a/b/c.so")
+        );
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(
+            parse_duration("2w").unwrap(),
+            Duration::from_secs(2 * 7 * 24 * 60 * 60)
+        );
+
+        // A unit is always required, and it has to be one of the supported units
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("5x").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("w").is_err());
+
+        // Both the amount itself and the number of seconds have to fit in a `u64`
+        assert!(parse_duration("18446744073709551616s").is_err());
+        assert!(parse_duration("18446744073709551615s").is_ok());
+        assert!(parse_duration("30000000000000000w").is_err());
+    }
+
+    #[test]
+    fn normalize_symlinked_paths() {
+        let temp_dir =
+            env::temp_dir().join(format!("yabridgectl-test-normalize-{}", std::process::id()));
+        let real_dir = temp_dir.join("real");
+        fs::create_dir_all(&real_dir).unwrap();
+        fs::write(real_dir.join("plugin.so"), "").unwrap();
+        std::os::unix::fs::symlink(&real_dir, temp_dir.join("linked")).unwrap();
+        std::os::unix::fs::symlink(real_dir.join("plugin.so"), temp_dir.join("linked.so")).unwrap();
+        // The temporary directory itself may also contain symlinks
+        let real_dir = fs::canonicalize(&real_dir).unwrap();
+        let temp_dir = fs::canonicalize(&temp_dir).unwrap();
+
+        // Every component is resolved, including the last one
+        assert_eq!(normalize_path(&temp_dir.join("linked")), real_dir);
+        assert_eq!(
+            normalize_path(&temp_dir.join("linked/plugin.so")),
+            real_dir.join("plugin.so")
+        );
+        assert_eq!(
+            normalize_path(&temp_dir.join("linked.so")),
+            real_dir.join("plugin.so")
+        );
+        assert_eq!(
+            normalize_path(&temp_dir.join("linked/does/not/exist.so")),
+            real_dir.join("does/not/exist.so")
+        );
+
+        // The cached version should behave the same, also when the directories are already cached
+        let mut cache = HashMap::new();
+        for _ in 0..2 {
+            assert_eq!(
+                normalize_path_cached(&temp_dir.join("linked"), &mut cache),
+                real_dir
+            );
+            assert_eq!(
+                normalize_path_cached(&temp_dir.join("linked/plugin.so"), &mut cache),
+                real_dir.join("plugin.so")
+            );
+            assert_eq!(
+                normalize_path_cached(&temp_dir.join("linked.so"), &mut cache),
+                real_dir.join("plugin.so")
+            );
+        }
+
+        // Except for with `normalize_parent_path()`
+        assert_eq!(
+            normalize_parent_path(&temp_dir.join("linked")),
+            temp_dir.join("linked")
+        );
+        assert_eq!(
+            normalize_parent_path(&temp_dir.join("linked.so")),
+            temp_dir.join("linked.so")
+        );
+        assert_eq!(
+            normalize_parent_path(&temp_dir.join("linked/plugin.so")),
+            real_dir.join("plugin.so")
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// The RVA the single section in the binaries created by `pe_fixture()` is mapped at.
+    const SECTION_RVA: u32 = 0x1000;
+
+    /// Build a minimal PE32+ binary with a single section containing `section_data`, mapped at
+    /// `SECTION_RVA`. `data_directories` contains the RVAs for the first data directories.
+    fn pe_fixture(data_directories: &[u32], section_data: &[u8]) -> Vec<u8> {
+        let mut binary = vec![0u8; 0x200];
+        binary[0..2].copy_from_slice(b"MZ");
+        put_u32(&mut binary, 0x3C, 0x40); // e_lfanew
+        binary[0x40..0x44].copy_from_slice(b"PE\0\0");
+        put_u16(&mut binary, 0x44, 0x8664); // Machine
+        put_u16(&mut binary, 0x46, 1); // NumberOfSections
+        put_u16(&mut binary, 0x54, 0xF0); // SizeOfOptionalHeader
+        put_u16(&mut binary, 0x58, 0x20B); // PE32+ magic
+        put_u32(&mut binary, 0xC4, 16); // NumberOfRvaAndSizes
+        for (i, rva) in data_directories.iter().enumerate() {
+            put_u32(&mut binary, 0xC8 + i * 8, *rva);
+            put_u32(&mut binary, 0xC8 + i * 8 + 4, section_data.len() as u32);
+        }
+
+        // The section table directly follows the optional header
+        put_u32(&mut binary, 0x148 + 8, section_data.len() as u32); // VirtualSize
+        put_u32(&mut binary, 0x148 + 12, SECTION_RVA); // VirtualAddress
+        put_u32(&mut binary, 0x148 + 16, section_data.len() as u32); // SizeOfRawData
+        put_u32(&mut binary, 0x148 + 20, 0x200); // PointerToRawData
+        binary.extend_from_slice(section_data);
+
+        binary
+    }
+
+    /// A section containing an export directory with a single named export. `names_rva` is the
+    /// RVA of the export name pointer table, which normally points to the table at offset `0x28`.
+    fn export_section(names_rva: u32) -> Vec<u8> {
+        let mut section = vec![0u8; 0x30];
+        put_u32(&mut section, 24, 1); // NumberOfNames
+        put_u32(&mut section, 32, names_rva); // AddressOfNames
+        put_u32(&mut section, 0x28, SECTION_RVA + 0x30);
+        section.extend_from_slice(b"VSTPluginMain\0");
+
+        section
+    }
+
+    /// A section containing a resource table with a single version resource with the strings
+    /// from `strings`, in the same layout as in `VS_VERSIONINFO`'s `StringTable`.
+    fn version_resource_section(strings: &[(&str, &str)]) -> Vec<u8> {
+        let mut version_info = Vec::new();
+        for (key, value) in strings {
+            let start = version_info.len();
+            version_info.resize(start + 6, 0);
+            version_info.extend(utf16_bytes(key));
+            version_info.resize((version_info.len() + 3) & !3, 0);
+            version_info.extend(utf16_bytes(value));
+            let length = version_info.len() - start;
+            put_u16(&mut version_info, start, length as u16); // wLength
+            put_u16(&mut version_info, start + 2, value.len() as u16 + 1); // wValueLength
+            put_u16(&mut version_info, start + 4, 1); // wType
+            version_info.resize((version_info.len() + 3) & !3, 0);
+        }
+
+        // The type, name, and language directories each contain a single entry with a numeric ID,
+        // followed by the data entry
+        let mut section = vec![0u8; 0x58];
+        for (directory_offset, id, next_offset) in &[
+            (0x00, 16, 0x8000_0018), // RT_VERSION
+            (0x18, 1, 0x8000_0030),
+            (0x30, 0x409, 0x48),
+        ] {
+            put_u16(&mut section, directory_offset + 14, 1); // NumberOfIdEntries
+            put_u32(&mut section, directory_offset + 16, *id);
+            put_u32(&mut section, directory_offset + 20, *next_offset);
+        }
+        put_u32(&mut section, 0x48, SECTION_RVA + 0x58);
+        put_u32(&mut section, 0x4C, version_info.len() as u32);
+        section.extend(version_info);
+
+        section
+    }
+
+    fn utf16_bytes(string: &str) -> Vec<u8> {
+        string
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .flat_map(|c| c.to_le_bytes())
+            .collect()
+    }
+
+    fn put_u16(buffer: &mut [u8], offset: usize, value: u16) {
+        buffer[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(buffer: &mut [u8], offset: usize, value: u32) {
+        buffer[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn parse_pe_exports() {
+        let temp_dir = env::temp_dir().join(format!("yabridgectl-test-pe-{}", std::process::id()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        let fixture = |name: &str, contents: &[u8]| {
+            let path = temp_dir.join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        };
+
+        let plugin = pe_fixture(&[SECTION_RVA], &export_section(SECTION_RVA + 0x28));
+        assert_eq!(
+            get_exported_functions(&fixture("plugin.dll", &plugin)).unwrap(),
+            Some((0x8664, vec![String::from("VSTPluginMain")]))
+        );
+        assert_eq!(
+            get_exported_functions(&fixture("no-exports.dll", &pe_fixture(&[], &[0u8; 0x10])))
+                .unwrap(),
+            Some((0x8664, Vec::new()))
+        );
+
+        // Files that aren't PE32(+) binaries are not an error, but broken headers are
+        let mut not_mz = plugin.clone();
+        not_mz[0..2].copy_from_slice(b"ZM");
+        assert_eq!(
+            get_exported_functions(&fixture("not-mz.dll", &not_mz)).unwrap(),
+            None
+        );
+        assert_eq!(
+            get_exported_functions(&fixture("text.dll", b"Not a plugin")).unwrap(),
+            None
+        );
+        assert!(get_exported_functions(&fixture("truncated.dll", &plugin[..0x100])).is_err());
+        assert!(
+            PeHeaders::read(&mut fs::File::open(temp_dir.join("truncated.dll")).unwrap()).is_err()
+        );
+
+        // Both the export directory and the name pointer table need to be inside of a section
+        assert!(get_exported_functions(&fixture(
+            "bad-export-rva.dll",
+            &pe_fixture(&[0x8000], &export_section(SECTION_RVA + 0x28))
+        ))
+        .is_err());
+        assert!(get_exported_functions(&fixture(
+            "bad-names-rva.dll",
+            &pe_fixture(&[SECTION_RVA], &export_section(0x8000))
+        ))
+        .is_err());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn parse_pe_product_name() {
+        let temp_dir =
+            env::temp_dir().join(format!("yabridgectl-test-version-{}", std::process::id()));
+        fs::create_dir_all(&temp_dir).unwrap();
+        let fixture = |name: &str, contents: &[u8]| {
+            let path = temp_dir.join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        };
+
+        let with_product_name = version_resource_section(&[
+            ("CompanyName", "Some Company"),
+            ("ProductName", "Test Plugin"),
+            ("ProductVersion", "1.0"),
+        ]);
+        assert_eq!(
+            get_product_name(&fixture(
+                "product-name.dll",
+                &pe_fixture(&[0, 0, SECTION_RVA], &with_product_name)
+            ))
+            .unwrap(),
+            Some(String::from("Test Plugin"))
+        );
+
+        let without_product_name = version_resource_section(&[("CompanyName", "Some Company")]);
+        assert_eq!(
+            get_product_name(&fixture(
+                "no-product-name.dll",
+                &pe_fixture(&[0, 0, SECTION_RVA], &without_product_name)
+            ))
+            .unwrap(),
+            None
+        );
+        assert_eq!(
+            get_product_name(&fixture(
+                "no-resources.dll",
+                &pe_fixture(&[], &with_product_name)
+            ))
+            .unwrap(),
+            None
+        );
+        assert_eq!(
+            get_product_name(&fixture("text.dll", b"Not a plugin")).unwrap(),
+            None
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}